@@ -0,0 +1,10 @@
+create table credential (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "profile_id" bigint NOT NULL,
+    "password_hash" varchar(255) NOT NULL,
+
+    constraint fk_profile foreign key(profile_id) references profile(id),
+    constraint uq_credential_profile unique(profile_id)
+);
@@ -0,0 +1,15 @@
+create table refresh_token (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "profile_id" bigint NOT NULL,
+    "token_hash" varchar(64) NOT NULL,
+    "family" varchar(64) NOT NULL,
+    "expires_at" timestamptz(3) NOT NULL,
+    "revoked_at" timestamptz(3),
+
+    constraint fk_profile foreign key(profile_id) references profile(id),
+    constraint uq_refresh_token_hash unique(token_hash)
+);
+
+create index idx_refresh_token_family on refresh_token(family);
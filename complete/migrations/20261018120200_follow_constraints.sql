@@ -0,0 +1,13 @@
+delete from follow f
+    using follow dup
+    where f.follower_id = dup.follower_id
+        and f.following_id = dup.following_id
+        and f.id > dup.id;
+
+delete from follow where follower_id = following_id;
+
+alter table follow
+    add constraint uq_follow unique (follower_id, following_id),
+    add constraint ck_follow_not_self check (follower_id <> following_id);
+
+create index idx_follow_following on follow(following_id);
@@ -0,0 +1,2 @@
+create index idx_message_user_updated on message(user_id, updated_at desc, id desc);
+create index idx_message_broadcast_main on message_broadcast(main_msg_id);
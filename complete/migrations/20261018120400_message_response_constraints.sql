@@ -0,0 +1,4 @@
+alter table message_response
+    add constraint uq_message_response_responding unique (responding_msg_id);
+
+create index idx_message_response_original on message_response(original_msg_id);
@@ -0,0 +1,30 @@
+create table message_like (
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "profile_id" bigint NOT NULL,
+    "message_id" bigint NOT NULL,
+
+    primary key (profile_id, message_id),
+    constraint fk_profile foreign key(profile_id) references profile(id),
+    constraint fk_message foreign key(message_id) references message(id)
+);
+
+create index idx_message_like_message on message_like(message_id, created_at desc, profile_id desc);
+
+-- message.likes is a denormalized count of message_like rows kept in step by trigger
+update message set likes = 0;
+
+create function message_like_count() returns trigger as $$
+begin
+    if tg_op = 'INSERT' then
+        update message set likes = likes + 1 where id = new.message_id;
+        return new;
+    else
+        update message set likes = likes - 1 where id = old.message_id;
+        return old;
+    end if;
+end;
+$$ language plpgsql;
+
+create trigger trg_message_like_count
+    after insert or delete on message_like
+    for each row execute function message_like_count();
@@ -0,0 +1,28 @@
+create function set_updated_at() returns trigger as $$
+begin
+    new.updated_at = now();
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger trg_profile_updated_at before update on profile
+    for each row execute function set_updated_at();
+
+create trigger trg_follow_updated_at before update on follow
+    for each row execute function set_updated_at();
+
+-- only content changes count; the denormalized likes counter must not reorder timelines
+create trigger trg_message_updated_at before update of body, image on message
+    for each row execute function set_updated_at();
+
+create trigger trg_message_response_updated_at before update on message_response
+    for each row execute function set_updated_at();
+
+create trigger trg_message_broadcast_updated_at before update on message_broadcast
+    for each row execute function set_updated_at();
+
+create trigger trg_credential_updated_at before update on credential
+    for each row execute function set_updated_at();
+
+create trigger trg_refresh_token_updated_at before update on refresh_token
+    for each row execute function set_updated_at();
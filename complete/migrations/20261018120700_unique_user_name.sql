@@ -0,0 +1,9 @@
+-- keep the oldest holder of a handle and suffix any case-insensitive duplicates with their id
+update profile p
+    set user_name = left(p.user_name, 50 - length(p.id::text) - 1) || '_' || p.id
+    where exists (
+        select 1 from profile o
+            where lower(o.user_name) = lower(p.user_name) and o.id < p.id
+    );
+
+create unique index uq_profile_user_name_lower on profile (lower(user_name));
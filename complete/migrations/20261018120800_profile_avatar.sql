@@ -0,0 +1,14 @@
+create table profile_avatar (
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "profile_id" bigint NOT NULL,
+    "size" int NOT NULL,
+    "content_type" varchar(50) NOT NULL,
+    "data" bytea NOT NULL,
+
+    primary key (profile_id, size),
+    constraint fk_profile foreign key(profile_id) references profile(id)
+);
+
+create trigger trg_profile_avatar_updated_at before update on profile_avatar
+    for each row execute function set_updated_at();
@@ -0,0 +1,56 @@
+-- Media bytes now live in a MediaStore backend; rows only reference them by key.
+create table media (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "owner_id" bigint NOT NULL,
+    "storage_key" varchar(100) NOT NULL,
+    "content_type" varchar(100) NOT NULL,
+    "byte_size" bigint NOT NULL,
+
+    constraint uq_media_storage_key unique (storage_key),
+    constraint ck_media_byte_size check (byte_size >= 0),
+    constraint fk_owner foreign key(owner_id) references profile(id)
+);
+
+-- Inline bytes can't be copied into an external store from SQL, so they are parked
+-- here until `BackfillLegacyMedia` moves them into the store. An avatar is kept at the
+-- largest size it was rendered at and rendered again at every size from that.
+create table legacy_avatar (
+    "profile_id" bigint primary key,
+    "data" bytea NOT NULL,
+
+    constraint fk_profile foreign key(profile_id) references profile(id)
+);
+insert into legacy_avatar (profile_id, data)
+    select distinct on (profile_id) profile_id, data from profile_avatar
+        order by profile_id, size desc;
+insert into legacy_avatar (profile_id, data)
+    select id, avatar from profile where avatar is not null
+    on conflict (profile_id) do nothing;
+
+create table legacy_message_image (
+    "message_id" bigint primary key,
+    "data" bytea NOT NULL,
+
+    constraint fk_message foreign key(message_id) references message(id)
+);
+insert into legacy_message_image (message_id, data)
+    select id, image from message where image is not null;
+
+delete from profile_avatar;
+alter table profile_avatar
+    drop column "content_type",
+    drop column "data",
+    add column "media_id" bigint NOT NULL,
+    add constraint fk_media foreign key(media_id) references media(id);
+
+alter table profile
+    drop column "avatar",
+    add column "avatar_id" bigint,
+    add constraint fk_avatar_media foreign key(avatar_id) references media(id) on delete set null;
+
+-- the message trigger lists the image column, so it has to be rebuilt without it
+drop trigger trg_message_updated_at on message;
+alter table message drop column "image";
+create trigger trg_message_updated_at before update of body on message
+    for each row execute function set_updated_at();
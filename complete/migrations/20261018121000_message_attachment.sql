@@ -0,0 +1,39 @@
+-- Messages carry up to four ordered image attachments, each with optional alt text.
+-- Images of messages from before are in legacy_message_image until the backfill job
+-- attaches them.
+create table message_attachment (
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "message_id" bigint NOT NULL,
+    "position" smallint NOT NULL,
+    "media_id" bigint NOT NULL,
+    "alt_text" varchar(1000),
+    "width" int NOT NULL,
+    "height" int NOT NULL,
+
+    primary key (message_id, position),
+    constraint uq_message_attachment_media unique (media_id),
+    constraint ck_message_attachment_position check (position between 0 and 3),
+    constraint fk_message foreign key(message_id) references message(id),
+    constraint fk_media foreign key(media_id) references media(id)
+);
+
+-- Attachment descriptors of a message in display order, as returned by the API.
+create function message_attachments(msg_id bigint) returns jsonb
+    language sql stable
+as $$
+    select coalesce(
+        jsonb_agg(jsonb_build_object(
+            'media_id', md.id,
+            'url', '/media/' || md.id,
+            'content_type', md.content_type,
+            'byte_size', md.byte_size,
+            'width', a.width,
+            'height', a.height,
+            'alt_text', a.alt_text
+        ) order by a.position),
+        '[]'::jsonb
+    )
+    from message_attachment a
+        join media md on md.id = a.media_id
+    where a.message_id = msg_id
+$$;
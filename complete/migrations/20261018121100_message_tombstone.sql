@@ -0,0 +1,51 @@
+-- Deleted messages stay behind as tombstones so broadcasts and reply threads that
+-- reference them remain valid. Their content is purged after a retention period.
+alter table message
+    add column "deleted_at" timestamptz(3),
+    add column "purged_at" timestamptz(3);
+
+create index idx_message_deleted on message(deleted_at) where deleted_at is not null and purged_at is null;
+
+-- tombstones have no attachments to show, even before their content is purged
+create or replace function message_attachments(msg_id bigint) returns jsonb
+    language sql stable
+as $$
+    select coalesce(
+        jsonb_agg(jsonb_build_object(
+            'media_id', md.id,
+            'url', '/media/' || md.id,
+            'content_type', md.content_type,
+            'byte_size', md.byte_size,
+            'width', a.width,
+            'height', a.height,
+            'alt_text', a.alt_text
+        ) order by a.position),
+        '[]'::jsonb
+    )
+    from message_attachment a
+        join message m on m.id = a.message_id
+        join media md on md.id = a.media_id
+    where a.message_id = msg_id and m.deleted_at is null
+$$;
+
+-- New replies, broadcasts and likes may not point at a deleted message. Raised as a
+-- foreign key violation since, to clients, the message no longer exists.
+create function ensure_message_not_deleted() returns trigger as $$
+declare
+    target_id bigint := (to_jsonb(new) ->> tg_argv[0])::bigint;
+begin
+    if exists (select 1 from message where id = target_id and deleted_at is not null) then
+        raise foreign_key_violation using message = format('message %s has been deleted', target_id);
+    end if;
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger trg_message_response_not_deleted before insert on message_response
+    for each row execute function ensure_message_not_deleted('original_msg_id');
+
+create trigger trg_message_broadcast_not_deleted before insert on message_broadcast
+    for each row execute function ensure_message_not_deleted('broadcasting_msg_id');
+
+create trigger trg_message_like_not_deleted before insert on message_like
+    for each row execute function ensure_message_not_deleted('message_id');
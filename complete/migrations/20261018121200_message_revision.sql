@@ -0,0 +1,17 @@
+alter table message add column "edited_at" timestamptz(3);
+
+-- Earlier bodies of an edited message; created_at is when that body was replaced.
+create table message_revision (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "message_id" bigint NOT NULL,
+    "body" varchar(140),
+
+    constraint fk_message foreign key(message_id) references message(id)
+);
+
+create index idx_message_revision_message on message_revision(message_id, id desc);
+
+-- Timelines page on updated_at, so an edit must not move a message to the top of them;
+-- edited_at marks the edit instead.
+drop trigger trg_message_updated_at on message;
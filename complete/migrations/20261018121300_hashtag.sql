@@ -0,0 +1,23 @@
+-- Hashtags are stored lowercased, once per name.
+create table hashtag (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "name" varchar(100) NOT NULL,
+
+    constraint uq_hashtag_name unique (name)
+);
+
+-- created_at is when the message started using the tag, which is what trends measure.
+-- An edit drops the tags a message no longer uses and links the ones it added.
+create table message_hashtag (
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "message_id" bigint NOT NULL,
+    "hashtag_id" bigint NOT NULL,
+
+    primary key (message_id, hashtag_id),
+    constraint fk_message foreign key(message_id) references message(id),
+    constraint fk_hashtag foreign key(hashtag_id) references hashtag(id)
+);
+
+create index idx_message_hashtag_hashtag on message_hashtag(hashtag_id, message_id);
+create index idx_message_hashtag_created_at on message_hashtag(created_at, hashtag_id);
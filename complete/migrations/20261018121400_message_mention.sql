@@ -0,0 +1,99 @@
+-- @mentions resolved to profiles when a message is written. Offsets are in characters
+-- and cover the @, so clients can link body[start_offset..end_offset] to the profile.
+create table message_mention (
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "message_id" bigint NOT NULL,
+    "profile_id" bigint NOT NULL,
+    "start_offset" int NOT NULL,
+    "end_offset" int NOT NULL,
+
+    primary key (message_id, start_offset),
+    constraint fk_message foreign key(message_id) references message(id),
+    constraint fk_profile foreign key(profile_id) references profile(id)
+);
+
+create index idx_message_mention_profile on message_mention(profile_id, message_id);
+
+create function message_mentions(msg_id bigint) returns jsonb
+    language sql stable
+as $$
+    select coalesce(
+        jsonb_agg(jsonb_build_object(
+            'start', mm.start_offset,
+            'end', mm.end_offset,
+            'profile_id', mm.profile_id
+        ) order by mm.start_offset),
+        '[]'::jsonb
+    )
+    from message_mention mm
+        join message m on m.id = mm.message_id
+    where mm.message_id = msg_id and m.deleted_at is null
+$$;
+
+-- Mentioned profiles are notified. Notifications group similar events: an event that
+-- arrives while a notification of the same kind about the same message is unread is
+-- folded into it, so several actors make one notification. Once read, the next event
+-- starts a new one.
+create table notification (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "last_event_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "recipient_id" bigint NOT NULL,
+    "kind" varchar(20) NOT NULL,
+    "message_id" bigint,
+    "read_at" timestamptz(3),
+
+    constraint fk_recipient foreign key(recipient_id) references profile(id),
+    constraint fk_message foreign key(message_id) references message(id),
+    constraint ck_notification_kind check (kind in ('mention'))
+);
+
+create unique index uq_notification_unread_group on notification(recipient_id, kind, coalesce(message_id, 0)) where read_at is null;
+create index idx_notification_recipient on notification(recipient_id, last_event_at desc, id desc);
+
+create table notification_actor (
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "notification_id" bigint NOT NULL,
+    "actor_id" bigint NOT NULL,
+
+    primary key (notification_id, actor_id),
+    constraint fk_notification foreign key(notification_id) references notification(id),
+    constraint fk_actor foreign key(actor_id) references profile(id)
+);
+
+create index idx_notification_actor_latest on notification_actor(notification_id, created_at desc);
+
+create function notify(recipient bigint, actor bigint, event_kind varchar, msg_id bigint) returns void as $$
+declare
+    group_id bigint;
+begin
+    if recipient = actor then
+        return;
+    end if;
+
+    insert into notification (recipient_id, kind, message_id) values (recipient, event_kind, msg_id)
+        on conflict (recipient_id, kind, coalesce(message_id, 0)) where read_at is null
+        do update set last_event_at = now()
+        returning id into group_id;
+    insert into notification_actor (notification_id, actor_id) values (group_id, actor)
+        on conflict (notification_id, actor_id) do update set created_at = now();
+end;
+$$ language plpgsql;
+
+-- mentions are resolved again whenever a message is edited, but a profile is only told
+-- about a given message once
+create function notify_message_mention() returns trigger as $$
+begin
+    if not exists (
+        select 1 from notification
+            where recipient_id = new.profile_id and kind = 'mention' and message_id = new.message_id
+    ) then
+        perform notify(new.profile_id, (select user_id from message where id = new.message_id), 'mention', new.message_id);
+    end if;
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger trg_notify_message_mention
+    after insert on message_mention
+    for each row execute function notify_message_mention();
@@ -0,0 +1,59 @@
+-- Follows, likes, replies and broadcasts notify the profile they concern, grouped the
+-- same way as mentions.
+alter table notification
+    drop constraint ck_notification_kind,
+    add constraint ck_notification_kind check (kind in ('follow', 'like', 'reply', 'broadcast', 'mention'));
+
+create function notify_follow() returns trigger as $$
+begin
+    perform notify(new.following_id, new.follower_id, 'follow', null);
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger trg_notify_follow
+    after insert on follow
+    for each row execute function notify_follow();
+
+create function notify_message_like() returns trigger as $$
+begin
+    perform notify((select user_id from message where id = new.message_id), new.profile_id, 'like', new.message_id);
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger trg_notify_message_like
+    after insert on message_like
+    for each row execute function notify_message_like();
+
+create function notify_message_response() returns trigger as $$
+begin
+    perform notify(
+        (select user_id from message where id = new.original_msg_id),
+        (select user_id from message where id = new.responding_msg_id),
+        'reply',
+        new.original_msg_id
+    );
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger trg_notify_message_response
+    after insert on message_response
+    for each row execute function notify_message_response();
+
+create function notify_message_broadcast() returns trigger as $$
+begin
+    perform notify(
+        (select user_id from message where id = new.broadcasting_msg_id),
+        (select user_id from message where id = new.main_msg_id),
+        'broadcast',
+        new.broadcasting_msg_id
+    );
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger trg_notify_message_broadcast
+    after insert on message_broadcast
+    for each row execute function notify_message_broadcast();
@@ -0,0 +1,116 @@
+-- Real-time events, written by triggers in the same transaction as the change they
+-- describe. Streams replay them by id when a client resumes, so `audience` lists the
+-- profiles that may see each one. Events are only kept for a limited time.
+create table event (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "seq" bigint,
+    "txid" xid8 NOT NULL DEFAULT pg_current_xact_id(),
+    "kind" varchar(20) NOT NULL,
+    "audience" bigint[] NOT NULL,
+    "data" jsonb NOT NULL,
+
+    constraint ck_event_kind check (kind in ('message', 'likes', 'notification'))
+);
+
+create index idx_event_audience on event using gin(audience);
+create index idx_event_created_at on event(created_at);
+create unique index uq_event_seq on event(seq);
+create index idx_event_unsequenced on event(id) where seq is null;
+
+-- seqs come from a sequence rather than max(seq) so they keep going up after expired
+-- events are deleted, past ids that clients and pumps have already seen
+create sequence event_seq;
+
+create function record_event(event_kind varchar, event_audience bigint[], event_data jsonb) returns void as $$
+begin
+    insert into event (kind, audience, data) values (event_kind, event_audience, event_data);
+end;
+$$ language plpgsql;
+
+-- Ids are taken in insert order but become visible in commit order, so a reader paging
+-- by id could pass over an event whose transaction is still open. Readers page by `seq`
+-- instead, which this numbers events with once every transaction that could still add
+-- an earlier one has finished. Only callers of this wait for each other, never writers,
+-- and each numbering commits before the next starts, so seqs become visible in order.
+create function sequence_events() returns bigint as $$
+declare
+    sequenced bigint;
+begin
+    perform pg_advisory_xact_lock(hashtext('event_seq'));
+    with ready as (
+        select id, nextval('event_seq') as seq from (
+            select id from event
+                where seq is null and txid < pg_snapshot_xmin(pg_current_snapshot())
+                order by id
+        ) unsequenced
+    )
+    update event set seq = ready.seq
+        from ready
+        where event.id = ready.id;
+    get diagnostics sequenced = row_count;
+    return sequenced;
+end;
+$$ language plpgsql;
+
+-- The last seq handed out, or 0 before the first. Waiting for the lock means a numbering
+-- in progress has committed, so every event up to the returned seq can be read.
+create function latest_event_seq() returns bigint as $$
+begin
+    perform pg_advisory_xact_lock(hashtext('event_seq'));
+    return (select case when is_called then last_value else 0 end from event_seq);
+end;
+$$ language plpgsql;
+
+-- the author's followers see a new message on their home timeline, as does the author
+create function message_event() returns trigger as $$
+begin
+    perform record_event(
+        'message',
+        array(select follower_id from follow where following_id = new.user_id) || new.user_id,
+        jsonb_build_object('message_id', new.id)
+    );
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger trg_message_event
+    after insert on message
+    for each row execute function message_event();
+
+-- runs after trg_message_like_count, since triggers fire in name order
+create function message_like_event() returns trigger as $$
+declare
+    liked message%rowtype;
+begin
+    select * into liked from message where id = coalesce(new.message_id, old.message_id);
+    perform record_event(
+        'likes',
+        array(select follower_id from follow where following_id = liked.user_id) || liked.user_id,
+        jsonb_build_object('message_id', liked.id, 'likes', liked.likes)
+    );
+    return null;
+end;
+$$ language plpgsql;
+
+create trigger trg_message_like_event
+    after insert or delete on message_like
+    for each row execute function message_like_event();
+
+create or replace function notify(recipient bigint, actor bigint, event_kind varchar, msg_id bigint) returns void as $$
+declare
+    group_id bigint;
+begin
+    if recipient = actor then
+        return;
+    end if;
+
+    insert into notification (recipient_id, kind, message_id) values (recipient, event_kind, msg_id)
+        on conflict (recipient_id, kind, coalesce(message_id, 0)) where read_at is null
+        do update set last_event_at = now()
+        returning id into group_id;
+    insert into notification_actor (notification_id, actor_id) values (group_id, actor)
+        on conflict (notification_id, actor_id) do update set created_at = now();
+    perform record_event('notification', array[recipient], jsonb_build_object('notification_id', group_id));
+end;
+$$ language plpgsql;
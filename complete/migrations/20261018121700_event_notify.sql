@@ -0,0 +1,58 @@
+-- Instances LISTEN on these channels instead of polling. Notifications are sent on
+-- commit, so a listener never hears about a row it can't read yet.
+--   event:        the id of a new row in the event table
+--   domain_event: a JSON domain event such as {"type": "follow_created", ...}
+create or replace function record_event(event_kind varchar, event_audience bigint[], event_data jsonb) returns void as $$
+declare
+    event_id bigint;
+begin
+    insert into event (kind, audience, data) values (event_kind, event_audience, event_data)
+        returning id into event_id;
+    perform pg_notify('event', event_id::text);
+end;
+$$ language plpgsql;
+
+create function message_domain_event() returns trigger as $$
+begin
+    perform pg_notify('domain_event', jsonb_build_object(
+        'type', 'message_created',
+        'message_id', new.id,
+        'author_id', new.user_id
+    )::text);
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger trg_message_domain_event
+    after insert on message
+    for each row execute function message_domain_event();
+
+create function follow_domain_event() returns trigger as $$
+begin
+    perform pg_notify('domain_event', jsonb_build_object(
+        'type', 'follow_created',
+        'follower_id', new.follower_id,
+        'following_id', new.following_id
+    )::text);
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger trg_follow_domain_event
+    after insert on follow
+    for each row execute function follow_domain_event();
+
+create function message_like_domain_event() returns trigger as $$
+begin
+    perform pg_notify('domain_event', jsonb_build_object(
+        'type', 'like_added',
+        'message_id', new.message_id,
+        'profile_id', new.profile_id
+    )::text);
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger trg_message_like_domain_event
+    after insert on message_like
+    for each row execute function message_like_domain_event();
@@ -0,0 +1,106 @@
+-- Webhooks receive the domain events that involve their owner as signed POSTs. A
+-- delivery row is queued in the same transaction as the change it describes, so no
+-- event is lost if an instance goes down before the worker gets to it.
+create table webhook (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "owner_id" bigint NOT NULL,
+    "url" varchar(2048) NOT NULL,
+    "secret" varchar(100) NOT NULL,
+    "event_types" varchar(30)[] NOT NULL,
+    -- failed attempts since the last success, across all deliveries
+    "consecutive_failures" integer NOT NULL DEFAULT 0,
+    "disabled_at" timestamptz(3),
+
+    constraint fk_owner foreign key(owner_id) references profile(id),
+    constraint ck_webhook_event_types check (
+        cardinality(event_types) > 0
+        and event_types <@ array['message_created', 'follow_created', 'like_added']::varchar(30)[]
+    )
+);
+
+create index idx_webhook_owner on webhook(owner_id, id);
+
+create trigger trg_webhook_updated_at before update on webhook
+    for each row execute function set_updated_at();
+
+create table webhook_delivery (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "webhook_id" bigint NOT NULL,
+    "event_type" varchar(30) NOT NULL,
+    "payload" jsonb NOT NULL,
+    "status" varchar(10) NOT NULL DEFAULT 'pending',
+    "attempts" integer NOT NULL DEFAULT 0,
+    -- while an attempt is in flight this is pushed out, so another worker only picks
+    -- the delivery up again if the first one died
+    "next_attempt_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "delivered_at" timestamptz(3),
+
+    constraint fk_webhook foreign key(webhook_id) references webhook(id) on delete cascade,
+    constraint ck_webhook_delivery_status check (status in ('pending', 'delivered', 'failed'))
+);
+
+create index idx_webhook_delivery_due on webhook_delivery(next_attempt_at) where status = 'pending';
+create index idx_webhook_delivery_webhook on webhook_delivery(webhook_id, id desc);
+
+create table webhook_delivery_attempt (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "delivery_id" bigint NOT NULL,
+    -- null when no response came back
+    "response_status" integer,
+    "error" varchar(500),
+    "duration_ms" integer NOT NULL,
+
+    constraint fk_delivery foreign key(delivery_id) references webhook_delivery(id) on delete cascade
+);
+
+create index idx_webhook_delivery_attempt_delivery on webhook_delivery_attempt(delivery_id, id);
+
+-- Publishes a domain event to listening instances and queues it for every enabled
+-- webhook subscribed to its type whose owner is one of the profiles `involved`.
+-- Webhooks never see what happens between other profiles, and a write only queues
+-- deliveries for the webhooks of those it involves.
+create function raise_domain_event(event jsonb, involved bigint[]) returns void as $$
+begin
+    perform pg_notify('domain_event', event::text);
+    insert into webhook_delivery (webhook_id, event_type, payload)
+        select id, event->>'type', event from webhook
+            where owner_id = any(involved) and disabled_at is null and event->>'type' = any(event_types);
+end;
+$$ language plpgsql;
+
+create or replace function message_domain_event() returns trigger as $$
+begin
+    perform raise_domain_event(jsonb_build_object(
+        'type', 'message_created',
+        'message_id', new.id,
+        'author_id', new.user_id
+    ), array[new.user_id]);
+    return new;
+end;
+$$ language plpgsql;
+
+create or replace function follow_domain_event() returns trigger as $$
+begin
+    perform raise_domain_event(jsonb_build_object(
+        'type', 'follow_created',
+        'follower_id', new.follower_id,
+        'following_id', new.following_id
+    ), array[new.follower_id, new.following_id]);
+    return new;
+end;
+$$ language plpgsql;
+
+create or replace function message_like_domain_event() returns trigger as $$
+begin
+    perform raise_domain_event(jsonb_build_object(
+        'type', 'like_added',
+        'message_id', new.message_id,
+        'profile_id', new.profile_id
+    ), array[new.profile_id, (select user_id from message where id = new.message_id)]);
+    return new;
+end;
+$$ language plpgsql;
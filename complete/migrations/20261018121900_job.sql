@@ -0,0 +1,42 @@
+-- Background jobs. Workers claim due jobs with `for update skip locked`, so any number
+-- of them, in any number of instances, can share the table without handing out a job
+-- twice. A claimed job is leased: if its worker dies, the job is due again once
+-- `locked_until` passes. Jobs that succeed are deleted; jobs out of attempts stay
+-- behind as `dead` until someone requeues or deletes them.
+create table job (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "kind" varchar(100) NOT NULL,
+    "payload" jsonb NOT NULL,
+    "status" varchar(10) NOT NULL DEFAULT 'pending',
+    "attempts" integer NOT NULL DEFAULT 0,
+    "max_attempts" integer NOT NULL,
+    "run_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "locked_until" timestamptz(3),
+    "last_error" varchar(1000),
+
+    constraint ck_job_status check (status in ('pending', 'running', 'dead')),
+    constraint ck_job_max_attempts check (max_attempts > 0)
+);
+
+create index idx_job_due on job(run_at, id) where status = 'pending';
+create index idx_job_running on job(locked_until) where status = 'running';
+create index idx_job_dead on job(kind, id) where status = 'dead';
+
+create trigger trg_job_updated_at before update on job
+    for each row execute function set_updated_at();
+
+-- wakes idle workers as soon as a job becomes due now
+create function job_enqueued() returns trigger as $$
+begin
+    if new.status = 'pending' and new.run_at <= now() then
+        perform pg_notify('job', new.kind);
+    end if;
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger trg_job_enqueued
+    after insert or update of status on job
+    for each row execute function job_enqueued();
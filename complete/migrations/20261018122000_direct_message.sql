@@ -0,0 +1,58 @@
+-- Who may start a conversation with a profile: anyone, or only profiles it follows.
+alter table profile
+    add column "dm_policy" varchar(20) NOT NULL DEFAULT 'everyone',
+    add constraint ck_profile_dm_policy check (dm_policy in ('everyone', 'following'));
+
+-- Private conversations between two or more profiles. Two profiles share at most one
+-- 1:1 conversation, keyed by the pair of their ids in ascending order; groups can be
+-- started any number of times.
+create table conversation (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "creator_id" bigint NOT NULL,
+    "is_group" boolean NOT NULL,
+    "title" varchar(100),
+    "pair_low_id" bigint,
+    "pair_high_id" bigint,
+    -- the latest message, or when the conversation was started
+    "last_message_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    constraint fk_creator foreign key(creator_id) references profile(id),
+    constraint ck_conversation_pair check (
+        (is_group and pair_low_id is null and pair_high_id is null)
+        or (not is_group and pair_low_id < pair_high_id)
+    )
+);
+
+create unique index uq_conversation_pair on conversation(pair_low_id, pair_high_id) where not is_group;
+
+create trigger trg_conversation_updated_at before update on conversation
+    for each row execute function set_updated_at();
+
+create table conversation_member (
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "conversation_id" bigint NOT NULL,
+    "profile_id" bigint NOT NULL,
+    -- everything up to this message has been read; 0 before reading anything
+    "last_read_message_id" bigint NOT NULL DEFAULT 0,
+
+    primary key (conversation_id, profile_id),
+    constraint fk_conversation foreign key(conversation_id) references conversation(id),
+    constraint fk_profile foreign key(profile_id) references profile(id)
+);
+
+create index idx_conversation_member_profile on conversation_member(profile_id);
+
+create table direct_message (
+    "id" bigserial primary key,
+    "created_at" timestamptz(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "conversation_id" bigint NOT NULL,
+    "sender_id" bigint NOT NULL,
+    "body" varchar(1000) NOT NULL,
+
+    constraint fk_conversation foreign key(conversation_id) references conversation(id),
+    constraint fk_sender foreign key(sender_id) references profile(id)
+);
+
+create index idx_direct_message_conversation on direct_message(conversation_id, id desc);
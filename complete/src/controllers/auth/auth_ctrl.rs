@@ -0,0 +1,185 @@
+use std::sync::Arc;
+use axum::response::{IntoResponse, Response};
+use axum::extract::State;
+use tracing::error;
+use crate::lib::app_state::AppState;
+use crate::lib::password::{hash_password, is_valid_password, verify_password, PASSWORD_MAX_LEN, PASSWORD_MIN_LEN};
+use crate::lib::token::{generate_opaque_token, hash_opaque_token};
+use crate::repository::auth::auth_repo::AuthRepo;
+use crate::repository::repo::Repository;
+use crate::routes::lib::app_response::AppResponse;
+use crate::routes::lib::error::AppErrors;
+use crate::routes::lib::extractors::AppJson;
+use chrono::Utc;
+use super::auth_models::{AuthTokens, LoginCredentials, RefreshTokenRequest, RegisterAccount};
+
+pub async fn register(State(state): State<Arc<AppState>>, AppJson(register_account): AppJson<RegisterAccount>) -> Response {
+    let app_state = Arc::clone(&state);
+    if !is_valid_password(&register_account.password) {
+        return AppErrors::ValidationFailed(
+            format!("password must be between {} and {} characters", PASSWORD_MIN_LEN, PASSWORD_MAX_LEN)
+        ).into_response();
+    }
+
+    let profile = register_account.profile;
+    if let Err(e) = profile.validate() {
+        return e.into_response();
+    }
+
+    let password_hash = match hash_password(register_account.password).await {
+        Ok(hash) => hash,
+        Err(e) => {
+            error!("Error failed hash_password {:?}", e);
+            return AppErrors::InternalServerError.into_response();
+        }
+    };
+
+    match app_state.repo.insert_account(
+        app_state.repo.get_pool(),
+        profile.user_name,
+        profile.full_name,
+        profile.description,
+        profile.region,
+        profile.main_url,
+        password_hash
+    ).await {
+        Ok(entity) => AppResponse::Create(entity).into_response(),
+        Err(e) => {
+            error!("Error failed insert_account {:?}", e);
+            match AppErrors::from(e) {
+                AppErrors::Conflict(_) => AppErrors::Conflict("user name is already taken".to_string()).into_response(),
+                app_error => app_error.into_response()
+            }
+        }
+    }
+}
+
+/// Unknown user names and wrong passwords both produce the same 401.
+pub async fn login(State(state): State<Arc<AppState>>, AppJson(credentials): AppJson<LoginCredentials>) -> Response {
+    let app_state = Arc::clone(&state);
+    let credential = match app_state.repo.select_credential_by_user_name(app_state.repo.get_pool(), &credentials.user_name).await {
+        Ok(credential) => credential,
+        Err(e) => {
+            error!("Error failed select_credential_by_user_name {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    };
+
+    let (profile_id, password_hash) = match credential {
+        Some(credential) => (Some(credential.profile_id), Some(credential.password_hash)),
+        None => (None, None)
+    };
+
+    match (verify_password(credentials.password, password_hash).await, profile_id) {
+        (true, Some(id)) => match issue_tokens(&app_state, id).await {
+            Ok(tokens) => AppResponse::JsonData(tokens).into_response(),
+            Err(e) => e.into_response()
+        },
+        _ => AppErrors::Unauthorized.into_response()
+    }
+}
+
+/// Exchanges a refresh token for a new access/refresh pair. Each refresh token is
+/// single use; presenting one that was already rotated revokes its whole family.
+pub async fn refresh(State(state): State<Arc<AppState>>, AppJson(request): AppJson<RefreshTokenRequest>) -> Response {
+    let app_state = Arc::clone(&state);
+    let pool = app_state.repo.get_pool();
+    let token = match app_state.repo.select_refresh_token(pool, &hash_opaque_token(&request.refresh_token)).await {
+        Ok(Some(token)) => token,
+        Ok(None) => return AppErrors::Unauthorized.into_response(),
+        Err(e) => {
+            error!("Error failed select_refresh_token {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    };
+
+    if token.revoked_at.is_some() {
+        if let Err(e) = app_state.repo.revoke_refresh_token_family(pool, &token.family).await {
+            error!("Error failed revoke_refresh_token_family {:?}", e);
+        }
+        return AppErrors::Unauthorized.into_response();
+    }
+    if token.expires_at <= Utc::now() {
+        return AppErrors::Unauthorized.into_response();
+    }
+
+    let refresh_token = generate_opaque_token();
+    match app_state.repo.rotate_refresh_token(
+        pool,
+        token.id,
+        &hash_opaque_token(&refresh_token),
+        Utc::now() + app_state.tokens.refresh_ttl
+    ).await {
+        Ok(Some(_)) => (),
+        Ok(None) => {
+            if let Err(e) = app_state.repo.revoke_refresh_token_family(pool, &token.family).await {
+                error!("Error failed revoke_refresh_token_family {:?}", e);
+            }
+            return AppErrors::Unauthorized.into_response();
+        }
+        Err(e) => {
+            error!("Error failed rotate_refresh_token {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    }
+
+    match app_state.tokens.issue_access_token(token.profile_id) {
+        Ok(access_token) => AppResponse::JsonData(AuthTokens {
+            access_token,
+            refresh_token,
+            token_type: "Bearer".to_string(),
+            expires_in: app_state.tokens.access_ttl.num_seconds()
+        }).into_response(),
+        Err(e) => {
+            error!("Error failed issue_access_token {:?}", e);
+            AppErrors::InternalServerError.into_response()
+        }
+    }
+}
+
+/// Revokes the presented refresh token along with every token rotated from it.
+pub async fn logout(State(state): State<Arc<AppState>>, AppJson(request): AppJson<RefreshTokenRequest>) -> Response {
+    let app_state = Arc::clone(&state);
+    let pool = app_state.repo.get_pool();
+    match app_state.repo.select_refresh_token(pool, &hash_opaque_token(&request.refresh_token)).await {
+        Ok(Some(token)) => match app_state.repo.revoke_refresh_token_family(pool, &token.family).await {
+            Ok(_) => AppResponse::<()>::Ok.into_response(),
+            Err(e) => {
+                error!("Error failed revoke_refresh_token_family {:?}", e);
+                AppErrors::from(e).into_response()
+            }
+        },
+        Ok(None) => AppErrors::Unauthorized.into_response(),
+        Err(e) => {
+            error!("Error failed select_refresh_token {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// Issues an access token and a refresh token starting a new family.
+async fn issue_tokens(app_state: &AppState, profile_id: i64) -> Result<AuthTokens, AppErrors> {
+    let access_token = app_state.tokens.issue_access_token(profile_id).map_err(|e| {
+        error!("Error failed issue_access_token {:?}", e);
+        AppErrors::InternalServerError
+    })?;
+    let refresh_token = generate_opaque_token();
+    let family = generate_opaque_token();
+    app_state.repo.insert_refresh_token(
+        app_state.repo.get_pool(),
+        profile_id,
+        &hash_opaque_token(&refresh_token),
+        &family,
+        Utc::now() + app_state.tokens.refresh_ttl
+    ).await.map_err(|e| {
+        error!("Error failed insert_refresh_token {:?}", e);
+        AppErrors::from(e)
+    })?;
+
+    Ok(AuthTokens {
+        access_token,
+        refresh_token,
+        token_type: "Bearer".to_string(),
+        expires_in: app_state.tokens.access_ttl.num_seconds()
+    })
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+use crate::controllers::profile::profile_models::CreateProfile;
+
+#[derive(Deserialize)]
+pub struct RegisterAccount {
+    #[serde(flatten)]
+    pub profile: CreateProfile,
+    pub password: String
+}
+
+#[derive(Deserialize)]
+pub struct LoginCredentials {
+    pub user_name: String,
+    pub password: String
+}
+
+#[derive(Deserialize)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AuthTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: String,
+    pub expires_in: i64
+}
@@ -0,0 +1,209 @@
+use std::sync::Arc;
+use axum::response::{IntoResponse, Response};
+use axum::extract::State;
+use tracing::error;
+use crate::lib::app_state::AppState;
+use crate::repository::direct_message::direct_message_models::{ConversationCursor, DirectMessageCursor};
+use crate::repository::direct_message::direct_message_repo::DirectMessageRepo;
+use crate::repository::repo::Repository;
+use crate::routes::lib::app_response::AppResponse;
+use crate::routes::lib::auth_user::AuthUser;
+use crate::routes::lib::error::AppErrors;
+use crate::routes::lib::extractors::{AppJson, AppPath, AppQuery};
+use crate::routes::lib::pagination::{Page, PageQuery};
+use super::direct_message_models::{ConversationPage, MarkConversationRead, NewConversation, NewDirectMessage};
+
+/// Fails unless every one of `recipient_ids` exists and accepts messages from `sender_id`.
+async fn check_dm_recipients(app_state: &AppState, sender_id: i64, recipient_ids: &[i64]) -> Result<(), AppErrors> {
+    let recipients = match app_state.repo.select_dm_recipients(app_state.repo.get_pool(), sender_id, recipient_ids).await {
+        Ok(recipients) => recipients,
+        Err(e) => {
+            error!("Error failed select_dm_recipients {:?}", e);
+            return Err(AppErrors::from(e));
+        }
+    };
+    if let Some(missing) = recipient_ids.iter().find(|&&id| !recipients.iter().any(|r| r.profile_id == id)) {
+        return Err(AppErrors::InvalidReference(format!("profile {} does not exist", missing)));
+    }
+    if recipients.iter().any(|r| !r.accepts) {
+        return Err(AppErrors::Forbidden);
+    }
+    Ok(())
+}
+
+/// Everyone added must accept messages from the caller. Starting a 1:1 conversation
+/// that already exists returns it with a 200 instead of a 201.
+pub async fn start_conversation(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    AppJson(new_conversation): AppJson<NewConversation>
+) -> Response {
+    let app_state = Arc::clone(&state);
+    let pool = app_state.repo.get_pool();
+    let member_ids = match new_conversation.other_members(auth_user.profile_id) {
+        Ok(member_ids) => member_ids,
+        Err(e) => return e.into_response()
+    };
+    if let Err(e) = check_dm_recipients(&app_state, auth_user.profile_id, &member_ids).await {
+        return e.into_response();
+    }
+
+    let (id, created) = match app_state.repo.insert_conversation(pool, auth_user.profile_id, &member_ids, new_conversation.title).await {
+        Ok(inserted) => inserted,
+        Err(e) => {
+            error!("Error failed insert_conversation {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    };
+    match app_state.repo.select_conversation(pool, auth_user.profile_id, id).await {
+        Ok(Some(conversation)) if created => AppResponse::Create(conversation).into_response(),
+        Ok(conversation) => AppResponse::found(conversation),
+        Err(e) => {
+            error!("Error failed select_conversation {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+pub async fn get_conversations(State(state): State<Arc<AppState>>, auth_user: AuthUser, AppQuery(page): AppQuery<PageQuery>) -> Response {
+    let app_state = Arc::clone(&state);
+    let pool = app_state.repo.get_pool();
+    let before = match page.decode_cursor::<ConversationCursor>() {
+        Ok(cursor) => cursor,
+        Err(e) => return e.into_response()
+    };
+
+    let conversations = match app_state.repo.select_conversations(pool, auth_user.profile_id, before, page.page_size() as i64 + 1).await {
+        Ok(conversations) => conversations,
+        Err(e) => {
+            error!("Error failed select_conversations {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    };
+    match app_state.repo.select_unread_direct_message_count(pool, auth_user.profile_id).await {
+        Ok(unread_count) => AppResponse::JsonData(ConversationPage {
+            page: Page::from_rows(conversations, page.page_size(), |c| ConversationCursor {
+                last_message_at: c.last_message_at,
+                id: c.id
+            }),
+            unread_count
+        }).into_response(),
+        Err(e) => {
+            error!("Error failed select_unread_direct_message_count {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// Conversations the caller is not a member of are reported as missing.
+pub async fn get_conversation(State(state): State<Arc<AppState>>, auth_user: AuthUser, AppPath(id): AppPath<i64>) -> Response {
+    let app_state = Arc::clone(&state);
+    match app_state.repo.select_conversation(app_state.repo.get_pool(), auth_user.profile_id, id).await {
+        Ok(conversation) => AppResponse::found(conversation),
+        Err(e) => {
+            error!("Error failed select_conversation {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// In a 1:1 conversation the other member must still accept messages from the caller,
+/// so changing `dm_policy` also closes conversations already started. Group members
+/// agree when the group is started.
+pub async fn send_direct_message(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    AppPath(id): AppPath<i64>,
+    AppJson(new_message): AppJson<NewDirectMessage>
+) -> Response {
+    let app_state = Arc::clone(&state);
+    let pool = app_state.repo.get_pool();
+    if let Err(e) = new_message.validate() {
+        return e.into_response();
+    }
+    let conversation = match app_state.repo.select_conversation(pool, auth_user.profile_id, id).await {
+        Ok(Some(conversation)) => conversation,
+        Ok(None) => return AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed select_conversation {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    };
+    if !conversation.is_group {
+        let recipient_ids = conversation.members.iter()
+            .map(|member| member.profile_id)
+            .filter(|&profile_id| profile_id != auth_user.profile_id)
+            .collect::<Vec<i64>>();
+        if let Err(e) = check_dm_recipients(&app_state, auth_user.profile_id, &recipient_ids).await {
+            return e.into_response();
+        }
+    }
+
+    match app_state.repo.insert_direct_message(pool, id, auth_user.profile_id, &new_message.body).await {
+        Ok(Some(message)) => AppResponse::Create(message).into_response(),
+        Ok(None) => AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed insert_direct_message {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// Pages back through a conversation, newest message first.
+pub async fn get_direct_messages(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    AppPath(id): AppPath<i64>,
+    AppQuery(page): AppQuery<PageQuery>
+) -> Response {
+    let app_state = Arc::clone(&state);
+    let pool = app_state.repo.get_pool();
+    let before = match page.decode_cursor::<DirectMessageCursor>() {
+        Ok(cursor) => cursor,
+        Err(e) => return e.into_response()
+    };
+    match app_state.repo.select_conversation(pool, auth_user.profile_id, id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed select_conversation {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    }
+
+    match app_state.repo.select_direct_messages(pool, id, before, page.page_size() as i64 + 1).await {
+        Ok(messages) => AppResponse::JsonData(
+            Page::from_rows(messages, page.page_size(), |m| DirectMessageCursor { id: m.id })
+        ).into_response(),
+        Err(e) => {
+            error!("Error failed select_direct_messages {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// Responds with the conversation and its remaining `unread_count`.
+pub async fn mark_conversation_read(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    AppPath(id): AppPath<i64>,
+    AppJson(read): AppJson<MarkConversationRead>
+) -> Response {
+    let app_state = Arc::clone(&state);
+    let pool = app_state.repo.get_pool();
+    match app_state.repo.mark_conversation_read(pool, auth_user.profile_id, id, read.message_id).await {
+        Ok(true) => {}
+        Ok(false) => return AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed mark_conversation_read {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    }
+    match app_state.repo.select_conversation(pool, auth_user.profile_id, id).await {
+        Ok(conversation) => AppResponse::found(conversation),
+        Err(e) => {
+            error!("Error failed select_conversation {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
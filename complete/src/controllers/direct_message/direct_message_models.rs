@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use crate::repository::direct_message::direct_message_models::ConversationQueryResult;
+use crate::routes::lib::error::AppErrors;
+use crate::routes::lib::pagination::Page;
+
+// Limits match the `varchar` sizes in the direct message migration.
+pub const DIRECT_MESSAGE_MAX_LEN: usize = 1000;
+pub const CONVERSATION_TITLE_MAX_LEN: usize = 100;
+/// Members of a group conversation besides its creator.
+pub const MAX_CONVERSATION_MEMBERS: usize = 50;
+
+/// Body of `POST /conversations`. One other member makes a 1:1 conversation, more make
+/// a group, which may have a title.
+#[derive(Deserialize)]
+pub struct NewConversation {
+    pub member_ids: Vec<i64>,
+    pub title: Option<String>
+}
+
+impl NewConversation {
+    /// The members to add besides `creator_id`, each once, in the order given.
+    pub fn other_members(&self, creator_id: i64) -> Result<Vec<i64>, AppErrors> {
+        let mut member_ids: Vec<i64> = vec![];
+        for &id in &self.member_ids {
+            if id != creator_id && !member_ids.contains(&id) {
+                member_ids.push(id);
+            }
+        }
+
+        if member_ids.is_empty() {
+            return Err(AppErrors::ValidationFailed("member_ids must name someone besides yourself".to_string()));
+        }
+        if member_ids.len() > MAX_CONVERSATION_MEMBERS {
+            return Err(AppErrors::ValidationFailed(format!("a conversation has at most {} other members", MAX_CONVERSATION_MEMBERS)));
+        }
+        if let Some(title) = &self.title {
+            if member_ids.len() == 1 {
+                return Err(AppErrors::ValidationFailed("only group conversations have a title".to_string()));
+            }
+            if title.trim().is_empty() || title.chars().count() > CONVERSATION_TITLE_MAX_LEN {
+                return Err(AppErrors::ValidationFailed(format!("title must be 1 to {} characters", CONVERSATION_TITLE_MAX_LEN)));
+            }
+        }
+        Ok(member_ids)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewDirectMessage {
+    pub body: String
+}
+
+impl NewDirectMessage {
+    pub fn validate(&self) -> Result<(), AppErrors> {
+        if self.body.trim().is_empty() {
+            return Err(AppErrors::ValidationFailed("body must not be empty".to_string()));
+        }
+        if self.body.chars().count() > DIRECT_MESSAGE_MAX_LEN {
+            return Err(AppErrors::ValidationFailed(format!("body must be at most {} characters", DIRECT_MESSAGE_MAX_LEN)));
+        }
+        Ok(())
+    }
+}
+
+/// Body of `POST /conversations/:id/read`; without `message_id` everything is read.
+#[derive(Deserialize)]
+pub struct MarkConversationRead {
+    pub message_id: Option<i64>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ConversationPage {
+    #[serde(flatten)]
+    pub page: Page<ConversationQueryResult>,
+    /// Unread messages over all conversations, not just this page.
+    pub unread_count: i64
+}
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use axum::response::{IntoResponse, Response};
+use axum::extract::State;
+use tracing::error;
+use crate::controllers::profile::profile_ctrl::ensure_profile_exists;
+use crate::lib::app_state::AppState;
+use crate::repository::follow::follow_repo::FollowRepo;
+use crate::repository::repo::Repository;
+use crate::routes::lib::app_response::AppResponse;
+use crate::routes::lib::auth_user::AuthUser;
+use crate::routes::lib::error::AppErrors;
+use crate::routes::lib::extractors::{AppPath, AppQuery};
+use crate::routes::lib::pagination::{Page, PageQuery};
+
+pub async fn follow_profile(State(state): State<Arc<AppState>>, auth_user: AuthUser, AppPath(id): AppPath<i64>) -> Response {
+    let app_state = Arc::clone(&state);
+    if id == auth_user.profile_id {
+        return AppErrors::ValidationFailed("a profile cannot follow itself".to_string()).into_response();
+    }
+
+    match app_state.repo.insert_follow(app_state.repo.get_pool(), auth_user.profile_id, id).await {
+        Ok(entity) => AppResponse::Create(entity).into_response(),
+        Err(e) => {
+            error!("Error failed insert_follow {:?}", e);
+            match AppErrors::from(e) {
+                AppErrors::InvalidReference(_) => AppErrors::NotFound.into_response(),
+                app_error => app_error.into_response()
+            }
+        }
+    }
+}
+
+pub async fn unfollow_profile(State(state): State<Arc<AppState>>, auth_user: AuthUser, AppPath(id): AppPath<i64>) -> Response {
+    let app_state = Arc::clone(&state);
+    match app_state.repo.delete_follow(app_state.repo.get_pool(), auth_user.profile_id, id).await {
+        Ok(true) => AppResponse::<()>::Ok.into_response(),
+        Ok(false) => AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed delete_follow {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+pub async fn get_followers(State(state): State<Arc<AppState>>, AppPath(id): AppPath<i64>, AppQuery(page): AppQuery<PageQuery>) -> Response {
+    let app_state = Arc::clone(&state);
+    if let Err(e) = ensure_profile_exists(&app_state, id).await {
+        return e.into_response();
+    }
+    let before_follow_id = match page.decode_cursor::<i64>() {
+        Ok(cursor) => cursor,
+        Err(e) => return e.into_response()
+    };
+
+    match app_state.repo.select_followers(app_state.repo.get_pool(), id, before_follow_id, page.page_size() as i64 + 1).await {
+        Ok(rows) => AppResponse::JsonData(Page::from_rows(rows, page.page_size(), |f| f.follow_id)).into_response(),
+        Err(e) => {
+            error!("Error failed select_followers {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+pub async fn get_following(State(state): State<Arc<AppState>>, AppPath(id): AppPath<i64>, AppQuery(page): AppQuery<PageQuery>) -> Response {
+    let app_state = Arc::clone(&state);
+    if let Err(e) = ensure_profile_exists(&app_state, id).await {
+        return e.into_response();
+    }
+    let before_follow_id = match page.decode_cursor::<i64>() {
+        Ok(cursor) => cursor,
+        Err(e) => return e.into_response()
+    };
+
+    match app_state.repo.select_following(app_state.repo.get_pool(), id, before_follow_id, page.page_size() as i64 + 1).await {
+        Ok(rows) => AppResponse::JsonData(Page::from_rows(rows, page.page_size(), |f| f.follow_id)).into_response(),
+        Err(e) => {
+            error!("Error failed select_following {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
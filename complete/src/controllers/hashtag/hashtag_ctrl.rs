@@ -0,0 +1,55 @@
+use std::sync::Arc;
+use axum::response::{IntoResponse, Response};
+use axum::extract::State;
+use tracing::error;
+use crate::lib::app_state::AppState;
+use crate::lib::entities::normalize_hashtag;
+use crate::repository::hashtag::hashtag_repo::HashtagRepo;
+use crate::repository::message::message_models::MessageCursor;
+use crate::repository::message::message_repo::MessageRepo;
+use crate::repository::repo::Repository;
+use crate::routes::lib::app_response::AppResponse;
+use crate::routes::lib::error::AppErrors;
+use crate::routes::lib::extractors::{AppPath, AppQuery};
+use crate::routes::lib::pagination::{Page, PageQuery};
+use super::hashtag_models::TrendsQuery;
+
+/// Messages using a tag, newest first. The tag matches case-insensitively and may
+/// be given with or without its `#`.
+pub async fn get_hashtag_timeline(
+    State(state): State<Arc<AppState>>,
+    AppPath(tag): AppPath<String>,
+    AppQuery(page): AppQuery<PageQuery>
+) -> Response {
+    let app_state = Arc::clone(&state);
+    let Some(hashtag) = normalize_hashtag(&tag) else {
+        return AppErrors::ValidationFailed(format!("{} is not a hashtag", tag)).into_response();
+    };
+    let before = match page.decode_cursor::<MessageCursor>() {
+        Ok(cursor) => cursor,
+        Err(e) => return e.into_response()
+    };
+
+    match app_state.repo.select_messages_by_hashtag(app_state.repo.get_pool(), &hashtag, before, page.page_size() as i64 + 1).await {
+        Ok(messages) => AppResponse::JsonData(Page::from_rows(messages, page.page_size(), |m| MessageCursor {
+            updated_at: m.updated_at,
+            id: m.id
+        })).into_response(),
+        Err(e) => {
+            error!("Error failed select_messages_by_hashtag {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+pub async fn get_trends(State(state): State<Arc<AppState>>, AppQuery(trends): AppQuery<TrendsQuery>) -> Response {
+    let app_state = Arc::clone(&state);
+
+    match app_state.repo.select_trending_hashtags(app_state.repo.get_pool(), trends.window(), trends.limit()).await {
+        Ok(hashtags) => AppResponse::JsonData(hashtags).into_response(),
+        Err(e) => {
+            error!("Error failed select_trending_hashtags {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use chrono::Duration;
+use serde::Deserialize;
+
+pub const DEFAULT_TRENDS_WINDOW_MINUTES: i64 = 60;
+pub const MAX_TRENDS_WINDOW_MINUTES: i64 = 24 * 60;
+pub const DEFAULT_TRENDS_LIMIT: i64 = 10;
+pub const MAX_TRENDS_LIMIT: i64 = 50;
+
+/// `GET /trends` compares the last `window_minutes` with the same span before it.
+#[derive(Deserialize)]
+pub struct TrendsQuery {
+    pub window_minutes: Option<i64>,
+    pub limit: Option<i64>
+}
+
+impl TrendsQuery {
+    pub fn window(&self) -> Duration {
+        Duration::minutes(self.window_minutes.unwrap_or(DEFAULT_TRENDS_WINDOW_MINUTES).clamp(1, MAX_TRENDS_WINDOW_MINUTES))
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_TRENDS_LIMIT).clamp(1, MAX_TRENDS_LIMIT)
+    }
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use axum::response::{IntoResponse, Response};
+use axum::extract::State;
+use tracing::error;
+use crate::lib::app_state::AppState;
+use crate::repository::like::like_models::LikeCursor;
+use crate::repository::like::like_repo::LikeRepo;
+use crate::repository::repo::Repository;
+use crate::routes::lib::app_response::AppResponse;
+use crate::routes::lib::auth_user::AuthUser;
+use crate::routes::lib::error::AppErrors;
+use crate::routes::lib::extractors::{AppPath, AppQuery};
+use crate::routes::lib::pagination::{Page, PageQuery};
+use super::like_models::MessageLikes;
+
+/// Idempotent: liking an already liked message leaves the count unchanged.
+pub async fn like_message(State(state): State<Arc<AppState>>, auth_user: AuthUser, AppPath(id): AppPath<i64>) -> Response {
+    let app_state = Arc::clone(&state);
+    match app_state.repo.insert_like(app_state.repo.get_pool(), auth_user.profile_id, id).await {
+        Ok(likes) => AppResponse::JsonData(MessageLikes { message_id: id, likes, liked: true }).into_response(),
+        Err(e) => {
+            error!("Error failed insert_like {:?}", e);
+            match AppErrors::from(e) {
+                AppErrors::InvalidReference(_) => AppErrors::NotFound.into_response(),
+                app_error => app_error.into_response()
+            }
+        }
+    }
+}
+
+/// Idempotent: unliking a message that is not liked leaves the count unchanged.
+pub async fn unlike_message(State(state): State<Arc<AppState>>, auth_user: AuthUser, AppPath(id): AppPath<i64>) -> Response {
+    let app_state = Arc::clone(&state);
+    match app_state.repo.delete_like(app_state.repo.get_pool(), auth_user.profile_id, id).await {
+        Ok(Some(likes)) => AppResponse::JsonData(MessageLikes { message_id: id, likes, liked: false }).into_response(),
+        Ok(None) => AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed delete_like {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+pub async fn get_message_likes(State(state): State<Arc<AppState>>, AppPath(id): AppPath<i64>, AppQuery(page): AppQuery<PageQuery>) -> Response {
+    let app_state = Arc::clone(&state);
+    let pool = app_state.repo.get_pool();
+    let before = match page.decode_cursor::<LikeCursor>() {
+        Ok(cursor) => cursor,
+        Err(e) => return e.into_response()
+    };
+
+    match app_state.repo.select_like_count(pool, id).await {
+        Ok(Some(_)) => (),
+        Ok(None) => return AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed select_like_count {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    }
+
+    match app_state.repo.select_likes(pool, id, before, page.page_size() as i64 + 1).await {
+        Ok(likes) => AppResponse::JsonData(Page::from_rows(likes, page.page_size(), |like| LikeCursor {
+            liked_at: like.liked_at,
+            profile_id: like.profile_id
+        })).into_response(),
+        Err(e) => {
+            error!("Error failed select_likes {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
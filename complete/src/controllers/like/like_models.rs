@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct MessageLikes {
+    pub message_id: i64,
+    pub likes: i32,
+    pub liked: bool
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use tracing::error;
+use crate::lib::app_state::AppState;
+use crate::lib::media_jobs::delete_stored_media;
+use crate::lib::media_store::generate_media_key;
+use crate::repository::media::media_models::MediaQueryResult;
+use crate::repository::media::media_repo::MediaRepo;
+use crate::repository::repo::Repository;
+use crate::routes::lib::error::AppErrors;
+use crate::routes::lib::extractors::AppPath;
+
+/// Media ids always refer to the same bytes, so avatar renditions, which are only ever
+/// replaced by new media, may be cached indefinitely.
+pub const AVATAR_MEDIA_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+/// Attachments disappear when their message is deleted, so caches have to check back
+/// each time; the ETag keeps that to a 304 while the attachment is still there.
+pub const ATTACHMENT_MEDIA_CACHE_CONTROL: &str = "private, no-cache";
+
+pub async fn get_media(State(state): State<Arc<AppState>>, AppPath(id): AppPath<i64>, headers: HeaderMap) -> Response {
+    let app_state = Arc::clone(&state);
+    match app_state.repo.select_media(app_state.repo.get_pool(), id).await {
+        Ok(Some(media)) => {
+            let cache_control = if media.is_avatar { AVATAR_MEDIA_CACHE_CONTROL } else { ATTACHMENT_MEDIA_CACHE_CONTROL };
+            media_response(&app_state, media, &headers, cache_control).await
+        },
+        Ok(None) => AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed select_media {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// Streams a media object from the store. The storage key doubles as the ETag since it
+/// changes whenever the bytes would.
+pub async fn media_response(app_state: &AppState, media: MediaQueryResult, headers: &HeaderMap, cache_control: &str) -> Response {
+    let etag = format!("\"{}\"", media.storage_key);
+    let cache_headers = [
+        (header::CACHE_CONTROL, cache_control.to_string()),
+        (header::ETAG, etag.clone())
+    ];
+    if headers.get(header::IF_NONE_MATCH).and_then(|value| value.to_str().ok()) == Some(etag.as_str()) {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+
+    match app_state.media.get(&media.storage_key).await {
+        Ok(Some(stream)) => (
+            cache_headers,
+            [
+                (header::CONTENT_TYPE, media.content_type),
+                (header::CONTENT_LENGTH, media.byte_size.to_string()),
+                (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string())
+            ],
+            Body::from_stream(stream)
+        ).into_response(),
+        Ok(None) => {
+            error!("Media {} is missing from the store under {}", media.id, media.storage_key);
+            AppErrors::NotFound.into_response()
+        }
+        Err(e) => {
+            error!("Error failed media get {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// Writes `data` to the media store under a new key and records it, returning the media id.
+pub async fn store_media(app_state: &AppState, owner_id: i64, content_type: &str, data: Vec<u8>) -> Result<i64, AppErrors> {
+    let storage_key = generate_media_key();
+    let byte_size = data.len() as i64;
+    if let Err(e) = app_state.media.put(&storage_key, content_type, data).await {
+        error!("Error failed media put {:?}", e);
+        return Err(AppErrors::from(e));
+    }
+
+    match app_state.repo.insert_media(app_state.repo.get_pool(), owner_id, &storage_key, content_type, byte_size).await {
+        Ok(id) => Ok(id),
+        Err(e) => {
+            error!("Error failed insert_media {:?}", e);
+            delete_stored_media(app_state, &[storage_key]).await;
+            Err(AppErrors::from(e))
+        }
+    }
+}
@@ -1,32 +1,330 @@
 use std::sync::Arc;
+use chrono::Utc;
 use axum::response::{IntoResponse, Response};
-use axum::extract::{Json, Path, State};
+use axum::extract::multipart::Field;
+use axum::extract::{FromRequest, Multipart, Request, State};
+use axum::http::header;
 use tracing::error;
+use crate::controllers::media::media_ctrl::store_media;
 use crate::lib::app_state::AppState;
+use crate::lib::attachment::{process_attachment, ATTACHMENT_ALT_TEXT_MAX_LEN, ATTACHMENT_MAX_BYTES, MESSAGE_ATTACHMENTS_MAX};
+use crate::lib::image_upload::UploadError;
+use crate::lib::media_jobs::discard_media;
+use crate::repository::message::message_models::NewAttachment;
 use crate::repository::message::message_repo::MessageRepo;
-use crate::repository::repo::Repository;
+use crate::repository::repo::{EntityId, Repository};
 use crate::routes::lib::app_response::AppResponse;
+use crate::routes::lib::auth_user::AuthUser;
 use crate::routes::lib::error::AppErrors;
-use super::message_models::CreateMessage;
+use crate::routes::lib::extractors::{read_field_limited, AppJson, AppMultipart, AppPath, AppQuery};
+use crate::routes::lib::pagination::{Page, PageQuery};
+use super::message_models::{AttachmentUpload, CreateMessage, CreateReply, EditMessage, EditedMessage, MessageForm, MessageHistory, MessageThread, MESSAGE_BODY_MAX_LEN, MESSAGE_TEXT_FIELD_MAX_BYTES};
 
-pub async fn create_message(State(state): State<Arc<AppState>>, Json(create_message): Json<CreateMessage>) -> Response {
+/// Accepts either a JSON `CreateMessage` or a `multipart/form-data` form with the same
+/// fields plus up to `MESSAGE_ATTACHMENTS_MAX` `attachment` image files. An `alt_text`
+/// field describes the attachment sent just before it.
+pub async fn create_message(State(state): State<Arc<AppState>>, auth_user: AuthUser, request: Request) -> Response {
     let app_state = Arc::clone(&state);
-    match app_state.repo.insert_message(app_state.repo.get_pool(), create_message.user_id, &create_message.body, create_message.broadcasting_msg_id).await {
+    let is_multipart = request.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+    let form = if is_multipart {
+        match AppMultipart::from_request(request, &()).await {
+            Ok(AppMultipart(multipart)) => read_message_form(multipart).await,
+            Err(e) => Err(e)
+        }
+    } else {
+        AppJson::<CreateMessage>::from_request(request, &())
+            .await
+            .map(|AppJson(message)| MessageForm { message, attachments: vec![] })
+    };
+    let form = match form {
+        Ok(form) => form,
+        Err(e) => return e.into_response()
+    };
+    if let Err(e) = validate_body(&form.message.body) {
+        return e.into_response();
+    }
+
+    let mut images = vec![];
+    for upload in form.attachments {
+        match process_attachment(upload.data).await {
+            Ok(image) => images.push((image, upload.alt_text.filter(|alt_text| !alt_text.is_empty()))),
+            Err(UploadError::TooLarge) => return attachment_too_large().into_response(),
+            Err(UploadError::UnsupportedFormat) => {
+                return AppErrors::UnsupportedMediaType("attachments must be PNG, JPEG or WebP images".to_string()).into_response();
+            }
+            Err(UploadError::Invalid) => {
+                return AppErrors::ValidationFailed("attachment is not a readable image".to_string()).into_response();
+            }
+        }
+    }
+
+    let mut attachments: Vec<NewAttachment> = vec![];
+    for (image, alt_text) in images {
+        match store_media(&app_state, auth_user.profile_id, image.content_type, image.data).await {
+            Ok(media_id) => attachments.push(NewAttachment {
+                media_id,
+                alt_text,
+                width: image.width as i32,
+                height: image.height as i32
+            }),
+            Err(e) => {
+                discard_media(&app_state, &attachments.iter().map(|attachment| attachment.media_id).collect::<Vec<i64>>()).await;
+                return e.into_response();
+            }
+        }
+    }
+
+    match app_state.repo.insert_message(
+        app_state.repo.get_pool(),
+        auth_user.profile_id,
+        &form.message.body,
+        form.message.broadcasting_msg_id,
+        &attachments
+    ).await {
         Ok(entity) => AppResponse::Create(entity).into_response(),
         Err(e) => {
             error!("Error failed create_message {:?}", e);
-            AppErrors::InternalServerError.into_response()
+            discard_media(&app_state, &attachments.iter().map(|attachment| attachment.media_id).collect::<Vec<i64>>()).await;
+            AppErrors::from(e).into_response()
         }
     }
 }
 
-pub async fn get_message(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> Response {
+pub async fn get_message(State(state): State<Arc<AppState>>, AppPath(id): AppPath<i64>) -> Response {
     let app_state = Arc::clone(&state);
     match app_state.repo.select_message(app_state.repo.get_pool(), id).await {
-        Ok(msg) => AppResponse::JsonData(msg).into_response(),
+        Ok(msg) => AppResponse::found(msg),
         Err(e) => {
             error!("Error get_message {:?}", e);
-            AppErrors::InternalServerError.into_response()
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// Only the author may delete a message. It is kept as a tombstone, so broadcasts of it
+/// show a placeholder and replies keep their place in the thread.
+pub async fn delete_message(State(state): State<Arc<AppState>>, auth_user: AuthUser, AppPath(id): AppPath<i64>) -> Response {
+    let app_state = Arc::clone(&state);
+    let pool = app_state.repo.get_pool();
+    match app_state.repo.select_message_author(pool, id).await {
+        Ok(Some(author)) if author.user_id == auth_user.profile_id => {}
+        Ok(Some(_)) => return AppErrors::Forbidden.into_response(),
+        Ok(None) => return AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed select_message_author {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    }
+
+    match app_state.repo.soft_delete_message(pool, id).await {
+        Ok(true) => AppResponse::<()>::Ok.into_response(),
+        Ok(false) => AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed soft_delete_message {:?}", e);
+            AppErrors::from(e).into_response()
         }
     }
-}
\ No newline at end of file
+}
+
+/// Only the author may edit, and only within the configured edit window. The replaced
+/// body is kept and shown by `GET /message/:id/history`.
+pub async fn edit_message(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    AppPath(id): AppPath<i64>,
+    AppJson(edit_message): AppJson<EditMessage>
+) -> Response {
+    let app_state = Arc::clone(&state);
+    let pool = app_state.repo.get_pool();
+    if let Err(e) = validate_body(&edit_message.body) {
+        return e.into_response();
+    }
+
+    match app_state.repo.select_message_author(pool, id).await {
+        Ok(Some(author)) if author.user_id != auth_user.profile_id => return AppErrors::Forbidden.into_response(),
+        Ok(Some(author)) if author.created_at + app_state.messages.edit_window < Utc::now() => {
+            return AppErrors::Conflict("the edit window for this message has closed".to_string()).into_response();
+        }
+        Ok(Some(_)) => {}
+        Ok(None) => return AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed select_message_author {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    }
+
+    match app_state.repo.update_message_body(pool, id, &edit_message.body).await {
+        Ok(Some(edited_at)) => AppResponse::JsonData(EditedMessage { id, edited_at }).into_response(),
+        Ok(None) => AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed update_message_body {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+pub async fn get_message_history(State(state): State<Arc<AppState>>, AppPath(id): AppPath<i64>) -> Response {
+    let app_state = Arc::clone(&state);
+    let pool = app_state.repo.get_pool();
+    let message = match app_state.repo.select_message(pool, id).await {
+        Ok(Some(message)) => message,
+        Ok(None) => return AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed select_message {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    };
+
+    match app_state.repo.select_message_revisions(pool, id).await {
+        Ok(revisions) => AppResponse::JsonData(MessageHistory {
+            id,
+            body: message.body,
+            edited_at: message.edited_at,
+            revisions
+        }).into_response(),
+        Err(e) => {
+            error!("Error failed select_message_revisions {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+pub async fn reply_to_message(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    AppPath(id): AppPath<i64>,
+    AppJson(create_reply): AppJson<CreateReply>
+) -> Response {
+    let app_state = Arc::clone(&state);
+    if let Err(e) = validate_body(&create_reply.body) {
+        return e.into_response();
+    }
+
+    match app_state.repo.insert_response_message(app_state.repo.get_pool(), auth_user.profile_id, &create_reply.body, id).await {
+        Ok(reply_id) => AppResponse::Create(EntityId { id: reply_id }).into_response(),
+        Err(e) => {
+            error!("Error failed insert_response_message {:?}", e);
+            match AppErrors::from(e) {
+                AppErrors::InvalidReference(_) => AppErrors::NotFound.into_response(),
+                app_error => app_error.into_response()
+            }
+        }
+    }
+}
+
+/// Returns the ancestors of a message up to the thread root, the message itself and
+/// a page of its replies in depth-first order.
+pub async fn get_message_thread(
+    State(state): State<Arc<AppState>>,
+    AppPath(id): AppPath<i64>,
+    AppQuery(page): AppQuery<PageQuery>
+) -> Response {
+    let app_state = Arc::clone(&state);
+    let pool = app_state.repo.get_pool();
+    let after_path = match page.decode_cursor::<Vec<i64>>() {
+        Ok(cursor) => cursor,
+        Err(e) => return e.into_response()
+    };
+
+    let message = match app_state.repo.select_thread_message(pool, id).await {
+        Ok(Some(message)) => message,
+        Ok(None) => return AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed select_thread_message {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    };
+    let ancestors = match app_state.repo.select_message_ancestors(pool, id).await {
+        Ok(ancestors) => ancestors,
+        Err(e) => {
+            error!("Error failed select_message_ancestors {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    };
+
+    match app_state.repo.select_message_descendants(pool, id, after_path, page.page_size() as i64 + 1).await {
+        Ok(replies) => AppResponse::JsonData(MessageThread {
+            ancestors,
+            message,
+            replies: Page::from_rows(replies, page.page_size(), |reply| reply.path.clone())
+        }).into_response(),
+        Err(e) => {
+            error!("Error failed select_message_descendants {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+fn validate_body(body: &str) -> Result<(), AppErrors> {
+    if body.chars().count() > MESSAGE_BODY_MAX_LEN {
+        return Err(AppErrors::ValidationFailed(
+            format!("body must be at most {} characters", MESSAGE_BODY_MAX_LEN)
+        ));
+    }
+    Ok(())
+}
+
+async fn read_message_form(mut multipart: Multipart) -> Result<MessageForm, AppErrors> {
+    let mut body = None;
+    let mut broadcasting_msg_id = None;
+    let mut attachments: Vec<AttachmentUpload> = vec![];
+
+    while let Some(mut field) = multipart.next_field().await? {
+        match field.name() {
+            Some("body") => {
+                body = Some(read_text_field(&mut field, "body").await?);
+            }
+            Some("broadcasting_msg_id") => {
+                let value = read_text_field(&mut field, "broadcasting_msg_id").await?;
+                broadcasting_msg_id = Some(value.trim().parse::<i64>().map_err(|_| {
+                    AppErrors::ValidationFailed("broadcasting_msg_id must be an integer".to_string())
+                })?);
+            }
+            Some("attachment") => {
+                if attachments.len() == MESSAGE_ATTACHMENTS_MAX {
+                    return Err(AppErrors::ValidationFailed(
+                        format!("a message can have at most {} attachments", MESSAGE_ATTACHMENTS_MAX)
+                    ));
+                }
+                let data = read_field_limited(&mut field, ATTACHMENT_MAX_BYTES, "attachment").await?;
+                attachments.push(AttachmentUpload { data, alt_text: None });
+            }
+            Some("alt_text") => {
+                let alt_text = read_text_field(&mut field, "alt_text").await?;
+                match attachments.last_mut() {
+                    Some(attachment) if attachment.alt_text.is_none() => {
+                        let alt_text = alt_text.trim();
+                        if alt_text.chars().count() > ATTACHMENT_ALT_TEXT_MAX_LEN {
+                            return Err(AppErrors::ValidationFailed(
+                                format!("alt_text must be at most {} characters", ATTACHMENT_ALT_TEXT_MAX_LEN)
+                            ));
+                        }
+                        attachment.alt_text = Some(alt_text.to_string());
+                    }
+                    _ => return Err(AppErrors::ValidationFailed("alt_text must follow the attachment it describes".to_string()))
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(MessageForm {
+        message: CreateMessage {
+            body: body.unwrap_or_default(),
+            broadcasting_msg_id
+        },
+        attachments
+    })
+}
+
+async fn read_text_field(field: &mut Field<'_>, label: &str) -> Result<String, AppErrors> {
+    let data = read_field_limited(field, MESSAGE_TEXT_FIELD_MAX_BYTES, label).await?;
+    String::from_utf8(data).map_err(|_| AppErrors::ValidationFailed(format!("{} must be UTF-8 text", label)))
+}
+
+fn attachment_too_large() -> AppErrors {
+    AppErrors::PayloadTooLarge(format!("attachment must be at most {} bytes", ATTACHMENT_MAX_BYTES))
+}
@@ -1,8 +1,58 @@
-use serde::Deserialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::repository::message::message_models::{MessageRevisionQueryResult, ThreadMessageQueryResult};
+use crate::routes::lib::pagination::Page;
+
+/// Matches `message.body varchar(140)` in the init migration.
+pub const MESSAGE_BODY_MAX_LEN: usize = 140;
+/// Cap on the text fields of a multipart message form, well above any valid value.
+pub const MESSAGE_TEXT_FIELD_MAX_BYTES: usize = 8 * 1024;
 
 #[derive(Deserialize)]
 pub struct CreateMessage {
-    pub user_id: i64,
     pub body: String,
     pub broadcasting_msg_id: Option<i64>
-}
\ No newline at end of file
+}
+
+/// A message as read from a multipart form, before its attachments are processed.
+pub struct MessageForm {
+    pub message: CreateMessage,
+    pub attachments: Vec<AttachmentUpload>
+}
+
+pub struct AttachmentUpload {
+    pub data: Vec<u8>,
+    pub alt_text: Option<String>
+}
+
+#[derive(Deserialize)]
+pub struct EditMessage {
+    pub body: String
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EditedMessage {
+    pub id: i64,
+    pub edited_at: DateTime<Utc>
+}
+
+/// The current body of a message followed by the bodies it replaced.
+#[derive(Serialize, Deserialize)]
+pub struct MessageHistory {
+    pub id: i64,
+    pub body: Option<String>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub revisions: Vec<MessageRevisionQueryResult>
+}
+
+#[derive(Deserialize)]
+pub struct CreateReply {
+    pub body: String
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MessageThread {
+    pub ancestors: Vec<ThreadMessageQueryResult>,
+    pub message: ThreadMessageQueryResult,
+    pub replies: Page<ThreadMessageQueryResult>
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+use axum::response::{IntoResponse, Response};
+use axum::extract::State;
+use tracing::error;
+use crate::lib::app_state::AppState;
+use crate::repository::notification::notification_models::NotificationCursor;
+use crate::repository::notification::notification_repo::NotificationRepo;
+use crate::repository::repo::Repository;
+use crate::routes::lib::app_response::AppResponse;
+use crate::routes::lib::auth_user::AuthUser;
+use crate::routes::lib::error::AppErrors;
+use crate::routes::lib::extractors::{AppJson, AppQuery};
+use crate::routes::lib::pagination::{Page, PageQuery};
+use super::notification_models::{MarkNotificationsRead, Notification, NotificationPage, UnreadNotifications};
+
+pub async fn get_notifications(State(state): State<Arc<AppState>>, auth_user: AuthUser, AppQuery(page): AppQuery<PageQuery>) -> Response {
+    let app_state = Arc::clone(&state);
+    let pool = app_state.repo.get_pool();
+    let before = match page.decode_cursor::<NotificationCursor>() {
+        Ok(cursor) => cursor,
+        Err(e) => return e.into_response()
+    };
+
+    let notifications = match app_state.repo.select_notifications(pool, auth_user.profile_id, before, page.page_size() as i64 + 1).await {
+        Ok(notifications) => notifications,
+        Err(e) => {
+            error!("Error failed select_notifications {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    };
+    match app_state.repo.select_unread_notification_count(pool, auth_user.profile_id).await {
+        Ok(unread_count) => {
+            let page = Page::from_rows(notifications, page.page_size(), |n| NotificationCursor {
+                last_event_at: n.last_event_at,
+                id: n.id
+            });
+            AppResponse::JsonData(NotificationPage {
+                page: Page {
+                    items: page.items.into_iter().map(Notification::from).collect(),
+                    next_cursor: page.next_cursor
+                },
+                unread_count
+            }).into_response()
+        },
+        Err(e) => {
+            error!("Error failed select_unread_notification_count {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// Ids that are not the caller's, or already read, are ignored.
+pub async fn mark_notifications_read(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    AppJson(read): AppJson<MarkNotificationsRead>
+) -> Response {
+    let app_state = Arc::clone(&state);
+    let pool = app_state.repo.get_pool();
+
+    if let Err(e) = app_state.repo.mark_notifications_read(pool, auth_user.profile_id, read.ids.as_deref()).await {
+        error!("Error failed mark_notifications_read {:?}", e);
+        return AppErrors::from(e).into_response();
+    }
+    match app_state.repo.select_unread_notification_count(pool, auth_user.profile_id).await {
+        Ok(unread_count) => AppResponse::JsonData(UnreadNotifications { unread_count }).into_response(),
+        Err(e) => {
+            error!("Error failed select_unread_notification_count {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use crate::repository::notification::notification_models::{NotificationKind, NotificationQueryResult};
+use crate::routes::lib::pagination::Page;
+
+#[derive(Serialize, Deserialize)]
+pub struct Notification {
+    #[serde(flatten)]
+    pub notification: NotificationQueryResult,
+    /// Ready to display text such as "alice and 4 others liked your message".
+    pub summary: String
+}
+
+impl From<NotificationQueryResult> for Notification {
+    fn from(notification: NotificationQueryResult) -> Self {
+        let latest = notification.actors.first().map(|actor| actor.user_name.as_str()).unwrap_or("someone");
+        let actors = match notification.actor_count {
+            0 | 1 => latest.to_string(),
+            2 => format!("{} and 1 other", latest),
+            count => format!("{} and {} others", latest, count - 1)
+        };
+        let event = match notification.kind {
+            NotificationKind::Follow => "followed you",
+            NotificationKind::Like => "liked your message",
+            NotificationKind::Reply => "replied to your message",
+            NotificationKind::Broadcast => "broadcast your message",
+            NotificationKind::Mention => "mentioned you"
+        };
+
+        Notification { summary: format!("{} {}", actors, event), notification }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NotificationPage {
+    #[serde(flatten)]
+    pub page: Page<Notification>,
+    pub unread_count: i64
+}
+
+/// Body of `POST /notifications/read`; without `ids` every notification is marked read.
+#[derive(Deserialize)]
+pub struct MarkNotificationsRead {
+    pub ids: Option<Vec<i64>>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UnreadNotifications {
+    pub unread_count: i64
+}
@@ -1,44 +1,182 @@
 use std::sync::Arc;
 use tracing::error;
 use axum::response::{IntoResponse, Response};
-use axum::extract::{Path, State};
-use axum::Json;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use crate::controllers::media::media_ctrl::{media_response, store_media};
 use crate::lib::app_state::AppState;
-use crate::repository::profile::profile_repo::{InsertProfileFn, SelectProfileFn};
+use crate::lib::avatar::{process_avatar, AVATAR_CONTENT_TYPE, AVATAR_DEFAULT_SIZE, AVATAR_MAX_BYTES, AVATAR_SIZES};
+use crate::lib::image_upload::UploadError;
+use crate::lib::media_jobs::{delete_stored_media, discard_media};
+use crate::repository::profile::profile_repo::{ProfileAvatarFn, SelectProfileFn, UpdateProfileFn};
+use crate::repository::profile::profile_models::ProfileUpdate;
 use crate::repository::repo::Repository;
 use crate::routes::lib::app_response::AppResponse;
+use crate::routes::lib::auth_user::AuthUser;
 use crate::routes::lib::error::AppErrors;
-use super::profile_models::CreateProfile;
+use crate::routes::lib::extractors::{read_field_limited, AppJson, AppMultipart, AppPath, AppQuery};
+use super::profile_models::{is_valid_user_name, AvatarQuery, AvatarUploaded, UpdateProfile};
 
+const AVATAR_CACHE_CONTROL: &str = "public, max-age=86400";
 
-pub async fn create_profile(State(state): State<Arc<AppState>>, Json(create_profile): Json<CreateProfile>) -> Response {
-    // todo: add auth middleware
+pub async fn get_profile(State(state): State<Arc<AppState>>, AppPath(id): AppPath<i64>) -> Response {
+    let app_state = Arc::clone(&state);
+    match app_state.repo.select_profile(app_state.repo.get_pool(), id).await {
+        Ok(profile) => AppResponse::found(profile),
+        Err(e) => {
+            error!("Error failed get_profile {:?}", e);   
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+pub async fn get_profile_by_user_name(State(state): State<Arc<AppState>>, AppPath(user_name): AppPath<String>) -> Response {
+    let app_state = Arc::clone(&state);
+    if !is_valid_user_name(&user_name) {
+        return AppErrors::NotFound.into_response();
+    }
+
+    match app_state.repo.select_profile_by_user_name(app_state.repo.get_pool(), &user_name).await {
+        Ok(profile) => AppResponse::found(profile),
+        Err(e) => {
+            error!("Error failed get_profile_by_user_name {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// Only the owner may edit a profile. Absent fields are left unchanged.
+pub async fn update_profile(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    AppPath(id): AppPath<i64>,
+    AppJson(update_profile): AppJson<UpdateProfile>
+) -> Response {
+    let app_state = Arc::clone(&state);
+    if auth_user.profile_id != id {
+        return AppErrors::Forbidden.into_response();
+    }
+    if let Err(e) = update_profile.validate() {
+        return e.into_response();
+    }
+
+    match app_state.repo.update_profile(app_state.repo.get_pool(), id, &ProfileUpdate::from(update_profile)).await {
+        Ok(profile) => AppResponse::found(profile),
+        Err(e) => {
+            error!("Error failed update_profile {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
 
+/// Accepts a multipart upload with an `avatar` file field. Only the owner may replace
+/// their avatar; the image is validated and re-rendered at each of `AVATAR_SIZES`.
+pub async fn upload_avatar(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    AppPath(id): AppPath<i64>,
+    AppMultipart(mut multipart): AppMultipart
+) -> Response {
     let app_state = Arc::clone(&state);
-    match app_state.repo.insert_profile(
-        app_state.repo.get_pool(), 
-        create_profile.user_name,
-        create_profile.full_name,
-        create_profile.description,
-        create_profile.region,
-        create_profile.main_url,
-        create_profile.avatar
-    ).await {
-        Ok(entity) => AppResponse::Create(entity).into_response(),
+    if auth_user.profile_id != id {
+        return AppErrors::Forbidden.into_response();
+    }
+
+    let upload = match read_avatar_field(&mut multipart).await {
+        Ok(Some(upload)) => upload,
+        Ok(None) => return AppErrors::ValidationFailed("an avatar file field is required".to_string()).into_response(),
+        Err(e) => return e.into_response()
+    };
+    let images = match process_avatar(upload).await {
+        Ok(images) => images,
+        Err(UploadError::TooLarge) => {
+            return AppErrors::PayloadTooLarge(format!("avatar must be at most {} bytes", AVATAR_MAX_BYTES)).into_response();
+        }
+        Err(UploadError::UnsupportedFormat) => {
+            return AppErrors::UnsupportedMediaType("avatar must be a PNG, JPEG or WebP image".to_string()).into_response();
+        }
+        Err(UploadError::Invalid) => return AppErrors::ValidationFailed("avatar is not a readable image".to_string()).into_response()
+    };
+
+    let mut sizes: Vec<(i32, i64)> = vec![];
+    for image in images {
+        match store_media(&app_state, id, AVATAR_CONTENT_TYPE, image.data).await {
+            Ok(media_id) => sizes.push((image.size as i32, media_id)),
+            Err(e) => {
+                let stored_ids = sizes.iter().map(|(_, media_id)| *media_id).collect::<Vec<i64>>();
+                discard_media(&app_state, &stored_ids).await;
+                return e.into_response();
+            }
+        }
+    }
+    let avatar_id = sizes.iter()
+        .find(|(size, _)| *size == AVATAR_DEFAULT_SIZE as i32)
+        .map(|(_, media_id)| *media_id)
+        .unwrap_or_default();
+
+    match app_state.repo.upsert_avatar(app_state.repo.get_pool(), id, avatar_id, sizes.clone()).await {
+        Ok(Some(replaced_keys)) => {
+            delete_stored_media(&app_state, &replaced_keys).await;
+            AppResponse::JsonData(AvatarUploaded {
+                profile_id: id,
+                avatar_id,
+                sizes: sizes.into_iter().map(|(size, _)| size as u32).collect()
+            }).into_response()
+        }
+        Ok(None) => {
+            discard_media(&app_state, &sizes.iter().map(|(_, media_id)| *media_id).collect::<Vec<i64>>()).await;
+            AppErrors::NotFound.into_response()
+        }
         Err(e) => {
-            error!("Error failed insert_profile {:?}", e);
-            AppErrors::InternalServerError.into_response()
+            error!("Error failed upsert_avatar {:?}", e);
+            discard_media(&app_state, &sizes.iter().map(|(_, media_id)| *media_id).collect::<Vec<i64>>()).await;
+            AppErrors::from(e).into_response()
         }
-    }    
+    }
 }
 
-pub async fn get_profile(State(state): State<Arc<AppState>>, Path(id): Path<i64>) -> Response {
+/// Avatars are addressed by profile, so unlike `/media/:id` they can change under the
+/// same URL and are only cached for a day.
+pub async fn get_avatar(
+    State(state): State<Arc<AppState>>,
+    AppPath(id): AppPath<i64>,
+    AppQuery(query): AppQuery<AvatarQuery>,
+    headers: HeaderMap
+) -> Response {
     let app_state = Arc::clone(&state);
+    let size = query.size.unwrap_or(AVATAR_DEFAULT_SIZE);
+    if !AVATAR_SIZES.contains(&size) {
+        return AppErrors::ValidationFailed(format!("size must be one of {:?}", AVATAR_SIZES)).into_response();
+    }
+
+    match app_state.repo.select_avatar(app_state.repo.get_pool(), id, size as i32).await {
+        Ok(Some(avatar)) => media_response(&app_state, avatar, &headers, AVATAR_CACHE_CONTROL).await,
+        Ok(None) => AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed select_avatar {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// Reads the `avatar` field, giving up as soon as it grows past `AVATAR_MAX_BYTES`.
+async fn read_avatar_field(multipart: &mut axum::extract::Multipart) -> Result<Option<Vec<u8>>, AppErrors> {
+    while let Some(mut field) = multipart.next_field().await? {
+        if field.name() == Some("avatar") {
+            return read_field_limited(&mut field, AVATAR_MAX_BYTES, "avatar").await.map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// For listings under `/profile/:id/...`, which should 404 rather than come back empty.
+pub async fn ensure_profile_exists(app_state: &AppState, id: i64) -> Result<(), AppErrors> {
     match app_state.repo.select_profile(app_state.repo.get_pool(), id).await {
-        Ok(profile) => AppResponse::JsonData(profile).into_response(),
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(AppErrors::NotFound),
         Err(e) => {
-            error!("Error failed get_profile {:?}", e);   
-            AppErrors::InternalServerError.into_response()
+            error!("Error failed select_profile {:?}", e);
+            Err(AppErrors::from(e))
         }
     }
-}
\ No newline at end of file
+}
@@ -1,4 +1,13 @@
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
+use crate::repository::profile::profile_models::{DmPolicy, ProfileUpdate};
+use crate::routes::lib::error::AppErrors;
+
+// Limits match the `varchar` sizes of the profile table in the init migration.
+pub const USER_NAME_MAX_LEN: usize = 50;
+pub const FULL_NAME_MAX_LEN: usize = 100;
+pub const DESCRIPTION_MAX_LEN: usize = 250;
+pub const REGION_MAX_LEN: usize = 50;
+pub const MAIN_URL_MAX_LEN: usize = 250;
 
 #[derive(Deserialize)]
 pub struct CreateProfile {
@@ -7,5 +16,123 @@ pub struct CreateProfile {
     pub description: String,
     pub region: Option<String>,
     pub main_url: Option<String>,
-    pub avatar: Option<Vec<u8>>,
+}
+
+impl CreateProfile {
+    pub fn validate(&self) -> Result<(), AppErrors> {
+        validate_user_name(&self.user_name)?;
+        validate_required("full_name", &self.full_name, FULL_NAME_MAX_LEN)?;
+        validate_len("description", &self.description, DESCRIPTION_MAX_LEN)?;
+        if let Some(region) = &self.region {
+            validate_len("region", region, REGION_MAX_LEN)?;
+        }
+        if let Some(main_url) = &self.main_url {
+            validate_main_url(main_url)?;
+        }
+        Ok(())
+    }
+}
+
+/// Partial update: an absent field is left unchanged, while an explicit `null`
+/// clears one of the optional fields.
+#[derive(Deserialize)]
+pub struct UpdateProfile {
+    pub full_name: Option<String>,
+    pub description: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub region: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub main_url: Option<Option<String>>,
+    pub dm_policy: Option<DmPolicy>,
+}
+
+impl UpdateProfile {
+    pub fn validate(&self) -> Result<(), AppErrors> {
+        if let Some(full_name) = &self.full_name {
+            validate_required("full_name", full_name, FULL_NAME_MAX_LEN)?;
+        }
+        if let Some(description) = &self.description {
+            validate_len("description", description, DESCRIPTION_MAX_LEN)?;
+        }
+        if let Some(Some(region)) = &self.region {
+            validate_len("region", region, REGION_MAX_LEN)?;
+        }
+        if let Some(Some(main_url)) = &self.main_url {
+            validate_main_url(main_url)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<UpdateProfile> for ProfileUpdate {
+    fn from(value: UpdateProfile) -> Self {
+        Self {
+            full_name: value.full_name,
+            description: value.description,
+            region: value.region,
+            main_url: value.main_url,
+            dm_policy: value.dm_policy
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AvatarQuery {
+    pub size: Option<u32>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AvatarUploaded {
+    pub profile_id: i64,
+    /// Media id of the default size, as now set on the profile's `avatar_id`.
+    pub avatar_id: i64,
+    pub sizes: Vec<u32>
+}
+
+/// Handles are 1 to 50 ASCII letters, digits or underscores and are unique
+/// regardless of case.
+pub fn is_valid_user_name(user_name: &str) -> bool {
+    !user_name.is_empty()
+        && user_name.len() <= USER_NAME_MAX_LEN
+        && user_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Wraps a present field in `Some`, so `null` becomes `Some(None)` rather than `None`.
+fn deserialize_present<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+fn validate_user_name(user_name: &str) -> Result<(), AppErrors> {
+    if !is_valid_user_name(user_name) {
+        return Err(AppErrors::ValidationFailed(format!(
+            "user_name must be 1 to {} letters, digits or underscores", USER_NAME_MAX_LEN
+        )));
+    }
+    Ok(())
+}
+
+fn validate_required(field: &str, value: &str, max_len: usize) -> Result<(), AppErrors> {
+    if value.trim().is_empty() {
+        return Err(AppErrors::ValidationFailed(format!("{} must not be empty", field)));
+    }
+    validate_len(field, value, max_len)
+}
+
+fn validate_len(field: &str, value: &str, max_len: usize) -> Result<(), AppErrors> {
+    if value.chars().count() > max_len {
+        return Err(AppErrors::ValidationFailed(format!("{} must be at most {} characters", field, max_len)));
+    }
+    Ok(())
+}
+
+fn validate_main_url(main_url: &str) -> Result<(), AppErrors> {
+    validate_len("main_url", main_url, MAIN_URL_MAX_LEN)?;
+    if !(main_url.starts_with("https://") || main_url.starts_with("http://")) {
+        return Err(AppErrors::ValidationFailed("main_url must be an http(s) url".to_string()));
+    }
+    Ok(())
 }
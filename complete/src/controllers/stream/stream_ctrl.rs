@@ -0,0 +1,167 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use futures_util::{stream, SinkExt, StreamExt};
+use tokio::sync::mpsc;
+use tracing::error;
+use crate::lib::app_state::AppState;
+use crate::lib::events::{subscribe_events, StreamEvent};
+use crate::repository::event::event_models::EventKind;
+use crate::routes::lib::auth_user::AuthUser;
+use crate::routes::lib::error::AppErrors;
+use crate::routes::lib::extractors::AppQuery;
+use super::stream_models::StreamQuery;
+
+/// A socket that doesn't accept a frame within this long is dropped; the client can
+/// reconnect and resume from the last event it got.
+const WEBSOCKET_SEND_TIMEOUT: Duration = Duration::from_secs(10);
+const WEBSOCKET_EVENT_KINDS: &[EventKind] = &[EventKind::Message, EventKind::Likes, EventKind::Notification];
+const HOME_EVENT_KINDS: &[EventKind] = &[EventKind::Message, EventKind::Likes];
+const NOTIFICATION_EVENT_KINDS: &[EventKind] = &[EventKind::Notification];
+
+/// The caller from the `Authorization` header, or failing that from `?access_token=`.
+fn stream_profile_id(app_state: &AppState, auth_user: Result<AuthUser, AppErrors>, query: &StreamQuery) -> Result<i64, AppErrors> {
+    match auth_user {
+        Ok(auth_user) => Ok(auth_user.profile_id),
+        Err(e) => query.access_token
+            .as_deref()
+            .and_then(|token| app_state.tokens.verify_access_token(token))
+            .ok_or(e)
+    }
+}
+
+/// Pushes home timeline messages, like counts and notifications as JSON text frames.
+/// `?last_event_id=` replays what was missed since that event.
+pub async fn connect_websocket(
+    State(state): State<Arc<AppState>>,
+    auth_user: Result<AuthUser, AppErrors>,
+    AppQuery(query): AppQuery<StreamQuery>,
+    upgrade: WebSocketUpgrade
+) -> Response {
+    let app_state = Arc::clone(&state);
+    let profile_id = match stream_profile_id(&app_state, auth_user, &query) {
+        Ok(profile_id) => profile_id,
+        Err(e) => return e.into_response()
+    };
+
+    let heartbeat_interval = app_state.events.config.heartbeat_interval;
+    match subscribe_events(app_state, profile_id, WEBSOCKET_EVENT_KINDS, query.last_event_id).await {
+        Ok(events) => upgrade.on_upgrade(move |socket| serve_websocket(socket, events, heartbeat_interval)),
+        Err(e) => {
+            error!("Error failed subscribe_events {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+async fn serve_websocket(socket: WebSocket, mut events: mpsc::Receiver<Arc<StreamEvent>>, heartbeat_interval: Duration) {
+    let (mut outgoing, mut incoming) = socket.split();
+    let mut heartbeat = tokio::time::interval(heartbeat_interval);
+    heartbeat.tick().await;
+    let mut last_heard = Instant::now();
+
+    loop {
+        let frame = tokio::select! {
+            event = events.recv() => match event {
+                Some(event) => match serde_json::to_string(event.as_ref()) {
+                    Ok(text) => Message::Text(text),
+                    Err(e) => {
+                        error!("Error failed to serialize event {} {:?}", event.id, e);
+                        continue;
+                    }
+                },
+                None => break
+            },
+            received = incoming.next() => match received {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // pongs, and pings which are answered automatically, show the client is alive
+                Some(Ok(_)) => {
+                    last_heard = Instant::now();
+                    continue;
+                }
+            },
+            _ = heartbeat.tick() => {
+                if last_heard.elapsed() > heartbeat_interval * 2 {
+                    break;
+                }
+                Message::Ping(vec![])
+            }
+        };
+
+        match tokio::time::timeout(WEBSOCKET_SEND_TIMEOUT, outgoing.send(frame)).await {
+            Ok(Ok(())) => {}
+            _ => break
+        }
+    }
+}
+
+/// Home timeline messages and like counts as `text/event-stream`.
+pub async fn stream_home(
+    State(state): State<Arc<AppState>>,
+    auth_user: Result<AuthUser, AppErrors>,
+    AppQuery(query): AppQuery<StreamQuery>,
+    headers: HeaderMap
+) -> Response {
+    serve_event_stream(state, auth_user, query, headers, HOME_EVENT_KINDS).await
+}
+
+/// Notifications as `text/event-stream`.
+pub async fn stream_notifications(
+    State(state): State<Arc<AppState>>,
+    auth_user: Result<AuthUser, AppErrors>,
+    AppQuery(query): AppQuery<StreamQuery>,
+    headers: HeaderMap
+) -> Response {
+    serve_event_stream(state, auth_user, query, headers, NOTIFICATION_EVENT_KINDS).await
+}
+
+/// Each event carries its id and kind as the SSE `id` and `event` fields, and the same
+/// JSON the WebSocket sends as `data`. A reconnecting `EventSource` sends the last id
+/// back as `Last-Event-ID`, which takes precedence over `?last_event_id=`.
+async fn serve_event_stream(
+    state: Arc<AppState>,
+    auth_user: Result<AuthUser, AppErrors>,
+    query: StreamQuery,
+    headers: HeaderMap,
+    kinds: &'static [EventKind]
+) -> Response {
+    let app_state = Arc::clone(&state);
+    let profile_id = match stream_profile_id(&app_state, auth_user, &query) {
+        Ok(profile_id) => profile_id,
+        Err(e) => return e.into_response()
+    };
+    let last_event_id = match headers.get("last-event-id") {
+        Some(value) => match value.to_str().ok().and_then(|id| id.trim().parse::<i64>().ok()) {
+            Some(id) => Some(id),
+            None => return AppErrors::BadRequest("Last-Event-ID must be an event id".to_string()).into_response()
+        },
+        None => query.last_event_id
+    };
+
+    let heartbeat_interval = app_state.events.config.heartbeat_interval;
+    let events = match subscribe_events(app_state, profile_id, kinds, last_event_id).await {
+        Ok(events) => events,
+        Err(e) => {
+            error!("Error failed subscribe_events {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    };
+    let sse_events = stream::unfold(events, |mut events| async move {
+        let event = events.recv().await?;
+        let sse_event = Event::default()
+            .id(event.id.to_string())
+            .event(event.kind.name())
+            .json_data(event.as_ref())
+            .ok()?;
+        Some((Ok::<Event, Infallible>(sse_event), events))
+    });
+
+    Sse::new(sse_events)
+        .keep_alive(KeepAlive::new().interval(heartbeat_interval))
+        .into_response()
+}
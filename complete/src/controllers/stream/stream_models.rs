@@ -0,0 +1,9 @@
+use serde::Deserialize;
+
+/// Browsers can't set headers on a WebSocket handshake or an `EventSource`, so the
+/// access token may be passed as `access_token` instead of an `Authorization` header.
+#[derive(Deserialize)]
+pub struct StreamQuery {
+    pub last_event_id: Option<i64>,
+    pub access_token: Option<String>
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use axum::response::{IntoResponse, Response};
+use axum::extract::State;
+use tracing::error;
+use crate::controllers::profile::profile_ctrl::ensure_profile_exists;
+use crate::lib::app_state::AppState;
+use crate::repository::message::message_models::MessageCursor;
+use crate::repository::message::message_repo::MessageRepo;
+use crate::repository::repo::Repository;
+use crate::routes::lib::app_response::AppResponse;
+use crate::routes::lib::auth_user::AuthUser;
+use crate::routes::lib::error::AppErrors;
+use crate::routes::lib::extractors::{AppPath, AppQuery};
+use crate::routes::lib::pagination::{Page, PageQuery};
+use super::timeline_models::AuthorTimelineQuery;
+
+pub async fn get_home_timeline(State(state): State<Arc<AppState>>, auth_user: AuthUser, AppQuery(page): AppQuery<PageQuery>) -> Response {
+    let app_state = Arc::clone(&state);
+    let before = match page.decode_cursor::<MessageCursor>() {
+        Ok(cursor) => cursor,
+        Err(e) => return e.into_response()
+    };
+
+    match app_state.repo.select_messages(app_state.repo.get_pool(), auth_user.profile_id, before, page.page_size() as i64 + 1).await {
+        Ok(messages) => AppResponse::JsonData(Page::from_rows(messages, page.page_size(), |m| MessageCursor {
+            updated_at: m.updated_at,
+            id: m.id
+        })).into_response(),
+        Err(e) => {
+            error!("Error failed select_messages {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// Messages a single profile posted, with the same broadcast expansion as the home timeline.
+pub async fn get_author_timeline(
+    State(state): State<Arc<AppState>>,
+    AppPath(id): AppPath<i64>,
+    AppQuery(page): AppQuery<PageQuery>,
+    AppQuery(filter): AppQuery<AuthorTimelineQuery>
+) -> Response {
+    let app_state = Arc::clone(&state);
+    let before = match page.decode_cursor::<MessageCursor>() {
+        Ok(cursor) => cursor,
+        Err(e) => return e.into_response()
+    };
+    if let Err(e) = ensure_profile_exists(&app_state, id).await {
+        return e.into_response();
+    }
+
+    match app_state.repo.select_messages_by_author(
+        app_state.repo.get_pool(),
+        id,
+        filter.filter(),
+        before,
+        page.page_size() as i64 + 1
+    ).await {
+        Ok(messages) => AppResponse::JsonData(Page::from_rows(messages, page.page_size(), |m| MessageCursor {
+            updated_at: m.updated_at,
+            id: m.id
+        })).into_response(),
+        Err(e) => {
+            error!("Error failed select_messages_by_author {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// Messages from other profiles that mention the signed in user.
+pub async fn get_mentions_timeline(State(state): State<Arc<AppState>>, auth_user: AuthUser, AppQuery(page): AppQuery<PageQuery>) -> Response {
+    let app_state = Arc::clone(&state);
+    let before = match page.decode_cursor::<MessageCursor>() {
+        Ok(cursor) => cursor,
+        Err(e) => return e.into_response()
+    };
+
+    match app_state.repo.select_messages_mentioning(app_state.repo.get_pool(), auth_user.profile_id, before, page.page_size() as i64 + 1).await {
+        Ok(messages) => AppResponse::JsonData(Page::from_rows(messages, page.page_size(), |m| MessageCursor {
+            updated_at: m.updated_at,
+            id: m.id
+        })).into_response(),
+        Err(e) => {
+            error!("Error failed select_messages_mentioning {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use serde::Deserialize;
+use crate::repository::message::message_models::AuthorMessageFilter;
+
+/// Filters for `GET /profile/:id/messages`. Replies and broadcasts are included unless
+/// turned off; `only_media` keeps just messages with attachments.
+#[derive(Deserialize)]
+pub struct AuthorTimelineQuery {
+    pub include_replies: Option<bool>,
+    pub include_broadcasts: Option<bool>,
+    pub only_media: Option<bool>
+}
+
+impl AuthorTimelineQuery {
+    pub fn filter(&self) -> AuthorMessageFilter {
+        AuthorMessageFilter {
+            include_replies: self.include_replies.unwrap_or(true),
+            include_broadcasts: self.include_broadcasts.unwrap_or(true),
+            only_media: self.only_media.unwrap_or(false)
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+use axum::response::{IntoResponse, Response};
+use axum::extract::State;
+use tracing::error;
+use crate::lib::app_state::AppState;
+use crate::lib::token::generate_opaque_token;
+use crate::repository::repo::Repository;
+use crate::repository::webhook::webhook_models::WebhookDeliveryCursor;
+use crate::repository::webhook::webhook_repo::WebhookRepo;
+use crate::routes::lib::app_response::AppResponse;
+use crate::routes::lib::auth_user::AuthUser;
+use crate::routes::lib::error::AppErrors;
+use crate::routes::lib::extractors::{AppJson, AppPath, AppQuery};
+use crate::routes::lib::pagination::{Page, PageQuery};
+use super::webhook_models::{CreatedWebhook, NewWebhook};
+
+/// Each webhook gets its own random secret for signing its deliveries. It receives the
+/// events its owner is involved in: their own messages, follows from and to them, and
+/// likes they give or get.
+pub async fn create_webhook(State(state): State<Arc<AppState>>, auth_user: AuthUser, AppJson(new_webhook): AppJson<NewWebhook>) -> Response {
+    let app_state = Arc::clone(&state);
+    if let Err(e) = new_webhook.validate(app_state.webhooks.allow_private_targets) {
+        return e.into_response();
+    }
+    let mut event_types = new_webhook.event_types;
+    event_types.sort();
+    event_types.dedup();
+    let secret = generate_opaque_token();
+
+    match app_state.repo.insert_webhook(app_state.repo.get_pool(), auth_user.profile_id, &new_webhook.url, &secret, &event_types).await {
+        Ok(webhook) => AppResponse::Create(CreatedWebhook { webhook, secret }).into_response(),
+        Err(e) => {
+            error!("Error failed insert_webhook {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+pub async fn get_webhooks(State(state): State<Arc<AppState>>, auth_user: AuthUser) -> Response {
+    let app_state = Arc::clone(&state);
+    match app_state.repo.select_webhooks(app_state.repo.get_pool(), auth_user.profile_id).await {
+        Ok(webhooks) => AppResponse::JsonData(webhooks).into_response(),
+        Err(e) => {
+            error!("Error failed select_webhooks {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// Other profiles' webhooks are reported as missing.
+pub async fn delete_webhook(State(state): State<Arc<AppState>>, auth_user: AuthUser, AppPath(id): AppPath<i64>) -> Response {
+    let app_state = Arc::clone(&state);
+    match app_state.repo.delete_webhook(app_state.repo.get_pool(), auth_user.profile_id, id).await {
+        Ok(true) => AppResponse::<()>::Ok.into_response(),
+        Ok(false) => AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed delete_webhook {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// Re-enables a webhook that was disabled for failing too often.
+pub async fn enable_webhook(State(state): State<Arc<AppState>>, auth_user: AuthUser, AppPath(id): AppPath<i64>) -> Response {
+    let app_state = Arc::clone(&state);
+    match app_state.repo.enable_webhook(app_state.repo.get_pool(), auth_user.profile_id, id).await {
+        Ok(webhook) => AppResponse::found(webhook),
+        Err(e) => {
+            error!("Error failed enable_webhook {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
+
+/// A webhook's deliveries, newest first, each with its attempts.
+pub async fn get_webhook_deliveries(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    AppPath(id): AppPath<i64>,
+    AppQuery(page): AppQuery<PageQuery>
+) -> Response {
+    let app_state = Arc::clone(&state);
+    let pool = app_state.repo.get_pool();
+    let before = match page.decode_cursor::<WebhookDeliveryCursor>() {
+        Ok(cursor) => cursor,
+        Err(e) => return e.into_response()
+    };
+    match app_state.repo.select_webhook(pool, auth_user.profile_id, id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return AppErrors::NotFound.into_response(),
+        Err(e) => {
+            error!("Error failed select_webhook {:?}", e);
+            return AppErrors::from(e).into_response();
+        }
+    }
+
+    match app_state.repo.select_webhook_deliveries(pool, id, before, page.page_size() as i64 + 1).await {
+        Ok(deliveries) => AppResponse::JsonData(
+            Page::from_rows(deliveries, page.page_size(), |d| WebhookDeliveryCursor { id: d.id })
+        ).into_response(),
+        Err(e) => {
+            error!("Error failed select_webhook_deliveries {:?}", e);
+            AppErrors::from(e).into_response()
+        }
+    }
+}
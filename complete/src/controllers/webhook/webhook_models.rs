@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use crate::lib::events::DOMAIN_EVENT_TYPES;
+use crate::lib::webhook_target::check_webhook_target;
+use crate::repository::webhook::webhook_models::WebhookQueryResult;
+use crate::routes::lib::error::AppErrors;
+
+/// Matches `webhook.url varchar(2048)`.
+pub const WEBHOOK_URL_MAX_LEN: usize = 2048;
+
+/// Body of `POST /webhooks`. `event_types` are domain event types such as
+/// `message_created`; a webhook only receives the ones that involve its owner.
+#[derive(Deserialize)]
+pub struct NewWebhook {
+    pub url: String,
+    pub event_types: Vec<String>
+}
+
+impl NewWebhook {
+    /// Loopback and private targets are refused unless `allow_private_targets`.
+    pub fn validate(&self, allow_private_targets: bool) -> Result<(), AppErrors> {
+        if self.url.chars().count() > WEBHOOK_URL_MAX_LEN {
+            return Err(AppErrors::ValidationFailed(format!("url must be at most {} characters", WEBHOOK_URL_MAX_LEN)));
+        }
+        if !(self.url.starts_with("https://") || self.url.starts_with("http://")) {
+            return Err(AppErrors::ValidationFailed("url must be an http(s) url".to_string()));
+        }
+        if !allow_private_targets {
+            check_webhook_target(&self.url).map_err(|e| AppErrors::ValidationFailed(e.to_string()))?;
+        }
+        if self.event_types.is_empty() {
+            return Err(AppErrors::ValidationFailed("event_types must not be empty".to_string()));
+        }
+        if let Some(unknown) = self.event_types.iter().find(|t| !DOMAIN_EVENT_TYPES.contains(&t.as_str())) {
+            return Err(AppErrors::ValidationFailed(format!(
+                "unknown event type {}, expected one of {}", unknown, DOMAIN_EVENT_TYPES.join(", ")
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The new webhook with its signing secret, which is not shown again.
+#[derive(Serialize, Deserialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: WebhookQueryResult,
+    pub secret: String
+}
@@ -1,30 +1,124 @@
 pub mod controllers {
+    pub mod auth {
+        pub mod auth_models;
+        pub mod auth_ctrl;
+    }
+    pub mod direct_message {
+        pub mod direct_message_models;
+        pub mod direct_message_ctrl;
+    }
+    pub mod follow {
+        pub mod follow_ctrl;
+    }
+    pub mod hashtag {
+        pub mod hashtag_models;
+        pub mod hashtag_ctrl;
+    }
+    pub mod like {
+        pub mod like_models;
+        pub mod like_ctrl;
+    }
+    pub mod media {
+        pub mod media_ctrl;
+    }
     pub mod message {
         pub mod message_models;
         pub mod message_ctrl;
     }
+    pub mod notification {
+        pub mod notification_models;
+        pub mod notification_ctrl;
+    }
     pub mod profile {        
         pub mod profile_models;
         pub mod profile_ctrl;
     }
+    pub mod stream {
+        pub mod stream_models;
+        pub mod stream_ctrl;
+    }
+    pub mod timeline {
+        pub mod timeline_models;
+        pub mod timeline_ctrl;
+    }
+    pub mod webhook {
+        pub mod webhook_models;
+        pub mod webhook_ctrl;
+    }
 }
 pub mod routes {
     pub mod lib {
         pub mod error;
         pub mod app_response;
+        pub mod auth_user;
+        pub mod extractors;
+        pub mod pagination;
+    }
+    pub mod auth {
+        pub mod auth_rt;
+    }
+    pub mod direct_message {
+        pub mod direct_message_rt;
+    }
+    pub mod follow {
+        pub mod follow_rt;
+    }
+    pub mod hashtag {
+        pub mod hashtag_rt;
+    }
+    pub mod like {
+        pub mod like_rt;
+    }
+    pub mod media {
+        pub mod media_rt;
     }
     pub mod message {
         pub mod message_rt;
     }
+    pub mod notification {
+        pub mod notification_rt;
+    }
     pub mod profile {
         pub mod profile_rt;
     }
+    pub mod stream {
+        pub mod stream_rt;
+    }
+    pub mod timeline {
+        pub mod timeline_rt;
+    }
+    pub mod webhook {
+        pub mod webhook_rt;
+    }
 }
 pub mod lib {
     pub mod app_state;
+    pub mod attachment;
+    pub mod avatar;
+    pub mod entities;
+    pub mod env_config;
+    pub mod event_config;
+    pub mod events;
+    pub mod image_upload;
+    pub mod job_config;
+    pub mod jobs;
+    pub mod media_backfill;
+    pub mod media_jobs;
+    pub mod media_store;
+    pub mod message_config;
+    pub mod message_purge;
+    pub mod password;
+    pub mod token;
+    pub mod webhook_config;
+    pub mod webhook_delivery;
+    pub mod webhook_target;
 }
 pub mod repository {
     pub mod repo;
+    pub mod auth {
+        pub mod auth_models;
+        pub mod auth_repo;
+    }
     pub mod message {
         pub mod message_models;
         pub mod message_repo;
@@ -37,6 +131,38 @@ pub mod repository {
         pub mod follow_models;
         pub mod follow_repo;
     }
+    pub mod like {
+        pub mod like_models;
+        pub mod like_repo;
+    }
+    pub mod media {
+        pub mod media_models;
+        pub mod media_repo;
+    }
+    pub mod hashtag {
+        pub mod hashtag_models;
+        pub mod hashtag_repo;
+    }
+    pub mod notification {
+        pub mod notification_models;
+        pub mod notification_repo;
+    }
+    pub mod event {
+        pub mod event_models;
+        pub mod event_repo;
+    }
+    pub mod webhook {
+        pub mod webhook_models;
+        pub mod webhook_repo;
+    }
+    pub mod job {
+        pub mod job_models;
+        pub mod job_repo;
+    }
+    pub mod direct_message {
+        pub mod direct_message_models;
+        pub mod direct_message_repo;
+    }
 }
 pub mod test_utils {
     pub mod fixtures;
@@ -45,11 +171,17 @@ pub mod test_utils {
 use std::sync::Arc;
 use std::env;
 use axum::{extract::State, Router};
+use chrono::Utc;
 use dotenv::dotenv;
 use lib::app_state::AppState;
-use repository::repo::DbRepo;
-use routes::{message::message_rt::get_message_routes, profile::profile_rt::get_profile_router};
-use tracing::{info, Level};
+use lib::events::spawn_event_pump;
+use lib::jobs::{app_jobs, enqueue_unless_pending, spawn_job_workers};
+use lib::media_backfill::BackfillLegacyMedia;
+use lib::message_purge::PurgeDeletedMessages;
+use lib::webhook_delivery::spawn_webhook_delivery;
+use routes::lib::error::AppErrors;
+use routes::{auth::auth_rt::get_auth_routes, direct_message::direct_message_rt::get_direct_message_routes, follow::follow_rt::get_follow_routes, hashtag::hashtag_rt::get_hashtag_routes, like::like_rt::get_like_routes, media::media_rt::get_media_routes, message::message_rt::get_message_routes, notification::notification_rt::get_notification_routes, profile::profile_rt::get_profile_router, stream::stream_rt::get_stream_routes, timeline::timeline_rt::get_timeline_routes, webhook::webhook_rt::get_webhook_routes};
+use tracing::{error, info, Level};
 use tracing_subscriber::FmtSubscriber;
 
 pub async fn run() {
@@ -64,15 +196,33 @@ pub async fn run() {
     tracing::subscriber::set_global_default(tracing_sub)
         .expect("Setting default subscriber failed");
 
-    let state = State(Arc::new(AppState {
-        repo: DbRepo::init().await
-    }));
+    let state = State(Arc::new(AppState::init().await));
+    spawn_event_pump(Arc::clone(&state.0));
+    spawn_webhook_delivery(Arc::clone(&state.0));
+    spawn_job_workers(Arc::clone(&state.0), app_jobs());
+    if let Err(e) = enqueue_unless_pending(&state.0, &BackfillLegacyMedia, Utc::now()).await {
+        error!("Error failed to enqueue the legacy media backfill {:?}", e);
+    }
+    if let Err(e) = enqueue_unless_pending(&state.0, &PurgeDeletedMessages, Utc::now()).await {
+        error!("Error failed to schedule the message purge {:?}", e);
+    }
 
     info!("Server starting at {}:{}", host, port);
     _ = axum::serve(
         tokio::net::TcpListener::bind(format!("{}:{}", host, port)).await.unwrap(),
         Router::new()
+            .merge(get_auth_routes(state.clone()))
             .merge(get_profile_router(state.clone()))
-            .merge(get_message_routes(state))
+            .merge(get_follow_routes(state.clone()))
+            .merge(get_message_routes(state.clone()))
+            .merge(get_like_routes(state.clone()))
+            .merge(get_media_routes(state.clone()))
+            .merge(get_timeline_routes(state.clone()))
+            .merge(get_hashtag_routes(state.clone()))
+            .merge(get_notification_routes(state.clone()))
+            .merge(get_stream_routes(state.clone()))
+            .merge(get_webhook_routes(state.clone()))
+            .merge(get_direct_message_routes(state))
+            .fallback(|| async { AppErrors::NotFound })
     ).await;
 }
\ No newline at end of file
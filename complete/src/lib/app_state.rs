@@ -1,6 +1,38 @@
+use std::sync::Arc;
+use dotenv::dotenv;
+use crate::lib::event_config::EventConfig;
+use crate::lib::events::EventHub;
+use crate::lib::job_config::JobConfig;
+use crate::lib::jobs::JobQueue;
+use crate::lib::media_store::{media_store_from_env, MediaStore};
+use crate::lib::message_config::MessageConfig;
+use crate::lib::token::TokenConfig;
+use crate::lib::webhook_config::WebhookConfig;
 use crate::repository::repo::DbRepo;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub repo: DbRepo
-}
\ No newline at end of file
+    pub repo: DbRepo,
+    pub tokens: TokenConfig,
+    pub media: Arc<dyn MediaStore>,
+    pub messages: MessageConfig,
+    pub events: EventHub,
+    pub webhooks: WebhookConfig,
+    pub jobs: JobQueue
+}
+
+impl AppState {
+    pub async fn init() -> Self {
+        dotenv().ok();
+
+        Self {
+            repo: DbRepo::init().await,
+            tokens: TokenConfig::from_env(),
+            media: media_store_from_env(),
+            messages: MessageConfig::from_env(),
+            events: EventHub::new(EventConfig::from_env()),
+            webhooks: WebhookConfig::from_env(),
+            jobs: JobQueue::new(JobConfig::from_env())
+        }
+    }
+}
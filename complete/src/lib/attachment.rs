@@ -0,0 +1,52 @@
+use std::io::Cursor;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat};
+use crate::lib::image_upload::{decode_upload, UploadError};
+
+/// Most images a single message may carry.
+pub const MESSAGE_ATTACHMENTS_MAX: usize = 4;
+/// Largest upload accepted per attachment, before decoding.
+pub const ATTACHMENT_MAX_BYTES: usize = 5 * 1024 * 1024;
+/// Matches `message_attachment.alt_text varchar(1000)`.
+pub const ATTACHMENT_ALT_TEXT_MAX_LEN: usize = 1000;
+
+const ATTACHMENT_JPEG_QUALITY: u8 = 90;
+
+pub struct AttachmentImage {
+    pub content_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>
+}
+
+/// Validates an uploaded PNG, JPEG or WebP and re-encodes it. JPEGs stay JPEGs;
+/// everything else is stored as PNG.
+pub async fn process_attachment(upload: Vec<u8>) -> Result<AttachmentImage, UploadError> {
+    tokio::task::spawn_blocking(move || process_attachment_blocking(&upload))
+        .await
+        .unwrap_or(Err(UploadError::Invalid))
+}
+
+fn process_attachment_blocking(upload: &[u8]) -> Result<AttachmentImage, UploadError> {
+    let (format, decoded) = decode_upload(upload, ATTACHMENT_MAX_BYTES)?;
+
+    let mut data = vec![];
+    let content_type = if format == ImageFormat::Jpeg {
+        // JPEG has no alpha channel, so flatten before encoding
+        let rgb = DynamicImage::ImageRgb8(decoded.to_rgb8());
+        rgb.write_with_encoder(JpegEncoder::new_with_quality(&mut data, ATTACHMENT_JPEG_QUALITY))
+            .map_err(|_| UploadError::Invalid)?;
+        "image/jpeg"
+    } else {
+        decoded.write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .map_err(|_| UploadError::Invalid)?;
+        "image/png"
+    };
+
+    Ok(AttachmentImage {
+        content_type,
+        width: decoded.width(),
+        height: decoded.height(),
+        data
+    })
+}
@@ -0,0 +1,42 @@
+use std::io::Cursor;
+use image::imageops::FilterType;
+use image::ImageFormat;
+use crate::lib::image_upload::{decode_upload, UploadError};
+
+/// Largest upload accepted for an avatar, before decoding.
+pub const AVATAR_MAX_BYTES: usize = 5 * 1024 * 1024;
+/// Square sizes, in pixels, every uploaded avatar is rendered at.
+pub const AVATAR_SIZES: [u32; 3] = [48, 128, 400];
+pub const AVATAR_DEFAULT_SIZE: u32 = 128;
+pub const AVATAR_CONTENT_TYPE: &str = "image/png";
+
+pub struct AvatarImage {
+    pub size: u32,
+    pub data: Vec<u8>
+}
+
+/// Validates an uploaded PNG, JPEG or WebP and renders it as center-cropped square
+/// PNGs at each of `AVATAR_SIZES`.
+pub async fn process_avatar(upload: Vec<u8>) -> Result<Vec<AvatarImage>, UploadError> {
+    tokio::task::spawn_blocking(move || process_avatar_blocking(&upload))
+        .await
+        .unwrap_or(Err(UploadError::Invalid))
+}
+
+fn process_avatar_blocking(upload: &[u8]) -> Result<Vec<AvatarImage>, UploadError> {
+    let (_, decoded) = decode_upload(upload, AVATAR_MAX_BYTES)?;
+    let side = decoded.width().min(decoded.height());
+    let square = decoded.crop_imm((decoded.width() - side) / 2, (decoded.height() - side) / 2, side, side);
+
+    AVATAR_SIZES
+        .iter()
+        .map(|&size| {
+            let mut data = vec![];
+            square
+                .resize_exact(size, size, FilterType::Lanczos3)
+                .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+                .map_err(|_| UploadError::Invalid)?;
+            Ok(AvatarImage { size, data })
+        })
+        .collect()
+}
@@ -0,0 +1,75 @@
+/// Matches `hashtag.name varchar(100)`.
+pub const HASHTAG_MAX_LEN: usize = 100;
+
+fn is_entity_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Words introduced by `sigil` in `body`, as `(start, end, word)` with char offsets
+/// covering the sigil. A sigil only starts a word when it does not follow a letter,
+/// digit or `_`, so `a#b` and `me@example.com` contain none.
+fn scan_entities(body: &str, sigil: char) -> Vec<(usize, usize, String)> {
+    let chars = body.chars().collect::<Vec<char>>();
+    let mut entities = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let starts_entity = chars[i] == sigil && (i == 0 || !is_entity_char(chars[i - 1]));
+        if !starts_entity {
+            i += 1;
+            continue;
+        }
+        let end = (i + 1..chars.len()).find(|&j| !is_entity_char(chars[j])).unwrap_or(chars.len());
+        if end > i + 1 {
+            entities.push((i, end, chars[i + 1..end].iter().collect()));
+        }
+        i = end.max(i + 1);
+    }
+    entities
+}
+
+/// Lowercases a tag, with or without its `#`, for storage and lookup. All-digit tags
+/// such as `#1` are not hashtags, and neither is anything longer than `HASHTAG_MAX_LEN`
+/// once lowercased, which can add chars (`İ` becomes `i̇`).
+pub fn normalize_hashtag(tag: &str) -> Option<String> {
+    let tag = tag.strip_prefix('#').unwrap_or(tag);
+    let valid = !tag.is_empty()
+        && tag.chars().all(is_entity_char)
+        && !tag.chars().all(|c| c.is_ascii_digit());
+    if !valid {
+        return None;
+    }
+    let tag = tag.to_lowercase();
+    if tag.chars().count() <= HASHTAG_MAX_LEN {
+        Some(tag)
+    } else {
+        None
+    }
+}
+
+/// Normalized hashtags used in `body`, each once, in order of first use.
+pub fn extract_hashtags(body: &str) -> Vec<String> {
+    let mut hashtags: Vec<String> = vec![];
+    for (_, _, word) in scan_entities(body, '#') {
+        if let Some(tag) = normalize_hashtag(&word) {
+            if !hashtags.contains(&tag) {
+                hashtags.push(tag);
+            }
+        }
+    }
+    hashtags
+}
+
+/// An `@user_name` in a message body. `start` and `end` are char offsets covering the `@`.
+pub struct MentionRange {
+    pub start: i32,
+    pub end: i32,
+    pub user_name: String
+}
+
+/// Every `@word` in `body`, in order. Whether a word names a profile is up to the caller.
+pub fn extract_mentions(body: &str) -> Vec<MentionRange> {
+    scan_entities(body, '@')
+        .into_iter()
+        .map(|(start, end, user_name)| MentionRange { start: start as i32, end: end as i32, user_name })
+        .collect()
+}
@@ -0,0 +1,14 @@
+use std::env;
+use std::time::Duration;
+
+/// Reads how often a timer ticks from the environment variable `name`, counted in the
+/// unit `from_units` converts from. Timers can't tick at zero, so like a missing or
+/// unparsable value, zero leaves `default`.
+pub fn interval_from_env(name: &str, from_units: fn(u64) -> Duration, default: u64) -> Duration {
+    let units = env::var(name)
+        .ok()
+        .and_then(|units| units.parse::<u64>().ok())
+        .filter(|&units| units > 0)
+        .unwrap_or(default);
+    from_units(units)
+}
@@ -0,0 +1,36 @@
+use std::env;
+use std::time::Duration;
+use crate::lib::env_config::interval_from_env;
+
+const DEFAULT_EVENT_POLL_INTERVAL_MS: u64 = 5000;
+const DEFAULT_EVENT_RETENTION_SECS: i64 = 24 * 60 * 60;
+const DEFAULT_STREAM_HEARTBEAT_SECS: u64 = 30;
+
+/// Timing of the real-time event streams.
+#[derive(Clone)]
+pub struct EventConfig {
+    /// How often the `event` table is checked for rows whose notification was missed.
+    pub poll_interval: Duration,
+    /// How long events are kept for clients resuming a stream.
+    pub retention: chrono::Duration,
+    /// How often idle streams are pinged; a WebSocket that stays silent for two
+    /// heartbeats is closed.
+    pub heartbeat_interval: Duration
+}
+
+impl EventConfig {
+    pub fn from_env() -> Self {
+        let poll_interval = interval_from_env("EVENT_POLL_INTERVAL_MS", Duration::from_millis, DEFAULT_EVENT_POLL_INTERVAL_MS);
+        let retention = env::var("EVENT_RETENTION_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_EVENT_RETENTION_SECS);
+        let heartbeat_interval = interval_from_env("STREAM_HEARTBEAT_SECS", Duration::from_secs, DEFAULT_STREAM_HEARTBEAT_SECS);
+
+        Self {
+            poll_interval,
+            retention: chrono::Duration::seconds(retention),
+            heartbeat_interval
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::postgres::PgListener;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+use crate::controllers::notification::notification_models::Notification;
+use crate::lib::app_state::AppState;
+use crate::lib::event_config::EventConfig;
+use crate::lib::jobs::JOB_CHANNEL;
+use crate::repository::event::event_models::{EventKind, EventQueryResult};
+use crate::repository::event::event_repo::EventRepo;
+use crate::repository::message::message_repo::MessageRepo;
+use crate::repository::notification::notification_repo::NotificationRepo;
+use crate::repository::repo::Repository;
+
+/// Channel notified with the id of each new `event` row.
+pub const STREAM_EVENT_CHANNEL: &str = "event";
+/// Channel carrying `DomainEvent`s as JSON.
+pub const DOMAIN_EVENT_CHANNEL: &str = "domain_event";
+const EVENT_HUB_CAPACITY: usize = 1024;
+const EVENT_BATCH_SIZE: i64 = 500;
+/// Events buffered per subscriber before it is considered slow.
+const SUBSCRIBER_BUFFER: usize = 64;
+const EVENT_CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// An event as pushed to clients, the same over every transport. `data` is the new
+/// message, `{ message_id, likes }`, or `{ notification, unread_count }` depending on
+/// `kind`. Clients resume a stream from the last `id` they saw.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StreamEvent {
+    pub id: i64,
+    pub kind: EventKind,
+    pub data: Value,
+    #[serde(skip)]
+    pub audience: Vec<i64>
+}
+
+/// Something that happened, for code in any instance that reacts to changes. Raised by
+/// triggers when the change commits and relayed to every instance over `LISTEN/NOTIFY`,
+/// so each instance sees each event once, whichever instance made the change.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DomainEvent {
+    MessageCreated { message_id: i64, author_id: i64 },
+    FollowCreated { follower_id: i64, following_id: i64 },
+    LikeAdded { message_id: i64, profile_id: i64 }
+}
+
+/// The `type` of each `DomainEvent`, which webhooks subscribe by.
+pub const DOMAIN_EVENT_TYPES: [&str; 3] = ["message_created", "follow_created", "like_added"];
+
+/// The in-process event bus: fans stream events out to the clients connected to this
+/// instance and domain events out to whatever subscribed to them.
+#[derive(Clone)]
+pub struct EventHub {
+    sender: broadcast::Sender<Arc<StreamEvent>>,
+    domain_sender: broadcast::Sender<DomainEvent>,
+    pub config: EventConfig
+}
+
+impl EventHub {
+    pub fn new(config: EventConfig) -> Self {
+        let (sender, _) = broadcast::channel(EVENT_HUB_CAPACITY);
+        let (domain_sender, _) = broadcast::channel(EVENT_HUB_CAPACITY);
+        Self { sender, domain_sender, config }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<StreamEvent>> {
+        self.sender.subscribe()
+    }
+
+    pub fn subscribe_domain(&self) -> broadcast::Receiver<DomainEvent> {
+        self.domain_sender.subscribe()
+    }
+
+    fn send(&self, event: StreamEvent) {
+        // nobody listening is fine
+        _ = self.sender.send(Arc::new(event));
+    }
+
+    fn send_domain(&self, event: DomainEvent) {
+        _ = self.domain_sender.send(event);
+    }
+}
+
+/// Relays events into the hub. New `event` rows are read in id order whenever Postgres
+/// notifies about one, and every `poll_interval` in case a notification was lost while
+/// the listening connection was down. Domain events are passed on as they arrive, and
+/// newly enqueued jobs wake the idle job workers.
+/// Events older than the retention period are deleted now and then.
+pub fn spawn_event_pump(app_state: Arc<AppState>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let pool = app_state.repo.get_pool();
+        let poll_interval = app_state.events.config.poll_interval;
+        let mut last_id = loop {
+            match app_state.repo.select_latest_event_id(pool).await {
+                Ok(id) => break id,
+                Err(e) => {
+                    error!("Error failed select_latest_event_id {:?}", e);
+                    tokio::time::sleep(poll_interval).await;
+                }
+            }
+        };
+        let mut listener = match listen_for_events(pool).await {
+            Ok(listener) => Some(listener),
+            Err(e) => {
+                error!("Error failed to listen for events, falling back to polling {:?}", e);
+                None
+            }
+        };
+
+        let mut interval = tokio::time::interval(poll_interval);
+        let mut last_cleanup = Instant::now();
+        loop {
+            let notification = match listener.as_mut() {
+                Some(listener) => tokio::select! {
+                    received = listener.recv() => Some(received),
+                    _ = interval.tick() => None
+                },
+                None => {
+                    interval.tick().await;
+                    None
+                }
+            };
+            match notification {
+                Some(Ok(notification)) if notification.channel() == DOMAIN_EVENT_CHANNEL => {
+                    match serde_json::from_str::<DomainEvent>(notification.payload()) {
+                        Ok(event) => app_state.events.send_domain(event),
+                        Err(e) => error!("Error failed to parse domain event {} {:?}", notification.payload(), e)
+                    }
+                    continue;
+                },
+                Some(Ok(notification)) if notification.channel() == JOB_CHANNEL => {
+                    app_state.jobs.wake();
+                    continue;
+                },
+                Some(Err(e)) => {
+                    // the listener reconnects on the next recv
+                    error!("Error failed to receive event notification {:?}", e);
+                    tokio::time::sleep(poll_interval).await;
+                },
+                _ => {}
+            }
+            last_id = pump_events(&app_state, last_id).await;
+
+            if last_cleanup.elapsed() >= EVENT_CLEANUP_INTERVAL {
+                last_cleanup = Instant::now();
+                match app_state.repo.delete_events_before(pool, Utc::now() - app_state.events.config.retention).await {
+                    Ok(0) => {}
+                    Ok(deleted) => info!("Deleted {} expired events", deleted),
+                    Err(e) => error!("Error failed delete_events_before {:?}", e)
+                }
+            }
+        }
+    })
+}
+
+async fn listen_for_events(pool: &sqlx::PgPool) -> Result<PgListener, sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen_all([STREAM_EVENT_CHANNEL, DOMAIN_EVENT_CHANNEL, JOB_CHANNEL]).await?;
+    Ok(listener)
+}
+
+/// Publishes every event after `last_id` and returns the id of the last one. Events are
+/// numbered first, so that ones whose transactions just finished are included.
+async fn pump_events(app_state: &AppState, mut last_id: i64) -> i64 {
+    if let Err(e) = app_state.repo.sequence_events(app_state.repo.get_pool()).await {
+        error!("Error failed sequence_events {:?}", e);
+    }
+    loop {
+        let events = match app_state.repo.select_events_after(app_state.repo.get_pool(), last_id, EVENT_BATCH_SIZE).await {
+            Ok(events) => events,
+            Err(e) => {
+                error!("Error failed select_events_after {:?}", e);
+                return last_id;
+            }
+        };
+        let batch_len = events.len();
+        for event in events {
+            last_id = event.id;
+            if let Some(event) = hydrate_event(app_state, event).await {
+                app_state.events.send(event);
+            }
+        }
+
+        if batch_len < EVENT_BATCH_SIZE as usize {
+            return last_id;
+        }
+    }
+}
+
+/// Loads what an event refers to. Events about something deleted since are dropped.
+async fn hydrate_event(app_state: &AppState, event: EventQueryResult) -> Option<StreamEvent> {
+    let pool = app_state.repo.get_pool();
+    let id_in_data = |field: &str| event.data.get(field).and_then(Value::as_i64);
+
+    let data = match event.kind {
+        EventKind::Message => {
+            match app_state.repo.select_message(pool, id_in_data("message_id")?).await {
+                Ok(message) => serde_json::to_value(message?).ok()?,
+                Err(e) => {
+                    error!("Error failed select_message for event {} {:?}", event.id, e);
+                    return None;
+                }
+            }
+        },
+        EventKind::Likes => event.data.0.clone(),
+        EventKind::Notification => {
+            let notification = match app_state.repo.select_notification(pool, id_in_data("notification_id")?).await {
+                Ok(notification) => notification?,
+                Err(e) => {
+                    error!("Error failed select_notification for event {} {:?}", event.id, e);
+                    return None;
+                }
+            };
+            let recipient_id = *event.audience.first()?;
+            let unread_count = app_state.repo.select_unread_notification_count(pool, recipient_id).await.ok()?;
+            json!({ "notification": Notification::from(notification), "unread_count": unread_count })
+        }
+    };
+
+    Some(StreamEvent { id: event.id, kind: event.kind, data, audience: event.audience })
+}
+
+/// Events of the given kinds meant for `profile_id`, in id order. With `last_event_id`
+/// the stream first replays what was missed since that event; without it the stream
+/// starts at the newest event recorded when this returns.
+///
+/// Each subscriber has a small buffer. One that falls so far behind that the hub
+/// drops events for it catches up from the `event` table instead, so a slow reader
+/// costs database reads but never loses events.
+pub async fn subscribe_events(
+    app_state: Arc<AppState>,
+    profile_id: i64,
+    kinds: &'static [EventKind],
+    last_event_id: Option<i64>
+) -> Result<mpsc::Receiver<Arc<StreamEvent>>, sqlx::Error> {
+    // subscribe before reading the position, so nothing falls between the two
+    let mut live = app_state.events.subscribe();
+    let mut position = match last_event_id {
+        Some(id) => id,
+        None => app_state.repo.select_latest_event_id(app_state.repo.get_pool()).await?
+    };
+    let (sender, receiver) = mpsc::channel(SUBSCRIBER_BUFFER);
+
+    tokio::spawn(async move {
+        let Some(replayed_to) = replay_events(&app_state, &sender, profile_id, kinds, position).await else {
+            return;
+        };
+        position = replayed_to;
+
+        loop {
+            let received = tokio::select! {
+                _ = sender.closed() => return,
+                received = live.recv() => received
+            };
+            match received {
+                Ok(event) => {
+                    if event.id <= position {
+                        continue;
+                    }
+                    position = event.id;
+                    if kinds.contains(&event.kind) && event.audience.contains(&profile_id) && sender.send(event).await.is_err() {
+                        return;
+                    }
+                },
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    match replay_events(&app_state, &sender, profile_id, kinds, position).await {
+                        Some(replayed_to) => position = replayed_to,
+                        None => return
+                    }
+                },
+                Err(broadcast::error::RecvError::Closed) => return
+            }
+        }
+    });
+
+    Ok(receiver)
+}
+
+/// Sends stored events after `after_id` and returns the id it got to, or `None` once
+/// the subscriber is gone.
+async fn replay_events(
+    app_state: &AppState,
+    sender: &mpsc::Sender<Arc<StreamEvent>>,
+    profile_id: i64,
+    kinds: &[EventKind],
+    mut after_id: i64
+) -> Option<i64> {
+    loop {
+        let events = match app_state.repo.select_profile_events_after(
+            app_state.repo.get_pool(),
+            profile_id,
+            after_id,
+            EVENT_BATCH_SIZE
+        ).await {
+            Ok(events) => events,
+            Err(e) => {
+                error!("Error failed select_profile_events_after {:?}", e);
+                return None;
+            }
+        };
+        let batch_len = events.len();
+        for event in events {
+            after_id = event.id;
+            if !kinds.contains(&event.kind) {
+                continue;
+            }
+            if let Some(event) = hydrate_event(app_state, event).await {
+                sender.send(Arc::new(event)).await.ok()?;
+            }
+        }
+
+        if batch_len < EVENT_BATCH_SIZE as usize {
+            return Some(after_id);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use std::io::Cursor;
+use image::{DynamicImage, ImageFormat, ImageReader, Limits};
+
+const UPLOAD_MAX_DIMENSION: u32 = 8192;
+const UPLOAD_MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+
+pub enum UploadError {
+    TooLarge,
+    UnsupportedFormat,
+    Invalid
+}
+
+/// Decodes an uploaded PNG, JPEG or WebP of at most `max_bytes`. Dimensions and decoder
+/// allocations are capped, so that a small file can't claim a huge image. Callers
+/// re-encode the returned pixels, which drops EXIF and other metadata, such as a photo's
+/// location, that would otherwise be published.
+pub fn decode_upload(upload: &[u8], max_bytes: usize) -> Result<(ImageFormat, DynamicImage), UploadError> {
+    if upload.len() > max_bytes {
+        return Err(UploadError::TooLarge);
+    }
+    let format = match image::guess_format(upload) {
+        Ok(format @ (ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP)) => format,
+        _ => return Err(UploadError::UnsupportedFormat)
+    };
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(UPLOAD_MAX_DIMENSION);
+    limits.max_image_height = Some(UPLOAD_MAX_DIMENSION);
+    limits.max_alloc = Some(UPLOAD_MAX_DECODE_ALLOC);
+    let mut reader = ImageReader::with_format(Cursor::new(upload), format);
+    reader.limits(limits);
+    let decoded = reader.decode().map_err(|_| UploadError::Invalid)?;
+    if decoded.width() == 0 || decoded.height() == 0 {
+        return Err(UploadError::Invalid);
+    }
+    Ok((format, decoded))
+}
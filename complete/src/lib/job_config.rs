@@ -0,0 +1,54 @@
+use std::env;
+use std::time::Duration;
+use crate::lib::env_config::interval_from_env;
+use crate::lib::jobs::backoff_delay;
+
+const DEFAULT_JOB_WORKERS: usize = 4;
+const DEFAULT_JOB_POLL_INTERVAL_MS: u64 = 5000;
+const DEFAULT_JOB_RETRY_BASE_SECS: u64 = 10;
+const DEFAULT_JOB_LEASE_SECS: u64 = 5 * 60;
+const MAX_JOB_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// Size and timing of the background job workers.
+#[derive(Clone)]
+pub struct JobConfig {
+    /// Workers per instance, each running one job at a time.
+    pub workers: usize,
+    /// How often idle workers look for jobs that came due without a notification,
+    /// such as scheduled ones.
+    pub poll_interval: Duration,
+    /// Wait before the first retry of a failed job; it doubles with every further attempt.
+    pub retry_base: Duration,
+    /// How long a job may run. Past it the run is abandoned and the job can be claimed again.
+    pub lease: Duration
+}
+
+impl JobConfig {
+    pub fn from_env() -> Self {
+        let workers = env::var("JOB_WORKERS")
+            .ok()
+            .and_then(|workers| workers.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_JOB_WORKERS);
+        let poll_interval = interval_from_env("JOB_POLL_INTERVAL_MS", Duration::from_millis, DEFAULT_JOB_POLL_INTERVAL_MS);
+        let retry_base = env::var("JOB_RETRY_BASE_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_JOB_RETRY_BASE_SECS);
+        let lease = env::var("JOB_LEASE_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_JOB_LEASE_SECS);
+
+        Self {
+            workers,
+            poll_interval,
+            retry_base: Duration::from_secs(retry_base),
+            lease: Duration::from_secs(lease)
+        }
+    }
+
+    /// How long to wait after the given number of failed attempts, at most an hour.
+    pub fn retry_delay(&self, attempts: i32) -> Duration {
+        backoff_delay(self.retry_base, attempts, MAX_JOB_RETRY_DELAY)
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+use crate::lib::app_state::AppState;
+use crate::lib::job_config::JobConfig;
+use crate::lib::media_backfill::BackfillLegacyMedia;
+use crate::lib::media_jobs::DeleteStoredMedia;
+use crate::lib::media_store::MediaStoreError;
+use crate::lib::message_purge::PurgeDeletedMessages;
+use crate::repository::job::job_models::ClaimedJob;
+use crate::repository::job::job_repo::JobRepo;
+use crate::repository::repo::Repository;
+
+/// Channel notified with the kind of each job that is due right away.
+pub const JOB_CHANNEL: &str = "job";
+pub const DEFAULT_JOB_MAX_ATTEMPTS: i32 = 5;
+/// Matches `job.last_error varchar(1000)`.
+const JOB_ERROR_MAX_LEN: usize = 1000;
+
+/// Work to do outside of a request. The job itself is the payload: it is stored as JSON
+/// when enqueued and deserialized again by whichever worker runs it, so it should hold
+/// ids rather than loaded rows.
+#[async_trait]
+pub trait Job: Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// Identifies the job type in the `job` table; must stay stable across releases.
+    const KIND: &'static str;
+    /// Runs before the job goes to the dead letters.
+    const MAX_ATTEMPTS: i32 = DEFAULT_JOB_MAX_ATTEMPTS;
+
+    /// Jobs may run more than once, for instance when a worker dies after the work was
+    /// done but before recording it, so running has to be safe to repeat.
+    async fn run(&self, app_state: &AppState) -> Result<(), JobError>;
+}
+
+#[derive(Debug)]
+pub enum JobError {
+    /// Worth retrying later.
+    Failed(String),
+    /// Will never succeed, so the job goes straight to the dead letters.
+    Invalid(String)
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::Failed(detail) => write!(f, "{}", detail),
+            JobError::Invalid(detail) => write!(f, "invalid: {}", detail)
+        }
+    }
+}
+
+impl From<sqlx::Error> for JobError {
+    fn from(value: sqlx::Error) -> Self {
+        JobError::Failed(format!("{:?}", value))
+    }
+}
+
+impl From<MediaStoreError> for JobError {
+    fn from(value: MediaStoreError) -> Self {
+        JobError::Failed(format!("{:?}", value))
+    }
+}
+
+/// Wakes this instance's idle workers when a job is enqueued anywhere.
+#[derive(Clone)]
+pub struct JobQueue {
+    wake: Arc<Notify>,
+    pub config: JobConfig
+}
+
+impl JobQueue {
+    pub fn new(config: JobConfig) -> Self {
+        Self { wake: Arc::new(Notify::new()), config }
+    }
+
+    pub(crate) fn wake(&self) {
+        self.wake.notify_waiters();
+    }
+}
+
+type JobRunner = Arc<dyn Fn(Arc<AppState>, serde_json::Value) -> BoxFuture<'static, Result<(), JobError>> + Send + Sync>;
+
+/// The job types a worker pool runs. Workers only claim kinds they know, so an
+/// instance that predates a new job type leaves those jobs to the ones that know it.
+#[derive(Clone, Default)]
+pub struct JobRegistry {
+    runners: HashMap<&'static str, JobRunner>
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<J: Job>(mut self) -> Self {
+        self.runners.insert(J::KIND, Arc::new(|app_state, payload| Box::pin(async move {
+            let job = serde_json::from_value::<J>(payload).map_err(|e| JobError::Invalid(e.to_string()))?;
+            job.run(&app_state).await
+        })));
+        self
+    }
+
+    fn kinds(&self) -> Vec<String> {
+        self.runners.keys().map(|kind| kind.to_string()).collect()
+    }
+}
+
+/// Every job type the server runs.
+pub fn app_jobs() -> JobRegistry {
+    JobRegistry::new()
+        .register::<DeleteStoredMedia>()
+        .register::<BackfillLegacyMedia>()
+        .register::<PurgeDeletedMessages>()
+}
+
+/// Queues `job` to run as soon as a worker is free and returns its id.
+pub async fn enqueue<J: Job>(app_state: &AppState, job: &J) -> Result<i64, sqlx::Error> {
+    enqueue_at(app_state, job, Utc::now()).await
+}
+
+/// Queues `job` to run no earlier than `run_at` and returns its id.
+pub async fn enqueue_at<J: Job>(app_state: &AppState, job: &J, run_at: DateTime<Utc>) -> Result<i64, sqlx::Error> {
+    let payload = serde_json::to_value(job).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+    app_state.repo.insert_job(app_state.repo.get_pool(), J::KIND, &payload, J::MAX_ATTEMPTS, run_at).await
+}
+
+/// Queues `job` to run no earlier than `run_at`, unless a job of its kind is pending
+/// already, and returns the new job's id if there is one. Jobs that run on a schedule
+/// queue their next run with this, so however many instances start the schedule only one
+/// run is ever waiting; should two get queued in a race, the next run of either finds the
+/// other pending and the schedule is back to one.
+pub async fn enqueue_unless_pending<J: Job>(app_state: &AppState, job: &J, run_at: DateTime<Utc>) -> Result<Option<i64>, sqlx::Error> {
+    let payload = serde_json::to_value(job).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+    app_state.repo.insert_job_unless_pending(app_state.repo.get_pool(), J::KIND, &payload, J::MAX_ATTEMPTS, run_at).await
+}
+
+/// Exponential backoff shared by everything that retries: `base` after the first failed
+/// attempt, doubling with every further one, and never more than `max`.
+pub fn backoff_delay(base: Duration, attempts: i32, max: Duration) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    base.saturating_mul(2u32.pow(exponent)).min(max)
+}
+
+/// Starts `config.workers` workers. Each runs one job at a time, looking for the next
+/// as soon as it is done and otherwise when a job is enqueued or every `poll_interval`.
+pub fn spawn_job_workers(app_state: Arc<AppState>, registry: JobRegistry) -> Vec<JoinHandle<()>> {
+    let registry = Arc::new(registry);
+    (0..app_state.jobs.config.workers).map(|_| {
+        let app_state = Arc::clone(&app_state);
+        let registry = Arc::clone(&registry);
+        tokio::spawn(async move {
+            loop {
+                // register interest before looking, so a wake up in between is not missed
+                let woken = app_state.jobs.wake.notified();
+                tokio::pin!(woken);
+                woken.as_mut().enable();
+                match run_next_job(&app_state, &registry).await {
+                    Ok(true) => continue,
+                    Ok(false) => {},
+                    Err(e) => error!("Error failed run_next_job {:?}", e)
+                }
+                tokio::select! {
+                    _ = woken => {},
+                    _ = tokio::time::sleep(app_state.jobs.config.poll_interval) => {}
+                }
+            }
+        })
+    }).collect()
+}
+
+/// Runs due jobs one after another until none are left and returns how many ran.
+pub async fn run_due_jobs(app_state: &Arc<AppState>, registry: &JobRegistry) -> Result<usize, sqlx::Error> {
+    let mut ran = 0;
+    while run_next_job(app_state, registry).await? {
+        ran += 1;
+    }
+    Ok(ran)
+}
+
+/// Claims and runs a single job. False when none was due.
+async fn run_next_job(app_state: &Arc<AppState>, registry: &JobRegistry) -> Result<bool, sqlx::Error> {
+    let pool = app_state.repo.get_pool();
+    let config = &app_state.jobs.config;
+    let lease = chrono::Duration::from_std(config.lease).unwrap_or_default();
+    let Some(job) = app_state.repo.claim_job(pool, &registry.kinds(), Utc::now() + lease).await? else {
+        return Ok(false);
+    };
+
+    let outcome = match registry.runners.get(job.kind.as_str()) {
+        Some(runner) => run_job(Arc::clone(app_state), runner, &job).await,
+        None => Err(JobError::Invalid(format!("no runner for {}", job.kind)))
+    };
+    let recorded = match outcome {
+        Ok(()) => app_state.repo.complete_job(pool, job.id, job.attempts).await?,
+        Err(e) => {
+            let detail = e.to_string().chars().take(JOB_ERROR_MAX_LEN).collect::<String>();
+            match e {
+                JobError::Failed(_) if job.attempts < job.max_attempts => {
+                    warn!("Job {} {} failed, attempt {} of {}: {}", job.kind, job.id, job.attempts, job.max_attempts, detail);
+                    let run_at = Utc::now() + chrono::Duration::from_std(config.retry_delay(job.attempts)).unwrap_or_default();
+                    app_state.repo.retry_job(pool, job.id, job.attempts, &detail, run_at).await?
+                },
+                _ => {
+                    error!("Job {} {} is dead after {} attempts: {}", job.kind, job.id, job.attempts, detail);
+                    app_state.repo.bury_job(pool, job.id, job.attempts, &detail).await?
+                }
+            }
+        }
+    };
+    if !recorded {
+        warn!("Job {} {} outlived its lease and was claimed again", job.kind, job.id);
+    }
+    Ok(true)
+}
+
+/// Runs the job on its own task so a panic fails the job instead of the worker, and
+/// abandons it once the lease is up.
+async fn run_job(app_state: Arc<AppState>, runner: &JobRunner, job: &ClaimedJob) -> Result<(), JobError> {
+    let lease = app_state.jobs.config.lease;
+    let mut run = tokio::spawn(runner(app_state, job.payload.0.clone()));
+    match tokio::time::timeout(lease, &mut run).await {
+        Ok(Ok(outcome)) => outcome,
+        Ok(Err(e)) => Err(JobError::Failed(format!("panicked: {}", e))),
+        Err(_) => {
+            run.abort();
+            Err(JobError::Failed(format!("timed out after {:?}", lease)))
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use crate::lib::app_state::AppState;
+use crate::lib::attachment::process_attachment;
+use crate::lib::avatar::{process_avatar, AVATAR_CONTENT_TYPE, AVATAR_DEFAULT_SIZE};
+use crate::lib::jobs::{Job, JobError};
+use crate::lib::media_jobs::{delete_stored_media, discard_media};
+use crate::lib::media_store::generate_media_key;
+use crate::repository::media::media_models::LegacyMediaQueryResult;
+use crate::repository::media::media_repo::MediaRepo;
+use crate::repository::message::message_models::NewAttachment;
+use crate::repository::profile::profile_repo::ProfileAvatarFn;
+use crate::repository::repo::Repository;
+
+const LEGACY_MEDIA_BATCH: i64 = 50;
+
+/// Moves avatars and message images that were stored inline before the media store
+/// existed into it, processing them like fresh uploads. It is queued on every start,
+/// unless it is pending already, and finds nothing to do once the legacy tables are
+/// empty. Bytes that can't be decoded are left where they are for an operator to look at.
+#[derive(Serialize, Deserialize)]
+pub struct BackfillLegacyMedia;
+
+#[async_trait]
+impl Job for BackfillLegacyMedia {
+    const KIND: &'static str = "backfill_legacy_media";
+
+    async fn run(&self, app_state: &AppState) -> Result<(), JobError> {
+        let pool = app_state.repo.get_pool();
+        app_state.repo.delete_superseded_legacy_media(pool).await?;
+
+        let mut backfilled = 0;
+        let mut after = 0;
+        loop {
+            let avatars = app_state.repo.select_legacy_avatars(pool, after, LEGACY_MEDIA_BATCH).await?;
+            let Some(last) = avatars.last() else { break };
+            after = last.id;
+            for avatar in avatars {
+                backfilled += backfill_avatar(app_state, avatar).await? as usize;
+            }
+        }
+        let mut after = 0;
+        loop {
+            let images = app_state.repo.select_legacy_message_images(pool, after, LEGACY_MEDIA_BATCH).await?;
+            let Some(last) = images.last() else { break };
+            after = last.id;
+            for image in images {
+                backfilled += backfill_message_image(app_state, image).await? as usize;
+            }
+        }
+
+        if backfilled > 0 {
+            info!("Moved {} legacy avatars and message images into the media store", backfilled);
+        }
+        Ok(())
+    }
+}
+
+async fn backfill_avatar(app_state: &AppState, legacy: LegacyMediaQueryResult) -> Result<bool, JobError> {
+    let pool = app_state.repo.get_pool();
+    let Ok(images) = process_avatar(legacy.data).await else {
+        warn!("Legacy avatar of profile {} is not a usable image, leaving it in legacy_avatar", legacy.id);
+        return Ok(false);
+    };
+
+    let mut sizes: Vec<(i32, i64)> = vec![];
+    for image in images {
+        match store_legacy_media(app_state, legacy.owner_id, AVATAR_CONTENT_TYPE, image.data).await {
+            Ok(media_id) => sizes.push((image.size as i32, media_id)),
+            Err(e) => {
+                discard_media(app_state, &sizes.iter().map(|(_, media_id)| *media_id).collect::<Vec<i64>>()).await;
+                return Err(e);
+            }
+        }
+    }
+    let avatar_id = sizes.iter()
+        .find(|(size, _)| *size == AVATAR_DEFAULT_SIZE as i32)
+        .map(|(_, media_id)| *media_id)
+        .unwrap_or_default();
+
+    match app_state.repo.upsert_avatar(pool, legacy.id, avatar_id, sizes.clone()).await {
+        Ok(replaced_keys) => delete_stored_media(app_state, &replaced_keys.unwrap_or_default()).await,
+        Err(e) => {
+            discard_media(app_state, &sizes.iter().map(|(_, media_id)| *media_id).collect::<Vec<i64>>()).await;
+            return Err(e.into());
+        }
+    }
+    app_state.repo.delete_legacy_avatar(pool, legacy.id).await?;
+    Ok(true)
+}
+
+async fn backfill_message_image(app_state: &AppState, legacy: LegacyMediaQueryResult) -> Result<bool, JobError> {
+    let Ok(image) = process_attachment(legacy.data).await else {
+        warn!("Legacy image of message {} is not a usable image, leaving it in legacy_message_image", legacy.id);
+        return Ok(false);
+    };
+
+    let attachment = NewAttachment {
+        media_id: store_legacy_media(app_state, legacy.owner_id, image.content_type, image.data).await?,
+        alt_text: None,
+        width: image.width as i32,
+        height: image.height as i32
+    };
+    match app_state.repo.attach_legacy_message_image(app_state.repo.get_pool(), legacy.id, &attachment).await {
+        Ok(true) => Ok(true),
+        Ok(false) => {
+            discard_media(app_state, &[attachment.media_id]).await;
+            Ok(false)
+        }
+        Err(e) => {
+            discard_media(app_state, &[attachment.media_id]).await;
+            Err(e.into())
+        }
+    }
+}
+
+async fn store_legacy_media(app_state: &AppState, owner_id: i64, content_type: &str, data: Vec<u8>) -> Result<i64, JobError> {
+    let storage_key = generate_media_key();
+    let byte_size = data.len() as i64;
+    app_state.media.put(&storage_key, content_type, data).await?;
+    match app_state.repo.insert_media(app_state.repo.get_pool(), owner_id, &storage_key, content_type, byte_size).await {
+        Ok(media_id) => Ok(media_id),
+        Err(e) => {
+            delete_stored_media(app_state, &[storage_key]).await;
+            Err(e.into())
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use crate::lib::app_state::AppState;
+use crate::lib::jobs::{enqueue, Job, JobError};
+use crate::repository::media::media_repo::MediaRepo;
+use crate::repository::repo::Repository;
+
+/// Removes objects whose rows are already gone. It happens in the background, where
+/// a failing store is retried; only if the job can't be queued are the objects deleted
+/// right away, with failures logged rather than surfaced.
+pub async fn delete_stored_media(app_state: &AppState, storage_keys: &[String]) {
+    if storage_keys.is_empty() {
+        return;
+    }
+    let job = DeleteStoredMedia { storage_keys: storage_keys.to_vec() };
+    if let Err(e) = enqueue(app_state, &job).await {
+        error!("Error failed to enqueue media delete {:?}", e);
+        if let Err(e) = job.run(app_state).await {
+            error!("Error failed media delete {}", e);
+        }
+    }
+}
+
+/// Deletes objects from the media store. Objects already gone count as deleted.
+#[derive(Serialize, Deserialize)]
+pub struct DeleteStoredMedia {
+    pub storage_keys: Vec<String>
+}
+
+#[async_trait]
+impl Job for DeleteStoredMedia {
+    const KIND: &'static str = "delete_stored_media";
+
+    async fn run(&self, app_state: &AppState) -> Result<(), JobError> {
+        for storage_key in &self.storage_keys {
+            app_state.media.delete(storage_key).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Removes media that was stored but never ended up referenced, rows and objects both.
+pub async fn discard_media(app_state: &AppState, ids: &[i64]) {
+    match app_state.repo.delete_media(app_state.repo.get_pool(), ids).await {
+        Ok(storage_keys) => delete_stored_media(app_state, &storage_keys).await,
+        Err(e) => error!("Error failed delete_media {:?}", e)
+    }
+}
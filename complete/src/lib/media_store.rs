@@ -0,0 +1,246 @@
+use std::env;
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use async_trait::async_trait;
+use axum::body::Bytes;
+use chrono::Utc;
+use futures_util::{Stream, TryStreamExt};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use reqwest::{Client, StatusCode, Url};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+
+const DEFAULT_LOCAL_ROOT: &str = "media";
+const DEFAULT_S3_REGION: &str = "us-east-1";
+const S3_SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+pub type MediaStream = Pin<Box<dyn Stream<Item = Result<Bytes, io::Error>> + Send>>;
+
+#[derive(Debug)]
+pub enum MediaStoreError {
+    InvalidKey(String),
+    Io(io::Error),
+    Http(String)
+}
+
+impl From<io::Error> for MediaStoreError {
+    fn from(value: io::Error) -> Self {
+        MediaStoreError::Io(value)
+    }
+}
+
+impl From<reqwest::Error> for MediaStoreError {
+    fn from(value: reqwest::Error) -> Self {
+        MediaStoreError::Http(value.to_string())
+    }
+}
+
+/// Where uploaded media bytes live. Rows only keep the key an object was stored under,
+/// so objects are written once and never modified; replacing media means a new key.
+#[async_trait]
+pub trait MediaStore: Send + Sync {
+    async fn put(&self, key: &str, content_type: &str, data: Vec<u8>) -> Result<(), MediaStoreError>;
+    /// Streams an object back, or `None` if nothing is stored under `key`.
+    async fn get(&self, key: &str) -> Result<Option<MediaStream>, MediaStoreError>;
+    /// Deleting a missing object is not an error.
+    async fn delete(&self, key: &str) -> Result<(), MediaStoreError>;
+}
+
+/// Picks a backend from `MEDIA_STORE`: `local` (the default) or `s3`.
+pub fn media_store_from_env() -> Arc<dyn MediaStore> {
+    match env::var("MEDIA_STORE").unwrap_or_default().as_str() {
+        "s3" => Arc::new(S3MediaStore::from_env()),
+        _ => Arc::new(LocalMediaStore::new(
+            env::var("MEDIA_LOCAL_ROOT").unwrap_or(DEFAULT_LOCAL_ROOT.to_string())
+        ))
+    }
+}
+
+/// Keys are random so that they can't be guessed or enumerated from media ids.
+pub fn generate_media_key() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Only keys made of `[A-Za-z0-9_-]` segments separated by `/` are accepted, which keeps
+/// them from escaping the local root or needing escaping in an S3 path.
+fn validate_key(key: &str) -> Result<(), MediaStoreError> {
+    let valid = !key.is_empty() && key.split('/').all(|segment| {
+        !segment.is_empty() && segment.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    });
+    if valid {
+        Ok(())
+    } else {
+        Err(MediaStoreError::InvalidKey(key.to_string()))
+    }
+}
+
+pub struct LocalMediaStore {
+    root: PathBuf
+}
+
+impl LocalMediaStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, MediaStoreError> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl MediaStore for LocalMediaStore {
+    async fn put(&self, key: &str, _content_type: &str, data: Vec<u8>) -> Result<(), MediaStoreError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // write beside the final path and rename, so readers never see a partial object
+        let partial_path = path.with_extension("partial");
+        let mut file = tokio::fs::File::create(&partial_path).await?;
+        file.write_all(&data).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&partial_path, &path).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<MediaStream>, MediaStoreError> {
+        match tokio::fs::File::open(self.path(key)?).await {
+            Ok(file) => Ok(Some(Box::pin(ReaderStream::new(file)))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into())
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), MediaStoreError> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(())
+        }
+    }
+}
+
+/// Talks to any S3-compatible service using path-style URLs (`{endpoint}/{bucket}/{key}`)
+/// and SigV4 signed requests.
+pub struct S3MediaStore {
+    client: Client,
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String
+}
+
+impl S3MediaStore {
+    pub fn new(endpoint: &str, bucket: &str, region: &str, access_key_id: &str, secret_access_key: &str) -> Self {
+        Self {
+            client: Client::new(),
+            endpoint: Url::parse(endpoint).unwrap(),
+            bucket: bucket.to_string(),
+            region: region.to_string(),
+            access_key_id: access_key_id.to_string(),
+            secret_access_key: secret_access_key.to_string()
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(
+            &env::var("S3_ENDPOINT").unwrap(),
+            &env::var("S3_BUCKET").unwrap(),
+            &env::var("S3_REGION").unwrap_or(DEFAULT_S3_REGION.to_string()),
+            &env::var("S3_ACCESS_KEY_ID").unwrap(),
+            &env::var("S3_SECRET_ACCESS_KEY").unwrap()
+        )
+    }
+
+    fn request(&self, method: reqwest::Method, key: &str, payload: &[u8]) -> Result<reqwest::RequestBuilder, MediaStoreError> {
+        validate_key(key)?;
+        let path = format!("{}/{}/{}", self.endpoint.path().trim_end_matches('/'), self.bucket, key);
+        let mut url = self.endpoint.clone();
+        url.set_path(&path);
+
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(MediaStoreError::Http(format!("S3 endpoint {} has no host", self.endpoint)))
+        };
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = format!("{:x}", Sha256::digest(payload));
+
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, host, payload_hash, amz_date, S3_SIGNED_HEADERS, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{:x}",
+            amz_date, scope, Sha256::digest(canonical_request.as_bytes())
+        );
+        let signing_key = [self.region.as_str(), "s3", "aws4_request"].iter().fold(
+            hmac_sha256(format!("AWS4{}", self.secret_access_key).as_bytes(), date.as_bytes()),
+            |key, part| hmac_sha256(&key, part.as_bytes())
+        );
+        let signature = hmac_sha256(&signing_key, string_to_sign.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+
+        Ok(self.client.request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("authorization", format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key_id, scope, S3_SIGNED_HEADERS, signature
+            )))
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[async_trait]
+impl MediaStore for S3MediaStore {
+    async fn put(&self, key: &str, content_type: &str, data: Vec<u8>) -> Result<(), MediaStoreError> {
+        let response = self.request(reqwest::Method::PUT, key, &data)?
+            .header("content-type", content_type)
+            .body(data)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(MediaStoreError::Http(format!("S3 put of {} failed with {}", key, response.status())));
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<MediaStream>, MediaStoreError> {
+        let response = self.request(reqwest::Method::GET, key, &[])?.send().await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(Box::pin(
+                response.bytes_stream().map_err(io::Error::other)
+            ))),
+            status => Err(MediaStoreError::Http(format!("S3 get of {} failed with {}", key, status)))
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), MediaStoreError> {
+        let response = self.request(reqwest::Method::DELETE, key, &[])?.send().await?;
+        if !response.status().is_success() && response.status() != StatusCode::NOT_FOUND {
+            return Err(MediaStoreError::Http(format!("S3 delete of {} failed with {}", key, response.status())));
+        }
+        Ok(())
+    }
+}
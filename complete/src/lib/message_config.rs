@@ -0,0 +1,42 @@
+use std::env;
+use chrono::Duration;
+use crate::lib::env_config::interval_from_env;
+
+const DEFAULT_MESSAGE_EDIT_WINDOW_SECS: i64 = 60 * 60;
+const DEFAULT_MESSAGE_RETENTION_SECS: i64 = 30 * 24 * 60 * 60;
+const DEFAULT_MESSAGE_PURGE_INTERVAL_SECS: u64 = 60 * 60;
+
+/// Time limits on editing and deleting messages.
+#[derive(Clone)]
+pub struct MessageConfig {
+    /// How long after posting the author may still edit a message.
+    pub edit_window: Duration,
+    /// How long deleted messages keep their content before it is purged.
+    pub retention: Duration,
+    /// How often the purge job runs.
+    pub purge_interval: std::time::Duration
+}
+
+impl MessageConfig {
+    pub fn from_env() -> Self {
+        let edit_window = env::var("MESSAGE_EDIT_WINDOW_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_MESSAGE_EDIT_WINDOW_SECS);
+        let retention = env::var("MESSAGE_RETENTION_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_MESSAGE_RETENTION_SECS);
+        let purge_interval = interval_from_env(
+            "MESSAGE_PURGE_INTERVAL_SECS",
+            std::time::Duration::from_secs,
+            DEFAULT_MESSAGE_PURGE_INTERVAL_SECS
+        );
+
+        Self {
+            edit_window: Duration::seconds(edit_window),
+            retention: Duration::seconds(retention),
+            purge_interval
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use crate::lib::app_state::AppState;
+use crate::lib::jobs::{enqueue_unless_pending, Job, JobError};
+use crate::lib::media_jobs::delete_stored_media;
+use crate::repository::message::message_repo::MessageRepo;
+use crate::repository::repo::Repository;
+
+const MESSAGE_PURGE_BATCH_SIZE: i64 = 100;
+
+/// Purges the content of messages deleted longer than the retention period ago, every
+/// `purge_interval`. It is queued on every start and each run queues the next one, before
+/// purging so that a run that fails for good doesn't end the schedule.
+#[derive(Serialize, Deserialize)]
+pub struct PurgeDeletedMessages;
+
+#[async_trait]
+impl Job for PurgeDeletedMessages {
+    const KIND: &'static str = "purge_deleted_messages";
+
+    async fn run(&self, app_state: &AppState) -> Result<(), JobError> {
+        let next_run_at = Utc::now() + Duration::from_std(app_state.messages.purge_interval).unwrap_or_default();
+        enqueue_unless_pending(app_state, &PurgeDeletedMessages, next_run_at).await?;
+
+        let purged = purge_deleted_messages(app_state, app_state.messages.retention).await?;
+        if purged > 0 {
+            info!("Purged {} deleted messages", purged);
+        }
+        Ok(())
+    }
+}
+
+/// Purges in batches until nothing past retention is left and returns how many
+/// messages were purged.
+pub async fn purge_deleted_messages(app_state: &AppState, retention: Duration) -> Result<usize, sqlx::Error> {
+    let deleted_before = Utc::now() - retention;
+    let mut purged = 0;
+    loop {
+        let batch = app_state.repo.purge_deleted_messages(
+            app_state.repo.get_pool(),
+            deleted_before,
+            MESSAGE_PURGE_BATCH_SIZE
+        ).await?;
+        delete_stored_media(app_state, &batch.storage_keys).await;
+        purged += batch.message_count;
+
+        if batch.message_count < MESSAGE_PURGE_BATCH_SIZE as usize {
+            return Ok(purged);
+        }
+    }
+}
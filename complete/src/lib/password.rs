@@ -0,0 +1,47 @@
+use std::sync::LazyLock;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+
+pub const PASSWORD_MIN_LEN: usize = 8;
+pub const PASSWORD_MAX_LEN: usize = 128;
+
+/// Hash verified against when a login names an unknown user, so that a missing
+/// account costs the same time as a wrong password.
+static DUMMY_HASH: LazyLock<String> = LazyLock::new(|| {
+    hash_password_blocking("dummy-password-for-timing").unwrap()
+});
+
+pub fn is_valid_password(password: &str) -> bool {
+    let len = password.chars().count();
+    (PASSWORD_MIN_LEN..=PASSWORD_MAX_LEN).contains(&len)
+}
+
+pub async fn hash_password(password: String) -> Result<String, argon2::password_hash::Error> {
+    tokio::task::spawn_blocking(move || hash_password_blocking(&password))
+        .await
+        .unwrap_or(Err(argon2::password_hash::Error::Crypto))
+}
+
+/// Verifies `password` against `password_hash`. When `password_hash` is `None`
+/// a dummy hash is checked instead and the result is always `false`.
+pub async fn verify_password(password: String, password_hash: Option<String>) -> bool {
+    tokio::task::spawn_blocking(move || {
+        let is_known_user = password_hash.is_some();
+        let hash = password_hash.unwrap_or_else(|| DUMMY_HASH.clone());
+        let is_match = match PasswordHash::new(&hash) {
+            Ok(parsed) => Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok(),
+            Err(_) => false
+        };
+        is_known_user && is_match
+    })
+    .await
+    .unwrap_or(false)
+}
+
+fn hash_password_blocking(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+}
@@ -0,0 +1,76 @@
+use std::env;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const DEFAULT_ACCESS_TOKEN_TTL_SECS: i64 = 15 * 60;
+const DEFAULT_REFRESH_TOKEN_TTL_SECS: i64 = 30 * 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize)]
+pub struct AccessClaims {
+    pub sub: String,
+    pub iat: i64,
+    pub exp: i64
+}
+
+/// Signing keys and lifetimes for access and refresh tokens.
+#[derive(Clone)]
+pub struct TokenConfig {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    pub access_ttl: Duration,
+    pub refresh_ttl: Duration
+}
+
+impl TokenConfig {
+    pub fn from_env() -> Self {
+        let secret = env::var("JWT_SECRET").unwrap();
+        let access_ttl = env::var("ACCESS_TOKEN_TTL_SECS")
+            .ok()
+            .and_then(|ttl| ttl.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_ACCESS_TOKEN_TTL_SECS);
+        let refresh_ttl = env::var("REFRESH_TOKEN_TTL_SECS")
+            .ok()
+            .and_then(|ttl| ttl.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_REFRESH_TOKEN_TTL_SECS);
+
+        Self {
+            encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+            decoding_key: DecodingKey::from_secret(secret.as_bytes()),
+            access_ttl: Duration::seconds(access_ttl),
+            refresh_ttl: Duration::seconds(refresh_ttl)
+        }
+    }
+
+    pub fn issue_access_token(&self, profile_id: i64) -> Result<String, jsonwebtoken::errors::Error> {
+        let now = Utc::now();
+        let claims = AccessClaims {
+            sub: profile_id.to_string(),
+            iat: now.timestamp(),
+            exp: (now + self.access_ttl).timestamp()
+        };
+        encode(&Header::default(), &claims, &self.encoding_key)
+    }
+
+    /// Returns the profile id of a valid, unexpired access token.
+    pub fn verify_access_token(&self, token: &str) -> Option<i64> {
+        decode::<AccessClaims>(token, &self.decoding_key, &Validation::default())
+            .ok()
+            .and_then(|data| data.claims.sub.parse::<i64>().ok())
+    }
+}
+
+/// Refresh tokens are opaque random strings; only their hash is stored.
+pub fn generate_opaque_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn hash_opaque_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
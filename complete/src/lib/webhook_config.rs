@@ -0,0 +1,69 @@
+use std::env;
+use std::time::Duration;
+use crate::lib::env_config::interval_from_env;
+use crate::lib::jobs::backoff_delay;
+
+const DEFAULT_WEBHOOK_RETRY_BASE_SECS: u64 = 30;
+const DEFAULT_WEBHOOK_MAX_ATTEMPTS: i32 = 8;
+const DEFAULT_WEBHOOK_DISABLE_AFTER_FAILURES: i32 = 20;
+const DEFAULT_WEBHOOK_POLL_INTERVAL_MS: u64 = 5000;
+const DEFAULT_WEBHOOK_TIMEOUT_SECS: u64 = 10;
+const MAX_WEBHOOK_RETRY_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Retry and give-up policy of webhook deliveries.
+#[derive(Clone)]
+pub struct WebhookConfig {
+    /// Wait before the first retry; it doubles with every further attempt.
+    pub retry_base: Duration,
+    /// Attempts per delivery before it is marked failed.
+    pub max_attempts: i32,
+    /// Failed attempts in a row, over all its deliveries, after which a webhook is disabled.
+    pub disable_after_failures: i32,
+    /// How often due retries are looked for when no new event wakes the worker.
+    pub poll_interval: Duration,
+    pub request_timeout: Duration,
+    /// Lets webhooks target loopback and private addresses, which are refused otherwise
+    /// so that the webhook API can't be used to reach internal services. Only for
+    /// development and tests.
+    pub allow_private_targets: bool
+}
+
+impl WebhookConfig {
+    pub fn from_env() -> Self {
+        let retry_base = env::var("WEBHOOK_RETRY_BASE_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_WEBHOOK_RETRY_BASE_SECS);
+        let max_attempts = env::var("WEBHOOK_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|attempts| attempts.parse::<i32>().ok())
+            .unwrap_or(DEFAULT_WEBHOOK_MAX_ATTEMPTS);
+        let disable_after_failures = env::var("WEBHOOK_DISABLE_AFTER_FAILURES")
+            .ok()
+            .and_then(|failures| failures.parse::<i32>().ok())
+            .unwrap_or(DEFAULT_WEBHOOK_DISABLE_AFTER_FAILURES);
+        let poll_interval = interval_from_env("WEBHOOK_POLL_INTERVAL_MS", Duration::from_millis, DEFAULT_WEBHOOK_POLL_INTERVAL_MS);
+        let request_timeout = env::var("WEBHOOK_TIMEOUT_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_WEBHOOK_TIMEOUT_SECS);
+        let allow_private_targets = env::var("WEBHOOK_ALLOW_PRIVATE_TARGETS")
+            .ok()
+            .and_then(|allow| allow.parse::<bool>().ok())
+            .unwrap_or(false);
+
+        Self {
+            retry_base: Duration::from_secs(retry_base),
+            max_attempts,
+            disable_after_failures,
+            poll_interval,
+            request_timeout: Duration::from_secs(request_timeout),
+            allow_private_targets
+        }
+    }
+
+    /// How long to wait after the given number of failed attempts, at most a day.
+    pub fn retry_delay(&self, attempts: i32) -> Duration {
+        backoff_delay(self.retry_base, attempts, MAX_WEBHOOK_RETRY_DELAY)
+    }
+}
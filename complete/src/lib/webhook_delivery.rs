@@ -0,0 +1,156 @@
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Instant;
+use chrono::Utc;
+use futures_util::future::join_all;
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy;
+use sha2::Sha256;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+use crate::lib::app_state::AppState;
+use crate::lib::webhook_config::WebhookConfig;
+use crate::lib::webhook_target::{check_webhook_target, PublicResolver};
+use crate::repository::repo::Repository;
+use crate::repository::webhook::webhook_models::{DueWebhookDelivery, WebhookAttemptResult};
+use crate::repository::webhook::webhook_repo::WebhookRepo;
+
+const WEBHOOK_BATCH_SIZE: i64 = 20;
+/// Matches `webhook_delivery_attempt.error varchar(500)`.
+const ATTEMPT_ERROR_MAX_LEN: usize = 500;
+/// Extra time a claimed delivery is held past the request timeout before another
+/// worker may take it over.
+const CLAIM_GRACE: chrono::Duration = chrono::Duration::seconds(60);
+
+/// Hex HMAC-SHA256 of `"{timestamp}.{body}"` keyed with the webhook's secret. Receivers
+/// recompute it to check a request came from us, and reject stale timestamps to stop
+/// replays.
+pub fn sign_webhook_payload(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Redirects are not followed; a 3xx counts as a failed attempt. Unless the config allows
+/// private targets, names only resolve to public addresses and no proxy is used, as a
+/// proxy would resolve them itself.
+pub fn webhook_client(config: &WebhookConfig) -> reqwest::Client {
+    let builder = reqwest::Client::builder().redirect(Policy::none());
+    let builder = if config.allow_private_targets {
+        builder
+    } else {
+        builder.dns_resolver(Arc::new(PublicResolver)).no_proxy()
+    };
+    builder.build().expect("webhook client config is valid")
+}
+
+/// Sends due webhook deliveries. It runs whenever a domain event arrives, since that
+/// is when new deliveries get queued, and every `poll_interval` for retries.
+pub fn spawn_webhook_delivery(app_state: Arc<AppState>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let client = webhook_client(&app_state.webhooks);
+        let mut domain_events = app_state.events.subscribe_domain();
+        let mut interval = tokio::time::interval(app_state.webhooks.poll_interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {},
+                // lagging only means there is more to send
+                _ = domain_events.recv() => {}
+            }
+            match deliver_due_webhooks(&app_state, &client).await {
+                Ok(0) => {}
+                Ok(attempted) => info!("Attempted {} webhook deliveries", attempted),
+                Err(e) => error!("Error failed deliver_due_webhooks {:?}", e)
+            }
+        }
+    })
+}
+
+/// Attempts every due delivery, a batch at a time, and returns how many were attempted.
+/// Claiming skips deliveries another instance is working on, so any number of
+/// instances can run this at once.
+pub async fn deliver_due_webhooks(app_state: &AppState, client: &reqwest::Client) -> Result<usize, sqlx::Error> {
+    let pool = app_state.repo.get_pool();
+    let lease = chrono::Duration::from_std(app_state.webhooks.request_timeout).unwrap_or_default() + CLAIM_GRACE;
+    let mut attempted = 0;
+    loop {
+        let batch = app_state.repo.claim_due_webhook_deliveries(pool, Utc::now() + lease, WEBHOOK_BATCH_SIZE).await?;
+        let batch_len = batch.len();
+        let results = join_all(batch.iter().map(|delivery| attempt_delivery(app_state, client, delivery))).await;
+        for result in results {
+            // fails when the webhook was deleted meanwhile, which should not hold up the rest
+            match app_state.repo.record_webhook_attempt(pool, &result, app_state.webhooks.disable_after_failures).await {
+                Ok(true) => warn!("Webhook {} is disabled after repeated failures", result.webhook_id),
+                Ok(false) => {}
+                Err(e) => error!("Error failed record_webhook_attempt for delivery {} {:?}", result.delivery_id, e)
+            }
+        }
+        attempted += batch_len;
+
+        if batch_len < WEBHOOK_BATCH_SIZE as usize {
+            return Ok(attempted);
+        }
+    }
+}
+
+async fn attempt_delivery(app_state: &AppState, client: &reqwest::Client, delivery: &DueWebhookDelivery) -> WebhookAttemptResult {
+    let config = &app_state.webhooks;
+    let body = serde_json::to_vec(&delivery.payload.0).unwrap_or_default();
+    let timestamp = Utc::now().timestamp();
+    let signature = sign_webhook_payload(&delivery.secret, timestamp, &body);
+
+    let started = Instant::now();
+    let target = if config.allow_private_targets { Ok(()) } else { check_webhook_target(&delivery.url) };
+    let response = match target {
+        Ok(()) => client.post(&delivery.url)
+            .timeout(config.request_timeout)
+            .header(CONTENT_TYPE, "application/json")
+            .header("x-webhook-id", delivery.webhook_id.to_string())
+            .header("x-webhook-delivery", delivery.id.to_string())
+            .header("x-webhook-event", &delivery.event_type)
+            .header("x-webhook-timestamp", timestamp.to_string())
+            .header("x-webhook-signature", format!("sha256={}", signature))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| error_chain(&e)),
+        Err(e) => Err(e.to_string())
+    };
+    let duration_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+
+    let (response_status, error) = match response {
+        Ok(response) => (Some(response.status().as_u16() as i32), None),
+        Err(e) => (None, Some(e.chars().take(ATTEMPT_ERROR_MAX_LEN).collect()))
+    };
+    let succeeded = response_status.is_some_and(|status| (200..300).contains(&status));
+    let retry_at = if succeeded || delivery.attempts >= config.max_attempts {
+        None
+    } else {
+        Some(Utc::now() + chrono::Duration::from_std(config.retry_delay(delivery.attempts)).unwrap_or_default())
+    };
+
+    WebhookAttemptResult {
+        delivery_id: delivery.id,
+        webhook_id: delivery.webhook_id,
+        succeeded,
+        response_status,
+        error,
+        duration_ms,
+        retry_at
+    }
+}
+
+/// The error with its causes, which is where reqwest says why a request could not be sent.
+fn error_chain(e: &dyn Error) -> String {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
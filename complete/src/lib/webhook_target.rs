@@ -0,0 +1,111 @@
+use std::error::Error;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::Url;
+
+/// Why a webhook may not be sent to the url it was given.
+#[derive(Debug)]
+pub enum WebhookTargetError {
+    InvalidUrl,
+    NotPublic(String)
+}
+
+impl fmt::Display for WebhookTargetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookTargetError::InvalidUrl => write!(f, "url is not a valid url"),
+            WebhookTargetError::NotPublic(host) => write!(f, "{} is not a public address", host)
+        }
+    }
+}
+
+impl Error for WebhookTargetError {}
+
+/// Whether an address is reachable on the public internet. Loopback, private, link-local
+/// (which includes cloud metadata endpoints such as 169.254.169.254), shared, reserved
+/// and multicast ranges are not.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => is_public_ipv6(ip)
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0
+        // shared address space of carrier-grade NAT, 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // protocol assignments, 192.0.0.0/24
+        || (a == 192 && b == 0 && c == 0)
+        // benchmarking, 198.18.0.0/15
+        || (a == 198 && (b == 18 || b == 19))
+        // reserved, 240.0.0.0/4
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    // mapped and compatible IPv4 addresses, which include :: and ::1, and NAT64's
+    // 64:ff9b::/96 all reach an IPv4 address
+    if let Some(ipv4) = ip.to_ipv4() {
+        return is_public_ipv4(ipv4);
+    }
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        let [.., a, b, c, d] = ip.octets();
+        return is_public_ipv4(Ipv4Addr::new(a, b, c, d));
+    }
+    !(ip.is_multicast()
+        // unique local, fc00::/7
+        || (segments[0] & 0xfe00) == 0xfc00
+        // link-local, fe80::/10
+        || (segments[0] & 0xffc0) == 0xfe80
+        // documentation, 2001:db8::/32
+        || (segments[0] == 0x2001 && segments[1] == 0xdb8))
+}
+
+/// Checks a webhook url as far as that is possible without resolving it: hosts given as
+/// addresses must be public and local names are refused. Other names are checked every
+/// time they are resolved for a delivery, by [`PublicResolver`].
+pub fn check_webhook_target(url: &str) -> Result<(), WebhookTargetError> {
+    let url = Url::parse(url).map_err(|_| WebhookTargetError::InvalidUrl)?;
+    let host = url.host_str().ok_or(WebhookTargetError::InvalidUrl)?;
+    let not_public = match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) => !is_public_ip(ip),
+        Err(_) => is_local_name(host)
+    };
+    if not_public {
+        return Err(WebhookTargetError::NotPublic(host.to_string()));
+    }
+    Ok(())
+}
+
+fn is_local_name(domain: &str) -> bool {
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    domain == "localhost" || domain.ends_with(".localhost") || domain.ends_with(".local") || domain.ends_with(".internal")
+}
+
+/// Resolves names like the system does, but fails for a name that resolves to any
+/// address that isn't public. As the connection is made to the addresses checked here,
+/// changing what a webhook's host points to after it was created gets no further.
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0)).await?.collect::<Vec<SocketAddr>>();
+            if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+                return Err(Box::new(WebhookTargetError::NotPublic(addr.ip().to_string())) as Box<dyn Error + Send + Sync>);
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
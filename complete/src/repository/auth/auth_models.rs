@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+#[derive(FromRow)]
+pub struct CredentialQueryResult {
+    pub profile_id: i64,
+    pub password_hash: String
+}
+
+#[derive(FromRow)]
+pub struct RefreshTokenQueryResult {
+    pub id: i64,
+    pub profile_id: i64,
+    pub family: String,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>
+}
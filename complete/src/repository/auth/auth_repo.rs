@@ -0,0 +1,208 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, Error, PgPool};
+use tracing::error;
+use crate::repository::repo::{DbRepo, EntityId};
+use super::auth_models::{CredentialQueryResult, RefreshTokenQueryResult};
+
+#[async_trait]
+pub trait AuthRepo {
+    #[allow(clippy::too_many_arguments)]
+    async fn insert_account(
+        &self,
+        pool: &PgPool,
+        user_name: String,
+        full_name: String,
+        description: String,
+        region: Option<String>,
+        main_url: Option<String>,
+        password_hash: String
+    ) -> Result<EntityId, Error>;
+    async fn select_credential_by_user_name(&self, pool: &PgPool, user_name: &str) -> Result<Option<CredentialQueryResult>, Error>;
+    async fn insert_refresh_token(
+        &self,
+        pool: &PgPool,
+        profile_id: i64,
+        token_hash: &str,
+        family: &str,
+        expires_at: DateTime<Utc>
+    ) -> Result<EntityId, Error>;
+    async fn select_refresh_token(&self, pool: &PgPool, token_hash: &str) -> Result<Option<RefreshTokenQueryResult>, Error>;
+    /// Revokes `old_id` and inserts its successor in the same family. Returns `None`
+    /// when `old_id` was already revoked, i.e. the old token is being replayed.
+    async fn rotate_refresh_token(
+        &self,
+        pool: &PgPool,
+        old_id: i64,
+        new_token_hash: &str,
+        expires_at: DateTime<Utc>
+    ) -> Result<Option<EntityId>, Error>;
+    async fn revoke_refresh_token_family(&self, pool: &PgPool, family: &str) -> Result<(), Error>;
+}
+
+#[async_trait]
+impl AuthRepo for DbRepo {
+    async fn insert_account(
+        &self,
+        pool: &PgPool,
+        user_name: String,
+        full_name: String,
+        description: String,
+        region: Option<String>,
+        main_url: Option<String>,
+        password_hash: String
+    ) -> Result<EntityId, Error> {
+        let mut tx = pool.begin().await?;
+
+        let profile = match query_as::<_, EntityId>(r"
+                insert into profile
+                (user_name, full_name, description, region, main_url)
+                values
+                ($1, $2, $3, $4, $5)
+                returning id
+            ")
+            .bind(user_name)
+            .bind(full_name)
+            .bind(description)
+            .bind(region)
+            .bind(main_url)
+            .fetch_one(&mut *tx)
+            .await {
+                Ok(entity) => entity,
+                Err(e) => {
+                    error!("insert_account profile failed: {}", e);
+                    _ = tx.rollback().await;
+                    return Err(e);
+                }
+            };
+
+        if let Err(e) = query_as::<_, EntityId>(
+                "insert into credential (profile_id, password_hash) values ($1, $2) returning id"
+            )
+            .bind(profile.id)
+            .bind(password_hash)
+            .fetch_one(&mut *tx)
+            .await {
+                error!("insert_account credential failed: {}", e);
+                _ = tx.rollback().await;
+                return Err(e);
+            }
+
+        tx.commit().await?;
+
+        Ok(profile)
+    }
+
+    async fn select_credential_by_user_name(&self, pool: &PgPool, user_name: &str) -> Result<Option<CredentialQueryResult>, Error> {
+        query_as::<_, CredentialQueryResult>(r"
+            select c.profile_id, c.password_hash
+                from credential c
+                    join profile p on p.id = c.profile_id
+                where lower(p.user_name) = lower($1)
+        ")
+        .bind(user_name)
+        .fetch_optional(pool)
+        .await
+    }
+
+    async fn insert_refresh_token(
+        &self,
+        pool: &PgPool,
+        profile_id: i64,
+        token_hash: &str,
+        family: &str,
+        expires_at: DateTime<Utc>
+    ) -> Result<EntityId, Error> {
+        query_as::<_, EntityId>(r"
+            insert into refresh_token
+            (profile_id, token_hash, family, expires_at)
+            values
+            ($1, $2, $3, $4)
+            returning id
+        ")
+        .bind(profile_id)
+        .bind(token_hash)
+        .bind(family)
+        .bind(expires_at)
+        .fetch_one(pool)
+        .await
+    }
+
+    async fn select_refresh_token(&self, pool: &PgPool, token_hash: &str) -> Result<Option<RefreshTokenQueryResult>, Error> {
+        query_as::<_, RefreshTokenQueryResult>(
+            "select id, profile_id, family, expires_at, revoked_at from refresh_token where token_hash = $1"
+        )
+        .bind(token_hash)
+        .fetch_optional(pool)
+        .await
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        pool: &PgPool,
+        old_id: i64,
+        new_token_hash: &str,
+        expires_at: DateTime<Utc>
+    ) -> Result<Option<EntityId>, Error> {
+        let mut tx = pool.begin().await?;
+
+        let revoked = query_as::<_, (i64, String)>(r"
+                update refresh_token
+                    set revoked_at = now()
+                    where id = $1 and revoked_at is null
+                    returning profile_id, family
+            ")
+            .bind(old_id)
+            .fetch_optional(&mut *tx)
+            .await;
+
+        let (profile_id, family) = match revoked {
+            Ok(Some(row)) => row,
+            Ok(None) => {
+                _ = tx.rollback().await;
+                return Ok(None);
+            }
+            Err(e) => {
+                error!("rotate_refresh_token revoke failed: {}", e);
+                _ = tx.rollback().await;
+                return Err(e);
+            }
+        };
+
+        match query_as::<_, EntityId>(r"
+                insert into refresh_token
+                (profile_id, token_hash, family, expires_at)
+                values
+                ($1, $2, $3, $4)
+                returning id
+            ")
+            .bind(profile_id)
+            .bind(new_token_hash)
+            .bind(family)
+            .bind(expires_at)
+            .fetch_one(&mut *tx)
+            .await {
+                Ok(entity) => {
+                    tx.commit().await?;
+                    Ok(Some(entity))
+                }
+                Err(e) => {
+                    error!("rotate_refresh_token insert failed: {}", e);
+                    _ = tx.rollback().await;
+                    Err(e)
+                }
+            }
+    }
+
+    async fn revoke_refresh_token_family(&self, pool: &PgPool, family: &str) -> Result<(), Error> {
+        query(r"
+            update refresh_token
+                set revoked_at = now()
+                where family = $1 and revoked_at is null
+        ")
+        .bind(family)
+        .execute(pool)
+        .await
+        .map(|_| ())
+    }
+}
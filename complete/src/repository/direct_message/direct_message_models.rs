@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::FromRow;
+
+/// A conversation as seen by one of its members. `unread_count` counts the others'
+/// messages after that member's read marker.
+#[derive(Serialize, Deserialize, FromRow)]
+pub struct ConversationQueryResult {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub is_group: bool,
+    pub title: Option<String>,
+    pub last_message_at: DateTime<Utc>,
+    pub unread_count: i64,
+    /// Every member, the viewer included, in the order they joined.
+    pub members: Json<Vec<ConversationMember>>,
+    pub last_message: Option<Json<DirectMessageQueryResult>>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConversationMember {
+    pub profile_id: i64,
+    pub user_name: String,
+    pub full_name: String,
+    pub avatar_id: Option<i64>
+}
+
+#[derive(Serialize, Deserialize, FromRow, Clone, Debug)]
+pub struct DirectMessageQueryResult {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub conversation_id: i64,
+    pub sender_id: i64,
+    pub body: String
+}
+
+/// Whether a profile accepts a new conversation from a given sender.
+#[derive(FromRow)]
+pub struct DmRecipient {
+    pub profile_id: i64,
+    pub accepts: bool
+}
+
+/// Keyset position in a profile's conversations, most recently active first. A
+/// conversation moves to the top with each new message, so it may show up again on a
+/// later page.
+#[derive(Serialize, Deserialize)]
+pub struct ConversationCursor {
+    pub last_message_at: DateTime<Utc>,
+    pub id: i64
+}
+
+/// Keyset position in a conversation's history, newest first.
+#[derive(Serialize, Deserialize)]
+pub struct DirectMessageCursor {
+    pub id: i64
+}
@@ -0,0 +1,282 @@
+use async_trait::async_trait;
+use sqlx::{query, query_as, query_scalar, Error, PgPool};
+use crate::repository::repo::DbRepo;
+use super::direct_message_models::{ConversationCursor, ConversationQueryResult, DirectMessageCursor, DirectMessageQueryResult, DmRecipient};
+
+/// Conversations as seen by the member bound to `$1`.
+const CONVERSATION_COLUMNS: &str = r"
+    select c.id, c.created_at, c.is_group, c.title, c.last_message_at,
+        (
+            select count(*) from direct_message dm
+                where dm.conversation_id = c.id and dm.id > me.last_read_message_id and dm.sender_id <> me.profile_id
+        ) as unread_count,
+        (
+            select coalesce(jsonb_agg(jsonb_build_object(
+                'profile_id', p.id,
+                'user_name', p.user_name,
+                'full_name', p.full_name,
+                'avatar_id', p.avatar_id
+            ) order by cm.created_at, p.id), '[]'::jsonb)
+            from conversation_member cm
+                join profile p on p.id = cm.profile_id
+            where cm.conversation_id = c.id
+        ) as members,
+        (
+            select jsonb_build_object(
+                'id', dm.id,
+                'created_at', dm.created_at,
+                'conversation_id', dm.conversation_id,
+                'sender_id', dm.sender_id,
+                'body', dm.body
+            )
+            from direct_message dm
+            where dm.conversation_id = c.id
+            order by dm.id desc
+            limit 1
+        ) as last_message
+    from conversation c
+        join conversation_member me on me.conversation_id = c.id and me.profile_id = $1";
+
+/// Conversations are only ever read through one of their members, so a profile that
+/// is not a member finds nothing.
+#[async_trait]
+pub trait DirectMessageRepo {
+    /// The profiles among `profile_ids` that exist, each with whether it accepts a new
+    /// conversation from `sender_id` under its `dm_policy`.
+    async fn select_dm_recipients(&self, pool: &PgPool, sender_id: i64, profile_ids: &[i64]) -> Result<Vec<DmRecipient>, Error>;
+    /// Starts a group conversation of `creator_id` and `member_ids`, or with a single
+    /// member a 1:1 conversation, returning its id and whether it is new. Starting a 1:1
+    /// conversation that exists already returns the existing one.
+    async fn insert_conversation(
+        &self,
+        pool: &PgPool,
+        creator_id: i64,
+        member_ids: &[i64],
+        title: Option<String>
+    ) -> Result<(i64, bool), Error>;
+    async fn select_conversation(&self, pool: &PgPool, profile_id: i64, id: i64) -> Result<Option<ConversationQueryResult>, Error>;
+    /// `profile_id`'s conversations, most recently active first, starting after `before`.
+    async fn select_conversations(
+        &self,
+        pool: &PgPool,
+        profile_id: i64,
+        before: Option<ConversationCursor>,
+        limit: i64
+    ) -> Result<Vec<ConversationQueryResult>, Error>;
+    /// Unread messages over all of `profile_id`'s conversations.
+    async fn select_unread_direct_message_count(&self, pool: &PgPool, profile_id: i64) -> Result<i64, Error>;
+    /// Adds a message unless `sender_id` is not a member. The conversation becomes the
+    /// most recently active, and the sender has read up to their own message.
+    async fn insert_direct_message(
+        &self,
+        pool: &PgPool,
+        conversation_id: i64,
+        sender_id: i64,
+        body: &str
+    ) -> Result<Option<DirectMessageQueryResult>, Error>;
+    /// A conversation's messages, newest first, starting after `before`.
+    async fn select_direct_messages(
+        &self,
+        pool: &PgPool,
+        conversation_id: i64,
+        before: Option<DirectMessageCursor>,
+        limit: i64
+    ) -> Result<Vec<DirectMessageQueryResult>, Error>;
+    /// Moves `profile_id`'s read marker up to `up_to`, or to the latest message. Markers
+    /// never move back. False when `profile_id` is not a member.
+    async fn mark_conversation_read(&self, pool: &PgPool, profile_id: i64, conversation_id: i64, up_to: Option<i64>) -> Result<bool, Error>;
+}
+
+#[async_trait]
+impl DirectMessageRepo for DbRepo {
+    async fn select_dm_recipients(&self, pool: &PgPool, sender_id: i64, profile_ids: &[i64]) -> Result<Vec<DmRecipient>, Error> {
+        query_as::<_, DmRecipient>(r"
+            select p.id as profile_id,
+                p.dm_policy = 'everyone' or exists (
+                    select 1 from follow f where f.follower_id = p.id and f.following_id = $1
+                ) as accepts
+                from profile p
+                where p.id = any($2)
+        ")
+        .bind(sender_id)
+        .bind(profile_ids)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn insert_conversation(
+        &self,
+        pool: &PgPool,
+        creator_id: i64,
+        member_ids: &[i64],
+        title: Option<String>
+    ) -> Result<(i64, bool), Error> {
+        let mut tx = pool.begin().await?;
+
+        let id = if let [member_id] = member_ids {
+            let (low, high) = (creator_id.min(*member_id), creator_id.max(*member_id));
+            let inserted = query_scalar::<_, i64>(r"
+                insert into conversation (creator_id, is_group, pair_low_id, pair_high_id) values ($1, false, $2, $3)
+                    on conflict (pair_low_id, pair_high_id) where not is_group do nothing
+                    returning id
+            ")
+            .bind(creator_id)
+            .bind(low)
+            .bind(high)
+            .fetch_optional(&mut *tx)
+            .await?;
+            match inserted {
+                Some(id) => id,
+                None => {
+                    let existing_id = query_scalar::<_, i64>(
+                        "select id from conversation where not is_group and pair_low_id = $1 and pair_high_id = $2"
+                    )
+                    .bind(low)
+                    .bind(high)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                    tx.commit().await?;
+                    return Ok((existing_id, false));
+                }
+            }
+        } else {
+            query_scalar::<_, i64>("insert into conversation (creator_id, is_group, title) values ($1, true, $2) returning id")
+                .bind(creator_id)
+                .bind(title)
+                .fetch_one(&mut *tx)
+                .await?
+        };
+
+        query(r"
+            insert into conversation_member (conversation_id, profile_id)
+                select $1, member_id from unnest(array_prepend($2::bigint, $3::bigint[])) member_id
+        ")
+        .bind(id)
+        .bind(creator_id)
+        .bind(member_ids)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok((id, true))
+    }
+
+    async fn select_conversation(&self, pool: &PgPool, profile_id: i64, id: i64) -> Result<Option<ConversationQueryResult>, Error> {
+        query_as::<_, ConversationQueryResult>(&format!("{} where c.id = $2", CONVERSATION_COLUMNS))
+            .bind(profile_id)
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn select_conversations(
+        &self,
+        pool: &PgPool,
+        profile_id: i64,
+        before: Option<ConversationCursor>,
+        limit: i64
+    ) -> Result<Vec<ConversationQueryResult>, Error> {
+        let (before_at, before_id) = before.map(|cursor| (cursor.last_message_at, cursor.id)).unzip();
+        query_as::<_, ConversationQueryResult>(&format!(r"
+            {}
+            where $2::timestamptz is null or (c.last_message_at, c.id) < ($2, $3)
+            order by c.last_message_at desc, c.id desc
+            limit $4
+        ", CONVERSATION_COLUMNS))
+        .bind(profile_id)
+        .bind(before_at)
+        .bind(before_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn select_unread_direct_message_count(&self, pool: &PgPool, profile_id: i64) -> Result<i64, Error> {
+        query_scalar::<_, i64>(r"
+            select count(*) from conversation_member me
+                join direct_message dm on dm.conversation_id = me.conversation_id
+                    and dm.id > me.last_read_message_id
+                    and dm.sender_id <> me.profile_id
+                where me.profile_id = $1
+        ")
+        .bind(profile_id)
+        .fetch_one(pool)
+        .await
+    }
+
+    async fn insert_direct_message(
+        &self,
+        pool: &PgPool,
+        conversation_id: i64,
+        sender_id: i64,
+        body: &str
+    ) -> Result<Option<DirectMessageQueryResult>, Error> {
+        let mut tx = pool.begin().await?;
+
+        let message = query_as::<_, DirectMessageQueryResult>(r"
+            insert into direct_message (conversation_id, sender_id, body)
+                select $1, $2, $3
+                where exists (select 1 from conversation_member where conversation_id = $1 and profile_id = $2)
+                returning id, created_at, conversation_id, sender_id, body
+        ")
+        .bind(conversation_id)
+        .bind(sender_id)
+        .bind(body)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(message) = message else {
+            return Ok(None);
+        };
+
+        // messages sent at about the same time may commit out of order
+        query("update conversation set last_message_at = greatest(last_message_at, $2) where id = $1")
+            .bind(conversation_id)
+            .bind(message.created_at)
+            .execute(&mut *tx)
+            .await?;
+        query("update conversation_member set last_read_message_id = $3 where conversation_id = $1 and profile_id = $2")
+            .bind(conversation_id)
+            .bind(sender_id)
+            .bind(message.id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(Some(message))
+    }
+
+    async fn select_direct_messages(
+        &self,
+        pool: &PgPool,
+        conversation_id: i64,
+        before: Option<DirectMessageCursor>,
+        limit: i64
+    ) -> Result<Vec<DirectMessageQueryResult>, Error> {
+        query_as::<_, DirectMessageQueryResult>(r"
+            select id, created_at, conversation_id, sender_id, body from direct_message
+                where conversation_id = $1 and ($2::bigint is null or id < $2)
+                order by id desc
+                limit $3
+        ")
+        .bind(conversation_id)
+        .bind(before.map(|cursor| cursor.id))
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn mark_conversation_read(&self, pool: &PgPool, profile_id: i64, conversation_id: i64, up_to: Option<i64>) -> Result<bool, Error> {
+        query(r"
+            update conversation_member set last_read_message_id = greatest(last_read_message_id, coalesce((
+                select max(id) from direct_message where conversation_id = $1 and ($3::bigint is null or id <= $3)
+            ), 0))
+                where conversation_id = $1 and profile_id = $2
+        ")
+        .bind(conversation_id)
+        .bind(profile_id)
+        .bind(up_to)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+    }
+}
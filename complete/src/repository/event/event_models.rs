@@ -0,0 +1,37 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::FromRow;
+
+/// `message`: a new message on the home timeline. `likes`: a message's like count
+/// changed. `notification`: a notification was created or gained an actor.
+#[derive(Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum EventKind {
+    Message,
+    Likes,
+    Notification
+}
+
+impl EventKind {
+    /// The name used on the wire, matching the serialized form.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::Message => "message",
+            EventKind::Likes => "likes",
+            EventKind::Notification => "notification"
+        }
+    }
+}
+
+/// An event as recorded. `data` only holds ids, except for `likes` events which carry
+/// the new count.
+#[derive(FromRow)]
+pub struct EventQueryResult {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub kind: EventKind,
+    pub audience: Vec<i64>,
+    pub data: Json<serde_json::Value>
+}
@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, query_scalar, Error, PgPool};
+use crate::repository::repo::DbRepo;
+use super::event_models::EventQueryResult;
+
+/// Events are written by triggers alongside the changes they describe. They are read by
+/// their `seq`, which is what these return as the id, so only sequenced events are seen.
+#[async_trait]
+pub trait EventRepo {
+    /// Numbers the events whose transactions have finished, returning how many there were.
+    async fn sequence_events(&self, pool: &PgPool) -> Result<i64, Error>;
+    /// Id of the newest event, or 0 before the first. It doesn't go back when expired
+    /// events are deleted.
+    async fn select_latest_event_id(&self, pool: &PgPool) -> Result<i64, Error>;
+    /// Events after `after_id` in id order, whoever they are for.
+    async fn select_events_after(&self, pool: &PgPool, after_id: i64, limit: i64) -> Result<Vec<EventQueryResult>, Error>;
+    /// Events after `after_id` that `profile_id` is in the audience of, in id order.
+    async fn select_profile_events_after(&self, pool: &PgPool, profile_id: i64, after_id: i64, limit: i64) -> Result<Vec<EventQueryResult>, Error>;
+    async fn delete_events_before(&self, pool: &PgPool, before: DateTime<Utc>) -> Result<u64, Error>;
+}
+
+#[async_trait]
+impl EventRepo for DbRepo {
+    async fn sequence_events(&self, pool: &PgPool) -> Result<i64, Error> {
+        query_scalar::<_, i64>("select sequence_events()")
+            .fetch_one(pool)
+            .await
+    }
+
+    async fn select_latest_event_id(&self, pool: &PgPool) -> Result<i64, Error> {
+        query_scalar::<_, i64>("select latest_event_seq()")
+            .fetch_one(pool)
+            .await
+    }
+
+    async fn select_events_after(&self, pool: &PgPool, after_id: i64, limit: i64) -> Result<Vec<EventQueryResult>, Error> {
+        query_as::<_, EventQueryResult>(
+            "select seq as id, created_at, kind, audience, data from event where seq > $1 order by seq limit $2"
+        )
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn select_profile_events_after(&self, pool: &PgPool, profile_id: i64, after_id: i64, limit: i64) -> Result<Vec<EventQueryResult>, Error> {
+        query_as::<_, EventQueryResult>(r"
+            select seq as id, created_at, kind, audience, data from event
+                where seq > $2 and audience @> array[$1::bigint]
+                order by seq
+                limit $3
+        ")
+        .bind(profile_id)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn delete_events_before(&self, pool: &PgPool, before: DateTime<Utc>) -> Result<u64, Error> {
+        query("delete from event where created_at < $1")
+            .bind(before)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected())
+    }
+}
@@ -1,5 +1,5 @@
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
 #[derive(Serialize, FromRow)]
@@ -9,4 +9,12 @@ pub struct Follow {
     updated_at: DateTime<Utc>,
     follower_id: i64,
     following_id: i64
-}
\ No newline at end of file
+}
+#[derive(Serialize, Deserialize, FromRow)]
+pub struct FollowProfileQueryResult {
+    pub follow_id: i64,
+    pub followed_at: DateTime<Utc>,
+    pub profile_id: i64,
+    pub user_name: String,
+    pub full_name: String
+}
@@ -2,18 +2,39 @@ use sqlx::PgPool;
 use async_trait::async_trait;
 use crate::repository::repo::{DbRepo, EntityId};
 
-use super::follow_models::Follow;
+use super::follow_models::{Follow, FollowProfileQueryResult};
 
 #[async_trait]
 pub trait FollowRepo {
-    async fn insert_follow(conn: &PgPool, follower_id: i64, following_id: i64) -> Result<EntityId, sqlx::Error>;
+    async fn insert_follow(&self, conn: &PgPool, follower_id: i64, following_id: i64) -> Result<EntityId, sqlx::Error>;
 
-    async fn select_follows_by_follower(pool: &PgPool, id: i64) -> Result<Vec<Follow>, sqlx::Error>;
+    /// Returns whether a follow existed to delete.
+    async fn delete_follow(&self, conn: &PgPool, follower_id: i64, following_id: i64) -> Result<bool, sqlx::Error>;
+
+    async fn select_follows_by_follower(&self, pool: &PgPool, id: i64) -> Result<Vec<Follow>, sqlx::Error>;
+
+    /// Profiles following `profile_id`, newest follow first, starting below `before_follow_id`.
+    async fn select_followers(
+        &self,
+        pool: &PgPool,
+        profile_id: i64,
+        before_follow_id: Option<i64>,
+        limit: i64
+    ) -> Result<Vec<FollowProfileQueryResult>, sqlx::Error>;
+
+    /// Profiles `profile_id` follows, newest follow first, starting below `before_follow_id`.
+    async fn select_following(
+        &self,
+        pool: &PgPool,
+        profile_id: i64,
+        before_follow_id: Option<i64>,
+        limit: i64
+    ) -> Result<Vec<FollowProfileQueryResult>, sqlx::Error>;
 }
 
 #[async_trait]
 impl FollowRepo for DbRepo {
-    async fn insert_follow(conn: &PgPool, follower_id: i64, following_id: i64) -> Result<EntityId, sqlx::Error> {
+    async fn insert_follow(&self, conn: &PgPool, follower_id: i64, following_id: i64) -> Result<EntityId, sqlx::Error> {
         sqlx::query_as::<_, EntityId>(
                 "insert into follow (follower_id, following_id) values ($1, $2) returning id"
             )
@@ -23,7 +44,16 @@ impl FollowRepo for DbRepo {
             .await
     }
 
-    async fn select_follows_by_follower(pool: &PgPool, id: i64) -> Result<Vec<Follow>, sqlx::Error> {
+    async fn delete_follow(&self, conn: &PgPool, follower_id: i64, following_id: i64) -> Result<bool, sqlx::Error> {
+        sqlx::query("delete from follow where follower_id = $1 and following_id = $2")
+            .bind(follower_id)
+            .bind(following_id)
+            .execute(conn)
+            .await
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    async fn select_follows_by_follower(&self, pool: &PgPool, id: i64) -> Result<Vec<Follow>, sqlx::Error> {
         sqlx::query_as::<_, Follow>(r"
             select * from follow
             where follower_id = $1
@@ -32,4 +62,52 @@ impl FollowRepo for DbRepo {
         .fetch_all(pool)
         .await
     }
+
+    async fn select_followers(
+        &self,
+        pool: &PgPool,
+        profile_id: i64,
+        before_follow_id: Option<i64>,
+        limit: i64
+    ) -> Result<Vec<FollowProfileQueryResult>, sqlx::Error> {
+        sqlx::query_as::<_, FollowProfileQueryResult>(r"
+            select f.id as follow_id, f.created_at as followed_at, p.id as profile_id, p.user_name, p.full_name
+                from follow f
+                    join profile p on p.id = f.follower_id
+                where
+                    f.following_id = $1
+                    and ($2::bigint is null or f.id < $2)
+                order by f.id desc
+                limit $3
+        ")
+        .bind(profile_id)
+        .bind(before_follow_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn select_following(
+        &self,
+        pool: &PgPool,
+        profile_id: i64,
+        before_follow_id: Option<i64>,
+        limit: i64
+    ) -> Result<Vec<FollowProfileQueryResult>, sqlx::Error> {
+        sqlx::query_as::<_, FollowProfileQueryResult>(r"
+            select f.id as follow_id, f.created_at as followed_at, p.id as profile_id, p.user_name, p.full_name
+                from follow f
+                    join profile p on p.id = f.following_id
+                where
+                    f.follower_id = $1
+                    and ($2::bigint is null or f.id < $2)
+                order by f.id desc
+                limit $3
+        ")
+        .bind(profile_id)
+        .bind(before_follow_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
 }
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// Uses of a tag in the latest window and in the window of the same length before it.
+/// `growth` is the rise relative to the earlier window, plus one so that new tags rank
+/// by their count rather than dividing by zero.
+#[derive(Serialize, Deserialize, FromRow)]
+pub struct TrendingHashtagQueryResult {
+    pub name: String,
+    pub current_count: i64,
+    pub previous_count: i64,
+    pub growth: f64
+}
@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use sqlx::{query_as, Error, PgPool};
+use crate::repository::repo::DbRepo;
+use super::hashtag_models::TrendingHashtagQueryResult;
+
+#[async_trait]
+pub trait HashtagRepo {
+    /// Tags used more in the last `window` than in the `window` before it, fastest
+    /// rising first. Deleted messages don't count.
+    async fn select_trending_hashtags(&self, pool: &PgPool, window: Duration, limit: i64) -> Result<Vec<TrendingHashtagQueryResult>, Error>;
+}
+
+#[async_trait]
+impl HashtagRepo for DbRepo {
+    async fn select_trending_hashtags(&self, pool: &PgPool, window: Duration, limit: i64) -> Result<Vec<TrendingHashtagQueryResult>, Error> {
+        let window_start = Utc::now() - window;
+
+        query_as::<_, TrendingHashtagQueryResult>(r"
+            with usage as (
+                select mh.hashtag_id,
+                    count(*) filter (where mh.created_at >= $1) as current_count,
+                    count(*) filter (where mh.created_at < $1) as previous_count
+                    from message_hashtag mh
+                        join message m on m.id = mh.message_id
+                    where mh.created_at >= $2 and m.deleted_at is null
+                    group by mh.hashtag_id
+            )
+            select h.name, u.current_count, u.previous_count,
+                (u.current_count - u.previous_count)::float8 / (u.previous_count + 1) as growth
+                from usage u
+                    join hashtag h on h.id = u.hashtag_id
+                where u.current_count > u.previous_count
+                order by growth desc, u.current_count desc, h.name
+                limit $3
+        ")
+        .bind(window_start)
+        .bind(window_start - window)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+}
@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::FromRow;
+
+#[derive(Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    /// Out of attempts, kept for inspection.
+    Dead
+}
+
+/// A job as stored. `payload` is the serialized `Job`.
+#[derive(Serialize, Deserialize, FromRow, Debug)]
+pub struct JobQueryResult {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub kind: String,
+    pub payload: Json<serde_json::Value>,
+    pub status: JobStatus,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: DateTime<Utc>,
+    pub last_error: Option<String>
+}
+
+/// A job claimed by a worker. `attempts` includes the run about to start and, with the
+/// id, identifies the claim.
+#[derive(FromRow)]
+pub struct ClaimedJob {
+    pub id: i64,
+    pub kind: String,
+    pub payload: Json<serde_json::Value>,
+    pub attempts: i32,
+    pub max_attempts: i32
+}
@@ -0,0 +1,172 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, query_scalar, Error, PgPool};
+use crate::repository::repo::DbRepo;
+use super::job_models::{ClaimedJob, JobQueryResult};
+
+const JOB_COLUMNS: &str = "id, created_at, kind, payload, status, attempts, max_attempts, run_at, last_error";
+
+/// Storage of the background job queue. Finishing a job only takes effect while its
+/// claim is current, so a worker that overran its lease cannot clobber the run that
+/// took over.
+#[async_trait]
+pub trait JobRepo {
+    /// Queues a job to run at `run_at` and returns its id.
+    async fn insert_job(
+        &self,
+        pool: &PgPool,
+        kind: &str,
+        payload: &serde_json::Value,
+        max_attempts: i32,
+        run_at: DateTime<Utc>
+    ) -> Result<i64, Error>;
+    /// Like `insert_job`, but only when no job of `kind` is pending yet. Returns the new
+    /// job's id, or `None` when one was already pending.
+    async fn insert_job_unless_pending(
+        &self,
+        pool: &PgPool,
+        kind: &str,
+        payload: &serde_json::Value,
+        max_attempts: i32,
+        run_at: DateTime<Utc>
+    ) -> Result<Option<i64>, Error>;
+    async fn select_job(&self, pool: &PgPool, id: i64) -> Result<Option<JobQueryResult>, Error>;
+    /// Claims the job of one of `kinds` that has been due longest, including running jobs
+    /// whose lease ran out. It stays claimed until `locked_until`.
+    async fn claim_job(&self, pool: &PgPool, kinds: &[String], locked_until: DateTime<Utc>) -> Result<Option<ClaimedJob>, Error>;
+    /// Removes a job that ran successfully.
+    async fn complete_job(&self, pool: &PgPool, id: i64, attempts: i32) -> Result<bool, Error>;
+    /// Puts a failed job back to run again at `run_at`.
+    async fn retry_job(&self, pool: &PgPool, id: i64, attempts: i32, error: &str, run_at: DateTime<Utc>) -> Result<bool, Error>;
+    /// Moves a job that will not succeed to the dead letters.
+    async fn bury_job(&self, pool: &PgPool, id: i64, attempts: i32, error: &str) -> Result<bool, Error>;
+    /// Dead jobs, oldest first, optionally of one kind.
+    async fn select_dead_jobs(&self, pool: &PgPool, kind: Option<&str>, limit: i64) -> Result<Vec<JobQueryResult>, Error>;
+    /// Gives a dead job a fresh set of attempts, to run now.
+    async fn requeue_dead_job(&self, pool: &PgPool, id: i64) -> Result<bool, Error>;
+}
+
+#[async_trait]
+impl JobRepo for DbRepo {
+    async fn insert_job(
+        &self,
+        pool: &PgPool,
+        kind: &str,
+        payload: &serde_json::Value,
+        max_attempts: i32,
+        run_at: DateTime<Utc>
+    ) -> Result<i64, Error> {
+        query_scalar::<_, i64>("insert into job (kind, payload, max_attempts, run_at) values ($1, $2, $3, $4) returning id")
+            .bind(kind)
+            .bind(payload)
+            .bind(max_attempts)
+            .bind(run_at)
+            .fetch_one(pool)
+            .await
+    }
+
+    async fn insert_job_unless_pending(
+        &self,
+        pool: &PgPool,
+        kind: &str,
+        payload: &serde_json::Value,
+        max_attempts: i32,
+        run_at: DateTime<Utc>
+    ) -> Result<Option<i64>, Error> {
+        query_scalar::<_, i64>(r"
+            insert into job (kind, payload, max_attempts, run_at)
+                select $1, $2, $3, $4
+                where not exists (select 1 from job where kind = $1 and status = 'pending')
+                returning id
+        ")
+        .bind(kind)
+        .bind(payload)
+        .bind(max_attempts)
+        .bind(run_at)
+        .fetch_optional(pool)
+        .await
+    }
+
+    async fn select_job(&self, pool: &PgPool, id: i64) -> Result<Option<JobQueryResult>, Error> {
+        query_as::<_, JobQueryResult>(&format!("select {} from job where id = $1", JOB_COLUMNS))
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn claim_job(&self, pool: &PgPool, kinds: &[String], locked_until: DateTime<Utc>) -> Result<Option<ClaimedJob>, Error> {
+        query_as::<_, ClaimedJob>(r"
+            update job set status = 'running', attempts = attempts + 1, locked_until = $2
+                where id = (
+                    select id from job
+                        where kind = any($1) and (
+                            (status = 'pending' and run_at <= now())
+                            or (status = 'running' and locked_until < now())
+                        )
+                        order by run_at, id
+                        limit 1
+                        for update skip locked
+                )
+                returning id, kind, payload, attempts, max_attempts
+        ")
+        .bind(kinds)
+        .bind(locked_until)
+        .fetch_optional(pool)
+        .await
+    }
+
+    async fn complete_job(&self, pool: &PgPool, id: i64, attempts: i32) -> Result<bool, Error> {
+        query("delete from job where id = $1 and attempts = $2 and status = 'running'")
+            .bind(id)
+            .bind(attempts)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    async fn retry_job(&self, pool: &PgPool, id: i64, attempts: i32, error: &str, run_at: DateTime<Utc>) -> Result<bool, Error> {
+        query(r"
+            update job set status = 'pending', locked_until = null, last_error = $3, run_at = $4
+                where id = $1 and attempts = $2 and status = 'running'
+        ")
+        .bind(id)
+        .bind(attempts)
+        .bind(error)
+        .bind(run_at)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+    }
+
+    async fn bury_job(&self, pool: &PgPool, id: i64, attempts: i32, error: &str) -> Result<bool, Error> {
+        query(r"
+            update job set status = 'dead', locked_until = null, last_error = $3
+                where id = $1 and attempts = $2 and status = 'running'
+        ")
+        .bind(id)
+        .bind(attempts)
+        .bind(error)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+    }
+
+    async fn select_dead_jobs(&self, pool: &PgPool, kind: Option<&str>, limit: i64) -> Result<Vec<JobQueryResult>, Error> {
+        query_as::<_, JobQueryResult>(&format!(
+            "select {} from job where status = 'dead' and ($1::varchar is null or kind = $1) order by id limit $2",
+            JOB_COLUMNS
+        ))
+        .bind(kind)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn requeue_dead_job(&self, pool: &PgPool, id: i64) -> Result<bool, Error> {
+        query("update job set status = 'pending', attempts = 0, run_at = now() where id = $1 and status = 'dead'")
+            .bind(id)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Serialize, Deserialize, FromRow)]
+pub struct LikeProfileQueryResult {
+    pub liked_at: DateTime<Utc>,
+    pub profile_id: i64,
+    pub user_name: String,
+    pub full_name: String
+}
+
+/// Keyset position in a message's list of likes.
+#[derive(Serialize, Deserialize)]
+pub struct LikeCursor {
+    pub liked_at: DateTime<Utc>,
+    pub profile_id: i64
+}
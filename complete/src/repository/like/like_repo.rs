@@ -0,0 +1,110 @@
+use async_trait::async_trait;
+use sqlx::{query, query_as, query_scalar, Error, PgPool};
+use tracing::error;
+use crate::repository::repo::DbRepo;
+use super::like_models::{LikeCursor, LikeProfileQueryResult};
+
+#[async_trait]
+pub trait LikeRepo {
+    /// Likes `message_id` if not already liked and returns its like count.
+    async fn insert_like(&self, pool: &PgPool, profile_id: i64, message_id: i64) -> Result<i32, Error>;
+    /// Removes a like if present and returns the like count, or `None` if the message does not exist.
+    async fn delete_like(&self, pool: &PgPool, profile_id: i64, message_id: i64) -> Result<Option<i32>, Error>;
+    /// Like count of a message, or `None` if the message does not exist.
+    async fn select_like_count(&self, pool: &PgPool, message_id: i64) -> Result<Option<i32>, Error>;
+    async fn select_likes(
+        &self,
+        pool: &PgPool,
+        message_id: i64,
+        before: Option<LikeCursor>,
+        limit: i64
+    ) -> Result<Vec<LikeProfileQueryResult>, Error>;
+}
+
+#[async_trait]
+impl LikeRepo for DbRepo {
+    async fn insert_like(&self, pool: &PgPool, profile_id: i64, message_id: i64) -> Result<i32, Error> {
+        let mut tx = pool.begin().await?;
+
+        if let Err(e) = query(r"
+                insert into message_like (profile_id, message_id)
+                values ($1, $2)
+                on conflict (profile_id, message_id) do nothing
+            ")
+            .bind(profile_id)
+            .bind(message_id)
+            .execute(&mut *tx)
+            .await {
+                error!("insert_like failed: {}", e);
+                _ = tx.rollback().await;
+                return Err(e);
+            }
+
+        let likes = query_scalar::<_, i32>("select likes from message where id = $1")
+            .bind(message_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(likes)
+    }
+
+    async fn delete_like(&self, pool: &PgPool, profile_id: i64, message_id: i64) -> Result<Option<i32>, Error> {
+        let mut tx = pool.begin().await?;
+
+        if let Err(e) = query("delete from message_like where profile_id = $1 and message_id = $2")
+            .bind(profile_id)
+            .bind(message_id)
+            .execute(&mut *tx)
+            .await {
+                error!("delete_like failed: {}", e);
+                _ = tx.rollback().await;
+                return Err(e);
+            }
+
+        let likes = query_scalar::<_, i32>("select likes from message where id = $1")
+            .bind(message_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(likes)
+    }
+
+    async fn select_like_count(&self, pool: &PgPool, message_id: i64) -> Result<Option<i32>, Error> {
+        query_scalar::<_, i32>("select likes from message where id = $1")
+            .bind(message_id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn select_likes(
+        &self,
+        pool: &PgPool,
+        message_id: i64,
+        before: Option<LikeCursor>,
+        limit: i64
+    ) -> Result<Vec<LikeProfileQueryResult>, Error> {
+        let (before_liked_at, before_profile_id) = match before {
+            Some(cursor) => (Some(cursor.liked_at), Some(cursor.profile_id)),
+            None => (None, None)
+        };
+
+        query_as::<_, LikeProfileQueryResult>(r"
+            select ml.created_at as liked_at, p.id as profile_id, p.user_name, p.full_name
+                from message_like ml
+                    join profile p on p.id = ml.profile_id
+                where
+                    ml.message_id = $1
+                    and ($2::timestamptz is null or (ml.created_at, ml.profile_id) < ($2, $3))
+                order by ml.created_at desc, ml.profile_id desc
+                limit $4
+        ")
+        .bind(message_id)
+        .bind(before_liked_at)
+        .bind(before_profile_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+}
@@ -0,0 +1,24 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+#[derive(FromRow)]
+pub struct MediaQueryResult {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub owner_id: i64,
+    pub storage_key: String,
+    pub content_type: String,
+    pub byte_size: i64,
+    /// Avatar renditions stay what they are until replaced, while attachments are taken
+    /// down with their message, so only the former may be cached for long.
+    pub is_avatar: bool
+}
+
+/// Bytes stored inline before media moved to a `MediaStore`, waiting to be backfilled.
+/// `id` is the profile or message they belong to.
+#[derive(FromRow)]
+pub struct LegacyMediaQueryResult {
+    pub id: i64,
+    pub owner_id: i64,
+    pub data: Vec<u8>
+}
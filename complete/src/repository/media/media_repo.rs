@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use sqlx::{query, query_as, query_scalar, Error, PgPool};
+use crate::repository::message::message_models::NewAttachment;
+use crate::repository::repo::{DbRepo, EntityId};
+use super::media_models::{LegacyMediaQueryResult, MediaQueryResult};
+
+#[async_trait]
+pub trait MediaRepo {
+    /// Records an object already written to the media store under `storage_key`.
+    async fn insert_media(
+        &self,
+        pool: &PgPool,
+        owner_id: i64,
+        storage_key: &str,
+        content_type: &str,
+        byte_size: i64
+    ) -> Result<i64, Error>;
+    /// Media attached to a deleted message is not found, even before the purge removes it.
+    async fn select_media(&self, pool: &PgPool, id: i64) -> Result<Option<MediaQueryResult>, Error>;
+    /// Deletes media rows and returns the storage keys of the objects they referenced.
+    async fn delete_media(&self, pool: &PgPool, ids: &[i64]) -> Result<Vec<String>, Error>;
+    /// Drops legacy bytes that have nowhere to go anymore: avatars of profiles that
+    /// uploaded a new one and images of deleted messages.
+    async fn delete_superseded_legacy_media(&self, pool: &PgPool) -> Result<(), Error>;
+    async fn select_legacy_avatars(&self, pool: &PgPool, after: i64, limit: i64) -> Result<Vec<LegacyMediaQueryResult>, Error>;
+    async fn delete_legacy_avatar(&self, pool: &PgPool, profile_id: i64) -> Result<(), Error>;
+    async fn select_legacy_message_images(&self, pool: &PgPool, after: i64, limit: i64) -> Result<Vec<LegacyMediaQueryResult>, Error>;
+    /// Attaches a backfilled image to its message and drops the legacy bytes. False when
+    /// the message was deleted or gained attachments meanwhile, leaving the media unused.
+    async fn attach_legacy_message_image(&self, pool: &PgPool, message_id: i64, attachment: &NewAttachment) -> Result<bool, Error>;
+}
+
+#[async_trait]
+impl MediaRepo for DbRepo {
+    async fn insert_media(
+        &self,
+        pool: &PgPool,
+        owner_id: i64,
+        storage_key: &str,
+        content_type: &str,
+        byte_size: i64
+    ) -> Result<i64, Error> {
+        let entity = query_as::<_, EntityId>(r"
+            insert into media (owner_id, storage_key, content_type, byte_size)
+            values ($1, $2, $3, $4)
+            returning id
+        ")
+        .bind(owner_id)
+        .bind(storage_key)
+        .bind(content_type)
+        .bind(byte_size)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(entity.id)
+    }
+
+    async fn select_media(&self, pool: &PgPool, id: i64) -> Result<Option<MediaQueryResult>, Error> {
+        query_as::<_, MediaQueryResult>(r"
+            select md.*, exists(select 1 from profile_avatar pa where pa.media_id = md.id) as is_avatar from media md
+                where md.id = $1 and not exists (
+                    select 1 from message_attachment a
+                        join message m on m.id = a.message_id
+                    where a.media_id = md.id and m.deleted_at is not null
+                )
+        ")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    async fn delete_media(&self, pool: &PgPool, ids: &[i64]) -> Result<Vec<String>, Error> {
+        query_scalar::<_, String>("delete from media where id = any($1) returning storage_key")
+            .bind(ids)
+            .fetch_all(pool)
+            .await
+    }
+
+    async fn delete_superseded_legacy_media(&self, pool: &PgPool) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        query("delete from legacy_avatar la using profile p where p.id = la.profile_id and p.avatar_id is not null")
+            .execute(&mut *tx)
+            .await?;
+        query("delete from legacy_message_image li using message m where m.id = li.message_id and m.deleted_at is not null")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+
+    async fn select_legacy_avatars(&self, pool: &PgPool, after: i64, limit: i64) -> Result<Vec<LegacyMediaQueryResult>, Error> {
+        query_as::<_, LegacyMediaQueryResult>(r"
+            select profile_id as id, profile_id as owner_id, data from legacy_avatar
+                where profile_id > $1
+                order by profile_id
+                limit $2
+        ")
+        .bind(after)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn delete_legacy_avatar(&self, pool: &PgPool, profile_id: i64) -> Result<(), Error> {
+        query("delete from legacy_avatar where profile_id = $1")
+            .bind(profile_id)
+            .execute(pool)
+            .await
+            .map(|_| ())
+    }
+
+    async fn select_legacy_message_images(&self, pool: &PgPool, after: i64, limit: i64) -> Result<Vec<LegacyMediaQueryResult>, Error> {
+        query_as::<_, LegacyMediaQueryResult>(r"
+            select li.message_id as id, m.user_id as owner_id, li.data from legacy_message_image li
+                join message m on m.id = li.message_id
+                where li.message_id > $1
+                order by li.message_id
+                limit $2
+        ")
+        .bind(after)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn attach_legacy_message_image(&self, pool: &PgPool, message_id: i64, attachment: &NewAttachment) -> Result<bool, Error> {
+        let mut tx = pool.begin().await?;
+
+        let attached = query(r"
+            insert into message_attachment (message_id, position, media_id, alt_text, width, height)
+                select m.id, 0, $2, $3, $4, $5 from message m
+                where m.id = $1 and m.deleted_at is null
+                    and not exists (select 1 from message_attachment a where a.message_id = m.id)
+        ")
+        .bind(message_id)
+        .bind(attachment.media_id)
+        .bind(&attachment.alt_text)
+        .bind(attachment.width)
+        .bind(attachment.height)
+        .execute(&mut *tx)
+        .await?;
+        query("delete from legacy_message_image where message_id = $1")
+            .bind(message_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(attached.rows_affected() > 0)
+    }
+}
@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
 use sqlx::FromRow;
 
 #[derive(Serialize, Deserialize, FromRow)]
@@ -9,7 +10,6 @@ pub struct MessageQueryResult {
     pub updated_at: DateTime<Utc>,
     pub user_id: i64,
     pub body: Option<String>,
-    pub image: Option<Vec<u8>>,
     pub likes: i32
 }
 
@@ -20,12 +20,16 @@ pub struct MessageWithProfileQueryResult {
     pub updated_at: DateTime<Utc>,
     pub body: Option<String>,
     pub likes: i32,
-    pub image: Option<Vec<u8>>,  
+    /// Set once the body has been edited; earlier bodies are kept as revisions.
+    pub edited_at: Option<DateTime<Utc>>,
+    pub attachments: Json<Vec<AttachmentDescriptor>>,  
+    pub mentions: Json<Vec<MentionEntity>>,
     // profile fields
     pub user_id: i64,
     pub user_name: String,
     pub full_name: String,
-    pub avatar: Option<Vec<u8>>,
+    pub avatar_id: Option<i64>,
+    pub deleted: bool,
     // broadcast message fields
     pub message_broadcast_id: Option<i64>    
 }
@@ -37,20 +41,112 @@ pub struct MessageWithFollowingAndBroadcastQueryResult {
     pub updated_at: DateTime<Utc>,
     pub body: Option<String>,
     pub likes: i32,
-    pub image: Option<Vec<u8>>,    
+    pub edited_at: Option<DateTime<Utc>>,
+    pub attachments: Json<Vec<AttachmentDescriptor>>,    
+    pub mentions: Json<Vec<MentionEntity>>,
     // profile fields
     pub user_id: i64,
     pub user_name: String,
     pub full_name: String,
-    pub avatar: Option<Vec<u8>>,
+    pub avatar_id: Option<i64>,
     // broadcast message fields
     pub message_broadcast_id: Option<i64>,
     pub message_broadcast_updated_at: Option<DateTime<Utc>>,
     pub message_broadcast_body: Option<String>,
     pub message_broadcast_likes: Option<i32>,
-    pub message_broadcast_image: Option<Vec<u8>>,    
+    pub message_broadcast_edited_at: Option<DateTime<Utc>>,
+    pub message_broadcast_attachments: Option<Json<Vec<AttachmentDescriptor>>>,    
+    pub message_broadcast_mentions: Option<Json<Vec<MentionEntity>>>,
     pub message_broadcast_user_id: Option<i64>,
     pub message_broadcast_user_name: Option<String>,
     pub message_broadcast_full_name: Option<String>,
-    pub message_broadcast_avatar: Option<Vec<u8>>
-}
\ No newline at end of file
+    pub message_broadcast_avatar_id: Option<i64>,
+    /// Set when the broadcast message was deleted; the other broadcast fields stay empty.
+    pub message_broadcast_unavailable: bool
+}
+
+/// An image attached to a message; the bytes are served from `url`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AttachmentDescriptor {
+    pub media_id: i64,
+    pub url: String,
+    pub content_type: String,
+    pub byte_size: i64,
+    pub width: i32,
+    pub height: i32,
+    pub alt_text: Option<String>
+}
+
+/// A resolved `@user_name` in a message body, spanning chars `start..end` including the `@`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MentionEntity {
+    pub start: i32,
+    pub end: i32,
+    pub profile_id: i64
+}
+
+/// An attachment already written to the media store, to be linked to a new message.
+pub struct NewAttachment {
+    pub media_id: i64,
+    pub alt_text: Option<String>,
+    pub width: i32,
+    pub height: i32
+}
+
+#[derive(FromRow)]
+pub struct MessageAuthorQueryResult {
+    pub user_id: i64,
+    pub created_at: DateTime<Utc>
+}
+
+/// A body a message had before it was edited.
+#[derive(Serialize, Deserialize, FromRow)]
+pub struct MessageRevisionQueryResult {
+    pub replaced_at: DateTime<Utc>,
+    pub body: Option<String>
+}
+
+pub struct PurgedMessages {
+    pub message_count: usize,
+    /// Keys of attachment objects that now have to be removed from the media store.
+    pub storage_keys: Vec<String>
+}
+
+/// Which of an author's messages to list.
+pub struct AuthorMessageFilter {
+    pub include_replies: bool,
+    pub include_broadcasts: bool,
+    /// Only messages with attachments, or broadcasting a message that has them.
+    pub only_media: bool
+}
+
+/// Keyset position in a message listing; `id` breaks ties between equal `updated_at`s.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MessageCursor {
+    pub updated_at: DateTime<Utc>,
+    pub id: i64
+}
+
+/// A message positioned in a reply thread. `depth` is relative to the message the
+/// thread was requested for: negative for ancestors, 0 for the message itself and
+/// positive for replies. `path` orders replies depth first.
+#[derive(Serialize, Deserialize, FromRow)]
+pub struct ThreadMessageQueryResult {
+    pub id: i64,
+    pub updated_at: DateTime<Utc>,
+    pub body: Option<String>,
+    pub likes: i32,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub attachments: Json<Vec<AttachmentDescriptor>>,
+    pub mentions: Json<Vec<MentionEntity>>,
+    pub user_id: i64,
+    pub user_name: String,
+    pub full_name: String,
+    pub avatar_id: Option<i64>,
+    /// Deleted messages stay in threads as placeholders without content.
+    pub deleted: bool,
+    pub parent_id: Option<i64>,
+    pub depth: i32,
+    pub reply_count: i64,
+    pub path: Vec<i64>
+}
@@ -1,33 +1,102 @@
 use async_trait::async_trait;
+use sqlx::{Error, PgConnection, PgPool};
 use chrono::{DateTime, Utc};
-use sqlx::{Error, PgPool};
-use sqlx::query_as;
+use sqlx::{query, query_as, query_scalar};
+use crate::lib::entities::{extract_hashtags, extract_mentions};
 use crate::repository::repo::{DbRepo, EntityId};
 use tracing::error;
-use super::message_models::{MessageWithFollowingAndBroadcastQueryResult, MessageWithProfileQueryResult};
+use super::message_models::{AuthorMessageFilter, MessageAuthorQueryResult, MessageCursor, MessageRevisionQueryResult, NewAttachment, PurgedMessages, MessageWithFollowingAndBroadcastQueryResult, MessageWithProfileQueryResult, ThreadMessageQueryResult};
 
 #[async_trait]
 pub trait MessageRepo {
-    async fn insert_message(&self, pool: &PgPool, user_id: i64, body: &str, broadcasting_msg_id: Option<i64>) -> Result<EntityId, Error>;    
+    /// Attachments are stored in the order given.
+    async fn insert_message(
+        &self,
+        pool: &PgPool,
+        user_id: i64,
+        body: &str,
+        broadcasting_msg_id: Option<i64>,
+        attachments: &[NewAttachment]
+    ) -> Result<EntityId, Error>;
     async fn insert_response_message(
+        &self,
         conn: &PgPool,
         user_id: i64,
         body: &str,
         original_msg_id: i64
     ) -> Result<i64, sqlx::Error>;
     async fn select_message(&self, pool: &PgPool, id: i64) -> Result<Option<MessageWithFollowingAndBroadcastQueryResult>, Error>;
+    /// Home timeline of the profiles `user_id` follows, newest first, starting after `before`.
     async fn select_messages(
+        &self,
         conn: &PgPool,
         user_id: i64,
-        last_updated_at: DateTime<Utc>,
-        page_size: i16
+        before: Option<MessageCursor>,
+        limit: i64
+    ) -> Result<Vec<MessageWithFollowingAndBroadcastQueryResult>, sqlx::Error>;
+    /// Messages posted by `author_id`, newest first, starting after `before`.
+    async fn select_messages_by_author(
+        &self,
+        conn: &PgPool,
+        author_id: i64,
+        filter: AuthorMessageFilter,
+        before: Option<MessageCursor>,
+        limit: i64
     ) -> Result<Vec<MessageWithFollowingAndBroadcastQueryResult>, sqlx::Error>;
+    /// Messages using the normalized `hashtag`, newest first, starting after `before`.
+    async fn select_messages_by_hashtag(
+        &self,
+        conn: &PgPool,
+        hashtag: &str,
+        before: Option<MessageCursor>,
+        limit: i64
+    ) -> Result<Vec<MessageWithFollowingAndBroadcastQueryResult>, sqlx::Error>;
+    /// Messages by other profiles that mention `profile_id`, newest first, starting after `before`.
+    async fn select_messages_mentioning(
+        &self,
+        conn: &PgPool,
+        profile_id: i64,
+        before: Option<MessageCursor>,
+        limit: i64
+    ) -> Result<Vec<MessageWithFollowingAndBroadcastQueryResult>, sqlx::Error>;
+    /// Author and posting time of a message that has not been deleted.
+    async fn select_message_author(&self, pool: &PgPool, id: i64) -> Result<Option<MessageAuthorQueryResult>, Error>;
+    /// Replaces the body of a message, keeping the previous one as a revision. The message
+    /// keeps its `updated_at`, and so its place in timelines. Returns the new `edited_at`,
+    /// or `None` if the message does not exist or was deleted.
+    async fn update_message_body(&self, pool: &PgPool, id: i64, body: &str) -> Result<Option<DateTime<Utc>>, Error>;
+    /// Earlier bodies of a message, most recently replaced first.
+    async fn select_message_revisions(&self, pool: &PgPool, id: i64) -> Result<Vec<MessageRevisionQueryResult>, Error>;
+    /// Marks a message deleted, leaving a tombstone for the broadcasts and replies that
+    /// reference it. Returns `false` if it was already deleted or does not exist.
+    async fn soft_delete_message(&self, pool: &PgPool, id: i64) -> Result<bool, Error>;
+    /// Strips the content of up to `limit` messages deleted before `deleted_before`,
+    /// including their likes and attachments.
+    async fn purge_deleted_messages(&self, pool: &PgPool, deleted_before: DateTime<Utc>, limit: i64) -> Result<PurgedMessages, Error>;
+    async fn select_thread_message(&self, conn: &PgPool, id: i64) -> Result<Option<ThreadMessageQueryResult>, sqlx::Error>;
+    /// Messages `id` replies to, from the root of the thread down to its direct parent.
+    async fn select_message_ancestors(&self, conn: &PgPool, id: i64) -> Result<Vec<ThreadMessageQueryResult>, sqlx::Error>;
+    /// Replies below `id`, depth first, starting after the reply at `after_path`.
+    async fn select_message_descendants(
+        &self,
+        conn: &PgPool,
+        id: i64,
+        after_path: Option<Vec<i64>>,
+        limit: i64
+    ) -> Result<Vec<ThreadMessageQueryResult>, sqlx::Error>;
 }
 
 #[async_trait]
 impl MessageRepo for DbRepo {
-    async fn insert_message(&self, pool: &PgPool, user_id: i64, body: &str, broadcasting_msg_id: Option<i64>) -> Result<EntityId, Error> {
-        let mut tx = pool.begin().await.unwrap();
+    async fn insert_message(
+        &self,
+        pool: &PgPool,
+        user_id: i64,
+        body: &str,
+        broadcasting_msg_id: Option<i64>,
+        attachments: &[NewAttachment]
+    ) -> Result<EntityId, Error> {
+        let mut tx = pool.begin().await?;
 
         let insert_msg_result = query_as::<_, EntityId>(
                 "insert into message (user_id, body) values ($1, $2) returning id"
@@ -59,18 +128,44 @@ impl MessageRepo for DbRepo {
             }
         }
 
-        _ = tx.commit().await;
+        for (position, attachment) in attachments.iter().enumerate() {
+            if let Err(e) = query(r"
+                    insert into message_attachment (message_id, position, media_id, alt_text, width, height)
+                    values ($1, $2, $3, $4, $5, $6)
+                ")
+                .bind(message_id)
+                .bind(position as i16)
+                .bind(attachment.media_id)
+                .bind(&attachment.alt_text)
+                .bind(attachment.width)
+                .bind(attachment.height)
+                .execute(&mut *tx)
+                .await {
+                    error!("insert_message attachment error: {}", e);
+                    _ = tx.rollback().await;
+                    return Err(e);
+                }
+        }
+
+        if let Err(e) = insert_message_entities(&mut tx, message_id, body).await {
+            error!("insert_message entity error: {}", e);
+            _ = tx.rollback().await;
+            return Err(e);
+        }
+
+        tx.commit().await?;
 
         Ok(EntityId { id: message_id })
     }
 
     async fn insert_response_message(
+        &self,
         conn: &PgPool,
         user_id: i64,
         body: &str,
         original_msg_id: i64
     ) -> Result<i64, sqlx::Error> {
-        let mut tx = conn.begin().await.unwrap();
+        let mut tx = conn.begin().await?;
 
         match query_as::<_, EntityId>(
                 "insert into message (user_id, body) values ($1, $2) returning id"
@@ -88,7 +183,12 @@ impl MessageRepo for DbRepo {
                     .fetch_one(&mut *tx)
                     .await {                        
                         Ok(_) => {
-                            _ = tx.commit().await;
+                            if let Err(e) = insert_message_entities(&mut tx, msg_entity.id, body).await {
+                                error!("insert_response_message entity error: {}", e);
+                                _ = tx.rollback().await;
+                                return Err(e);
+                            }
+                            tx.commit().await?;
                             Ok(msg_entity.id)
                         },
                         Err(e) => {
@@ -113,12 +213,13 @@ impl MessageRepo for DbRepo {
     ) -> Result<Option<MessageWithFollowingAndBroadcastQueryResult>, sqlx::Error> {
         let message_result = query_as::<_, MessageWithProfileQueryResult>(
             r"
-                select m.id, m.updated_at, m.body, m.likes, m.image, m.user_id, p.user_name, p.full_name, p.avatar, mb.id as message_broadcast_id                    
+                select m.id, m.updated_at, case when m.deleted_at is null then m.body end as body, m.likes, m.edited_at, message_attachments(m.id) as attachments, message_mentions(m.id) as mentions, m.user_id, p.user_name, p.full_name, p.avatar_id, m.deleted_at is not null as deleted, mb.id as message_broadcast_id                    
                     from message m 
                         join profile p on m.user_id = p.id
                         left join message_broadcast mb on m.id = mb.main_msg_id
                     where
                         m.id = $1
+                        and m.deleted_at is null
             "
             )
             .bind(id)
@@ -145,51 +246,437 @@ impl MessageRepo for DbRepo {
     }
 
     async fn select_messages(
+        &self,
         conn: &PgPool,
         user_id: i64,
-        last_updated_at: DateTime<Utc>,
-        page_size: i16
+        before: Option<MessageCursor>,
+        limit: i64
     ) -> Result<Vec<MessageWithFollowingAndBroadcastQueryResult>, sqlx::Error> {
+        let (before_updated_at, before_id) = match before {
+            Some(cursor) => (Some(cursor.updated_at), Some(cursor.id)),
+            None => (None, None)
+        };
+
         match query_as::<_, MessageWithProfileQueryResult>(
                 r"
-                select m.id, m.updated_at, m.body, m.likes, m.image, m.msg_group_type, m.user_id, p.user_name, p.full_name, p.avatar, mb.id as broadcast_msg_id                    
+                select m.id, m.updated_at, case when m.deleted_at is null then m.body end as body, m.likes, m.edited_at, message_attachments(m.id) as attachments, message_mentions(m.id) as mentions, m.user_id, p.user_name, p.full_name, p.avatar_id, m.deleted_at is not null as deleted, mb.id as message_broadcast_id
                     from message m 
                         join follow f on m.user_id = f.following_id
                         join profile p on p.id = f.following_id
                         left join message_broadcast mb on m.id = mb.main_msg_id
                         where
                             f.follower_id = $1 
-                            and m.updated_at < $2
-                        order by m.updated_at desc 
-                        limit $3
+                            and m.deleted_at is null
+                            and ($2::timestamptz is null or (m.updated_at, m.id) < ($2, $3))
+                        order by m.updated_at desc, m.id desc
+                        limit $4
             "
             )
             .bind(user_id)
-            .bind(last_updated_at)
-            .bind(page_size)
+            .bind(before_updated_at)
+            .bind(before_id)
+            .bind(limit)
             .fetch_all(conn)
             .await {                
-                Ok(following_messages) => {
-                    let following_messages_with_broadcasts = following_messages                        
-                        .iter()
-                        .filter(|msg| {
-                            msg.message_broadcast_id.is_some() && msg.message_broadcast_id.unwrap() > 0
-                        })
-                        .collect::<Vec<&MessageWithProfileQueryResult>>();
-
-                    let optional_matching_broadcast_messages = get_broadcasting_messages_of_messages(
-                        conn,
-                        following_messages_with_broadcasts
-                    ).await;
-                    let final_message_list = append_broadcast_msgs_to_msgs(
-                        &optional_matching_broadcast_messages,
-                        &following_messages
-                    );
-                    Ok(final_message_list)
-                }
+                Ok(following_messages) => Ok(expand_broadcasts(conn, following_messages).await),
                 Err(e) => Err(e),
             }
     }
+
+    async fn select_messages_by_author(
+        &self,
+        conn: &PgPool,
+        author_id: i64,
+        filter: AuthorMessageFilter,
+        before: Option<MessageCursor>,
+        limit: i64
+    ) -> Result<Vec<MessageWithFollowingAndBroadcastQueryResult>, sqlx::Error> {
+        let (before_updated_at, before_id) = match before {
+            Some(cursor) => (Some(cursor.updated_at), Some(cursor.id)),
+            None => (None, None)
+        };
+
+        let author_messages = query_as::<_, MessageWithProfileQueryResult>(
+                r"
+                select m.id, m.updated_at, case when m.deleted_at is null then m.body end as body, m.likes, m.edited_at, message_attachments(m.id) as attachments, message_mentions(m.id) as mentions, m.user_id, p.user_name, p.full_name, p.avatar_id, m.deleted_at is not null as deleted, mb.id as message_broadcast_id
+                    from message m
+                        join profile p on p.id = m.user_id
+                        left join message_broadcast mb on m.id = mb.main_msg_id
+                        where
+                            m.user_id = $1
+                            and m.deleted_at is null
+                            and ($2 or not exists (select 1 from message_response r where r.responding_msg_id = m.id))
+                            and ($3 or mb.id is null)
+                            and (not $4 or exists (
+                                select 1 from message_attachment a
+                                    where a.message_id = m.id or a.message_id = mb.broadcasting_msg_id
+                            ))
+                            and ($5::timestamptz is null or (m.updated_at, m.id) < ($5, $6))
+                        order by m.updated_at desc, m.id desc
+                        limit $7
+            "
+            )
+            .bind(author_id)
+            .bind(filter.include_replies)
+            .bind(filter.include_broadcasts)
+            .bind(filter.only_media)
+            .bind(before_updated_at)
+            .bind(before_id)
+            .bind(limit)
+            .fetch_all(conn)
+            .await?;
+
+        Ok(expand_broadcasts(conn, author_messages).await)
+    }
+
+    async fn select_messages_by_hashtag(
+        &self,
+        conn: &PgPool,
+        hashtag: &str,
+        before: Option<MessageCursor>,
+        limit: i64
+    ) -> Result<Vec<MessageWithFollowingAndBroadcastQueryResult>, sqlx::Error> {
+        let (before_updated_at, before_id) = match before {
+            Some(cursor) => (Some(cursor.updated_at), Some(cursor.id)),
+            None => (None, None)
+        };
+
+        let hashtag_messages = query_as::<_, MessageWithProfileQueryResult>(
+                r"
+                select m.id, m.updated_at, case when m.deleted_at is null then m.body end as body, m.likes, m.edited_at, message_attachments(m.id) as attachments, message_mentions(m.id) as mentions, m.user_id, p.user_name, p.full_name, p.avatar_id, m.deleted_at is not null as deleted, mb.id as message_broadcast_id
+                    from hashtag h
+                        join message_hashtag mh on mh.hashtag_id = h.id
+                        join message m on m.id = mh.message_id
+                        join profile p on p.id = m.user_id
+                        left join message_broadcast mb on m.id = mb.main_msg_id
+                        where
+                            h.name = $1
+                            and m.deleted_at is null
+                            and ($2::timestamptz is null or (m.updated_at, m.id) < ($2, $3))
+                        order by m.updated_at desc, m.id desc
+                        limit $4
+            "
+            )
+            .bind(hashtag)
+            .bind(before_updated_at)
+            .bind(before_id)
+            .bind(limit)
+            .fetch_all(conn)
+            .await?;
+
+        Ok(expand_broadcasts(conn, hashtag_messages).await)
+    }
+
+    async fn select_messages_mentioning(
+        &self,
+        conn: &PgPool,
+        profile_id: i64,
+        before: Option<MessageCursor>,
+        limit: i64
+    ) -> Result<Vec<MessageWithFollowingAndBroadcastQueryResult>, sqlx::Error> {
+        let (before_updated_at, before_id) = match before {
+            Some(cursor) => (Some(cursor.updated_at), Some(cursor.id)),
+            None => (None, None)
+        };
+
+        let mentioning_messages = query_as::<_, MessageWithProfileQueryResult>(
+                r"
+                select m.id, m.updated_at, case when m.deleted_at is null then m.body end as body, m.likes, m.edited_at, message_attachments(m.id) as attachments, message_mentions(m.id) as mentions, m.user_id, p.user_name, p.full_name, p.avatar_id, m.deleted_at is not null as deleted, mb.id as message_broadcast_id
+                    from message m
+                        join profile p on p.id = m.user_id
+                        left join message_broadcast mb on m.id = mb.main_msg_id
+                        where
+                            exists (select 1 from message_mention mm where mm.message_id = m.id and mm.profile_id = $1)
+                            and m.user_id <> $1
+                            and m.deleted_at is null
+                            and ($2::timestamptz is null or (m.updated_at, m.id) < ($2, $3))
+                        order by m.updated_at desc, m.id desc
+                        limit $4
+            "
+            )
+            .bind(profile_id)
+            .bind(before_updated_at)
+            .bind(before_id)
+            .bind(limit)
+            .fetch_all(conn)
+            .await?;
+
+        Ok(expand_broadcasts(conn, mentioning_messages).await)
+    }
+
+    async fn select_message_author(&self, pool: &PgPool, id: i64) -> Result<Option<MessageAuthorQueryResult>, Error> {
+        query_as::<_, MessageAuthorQueryResult>("select user_id, created_at from message where id = $1 and deleted_at is null")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    async fn update_message_body(&self, pool: &PgPool, id: i64, body: &str) -> Result<Option<DateTime<Utc>>, Error> {
+        let mut tx = pool.begin().await?;
+
+        let previous_body = match query_scalar::<_, Option<String>>(
+                "select body from message where id = $1 and deleted_at is null for update"
+            )
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await? {
+                Some(previous_body) => previous_body,
+                None => {
+                    _ = tx.rollback().await;
+                    return Ok(None);
+                }
+            };
+
+        query("insert into message_revision (message_id, body) values ($1, $2)")
+            .bind(id)
+            .bind(previous_body)
+            .execute(&mut *tx)
+            .await?;
+        let edited_at = query_scalar::<_, DateTime<Utc>>(
+                "update message set body = $2, edited_at = now() where id = $1 returning edited_at"
+            )
+            .bind(id)
+            .bind(body)
+            .fetch_one(&mut *tx)
+            .await?;
+        query(r"
+            delete from message_hashtag mh
+                using hashtag h
+                where h.id = mh.hashtag_id and mh.message_id = $1 and h.name <> all($2)
+        ")
+        .bind(id)
+        .bind(extract_hashtags(body))
+        .execute(&mut *tx)
+        .await?;
+        // offsets move with any edit, so mentions are always resolved again
+        query("delete from message_mention where message_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        insert_message_entities(&mut tx, id, body).await?;
+
+        tx.commit().await?;
+        Ok(Some(edited_at))
+    }
+
+    async fn select_message_revisions(&self, pool: &PgPool, id: i64) -> Result<Vec<MessageRevisionQueryResult>, Error> {
+        query_as::<_, MessageRevisionQueryResult>(
+            "select created_at as replaced_at, body from message_revision where message_id = $1 order by id desc"
+        )
+        .bind(id)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn soft_delete_message(&self, pool: &PgPool, id: i64) -> Result<bool, Error> {
+        let deleted = query("update message set deleted_at = now() where id = $1 and deleted_at is null")
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(deleted.rows_affected() > 0)
+    }
+
+    async fn purge_deleted_messages(&self, pool: &PgPool, deleted_before: DateTime<Utc>, limit: i64) -> Result<PurgedMessages, Error> {
+        let mut tx = pool.begin().await?;
+
+        let message_ids = query_scalar::<_, i64>(r"
+            select id from message
+                where deleted_at < $1 and purged_at is null
+                order by deleted_at
+                limit $2
+                for update skip locked
+        ")
+        .bind(deleted_before)
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?;
+        if message_ids.is_empty() {
+            _ = tx.rollback().await;
+            return Ok(PurgedMessages { message_count: 0, storage_keys: vec![] });
+        }
+
+        query("delete from message_revision where message_id = any($1)")
+            .bind(&message_ids)
+            .execute(&mut *tx)
+            .await?;
+        query("delete from message_mention where message_id = any($1)")
+            .bind(&message_ids)
+            .execute(&mut *tx)
+            .await?;
+        query("delete from message_hashtag where message_id = any($1)")
+            .bind(&message_ids)
+            .execute(&mut *tx)
+            .await?;
+        query("delete from message_like where message_id = any($1)")
+            .bind(&message_ids)
+            .execute(&mut *tx)
+            .await?;
+        let media_ids = query_scalar::<_, i64>("delete from message_attachment where message_id = any($1) returning media_id")
+            .bind(&message_ids)
+            .fetch_all(&mut *tx)
+            .await?;
+        let storage_keys = query_scalar::<_, String>("delete from media where id = any($1) returning storage_key")
+            .bind(&media_ids)
+            .fetch_all(&mut *tx)
+            .await?;
+        query("update message set body = null, purged_at = now() where id = any($1)")
+            .bind(&message_ids)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(PurgedMessages { message_count: message_ids.len(), storage_keys })
+    }
+
+    async fn select_thread_message(&self, conn: &PgPool, id: i64) -> Result<Option<ThreadMessageQueryResult>, sqlx::Error> {
+        query_as::<_, ThreadMessageQueryResult>(
+            r"
+            select m.id, m.updated_at, case when m.deleted_at is null then m.body end as body, m.likes, m.edited_at, message_attachments(m.id) as attachments, message_mentions(m.id) as mentions, m.user_id, p.user_name, p.full_name, p.avatar_id, m.deleted_at is not null as deleted,
+                    parent.original_msg_id as parent_id,
+                    0 as depth,
+                    (select count(*) from message_response r where r.original_msg_id = m.id) as reply_count,
+                    array[m.id] as path
+                from message m
+                    join profile p on p.id = m.user_id
+                    left join message_response parent on parent.responding_msg_id = m.id
+                where m.id = $1
+        "
+        )
+        .bind(id)
+        .fetch_optional(conn)
+        .await
+    }
+
+    async fn select_message_ancestors(&self, conn: &PgPool, id: i64) -> Result<Vec<ThreadMessageQueryResult>, sqlx::Error> {
+        query_as::<_, ThreadMessageQueryResult>(
+            r"
+            with recursive ancestors as (
+                select mr.original_msg_id as id, 1 as distance
+                    from message_response mr
+                    where mr.responding_msg_id = $1
+                union all
+                select mr.original_msg_id, a.distance + 1
+                    from message_response mr
+                        join ancestors a on mr.responding_msg_id = a.id
+            )
+            select m.id, m.updated_at, case when m.deleted_at is null then m.body end as body, m.likes, m.edited_at, message_attachments(m.id) as attachments, message_mentions(m.id) as mentions, m.user_id, p.user_name, p.full_name, p.avatar_id, m.deleted_at is not null as deleted,
+                    parent.original_msg_id as parent_id,
+                    -a.distance as depth,
+                    (select count(*) from message_response r where r.original_msg_id = m.id) as reply_count,
+                    array[m.id] as path
+                from ancestors a
+                    join message m on m.id = a.id
+                    join profile p on p.id = m.user_id
+                    left join message_response parent on parent.responding_msg_id = m.id
+                order by a.distance desc
+        "
+        )
+        .bind(id)
+        .fetch_all(conn)
+        .await
+    }
+
+    async fn select_message_descendants(
+        &self,
+        conn: &PgPool,
+        id: i64,
+        after_path: Option<Vec<i64>>,
+        limit: i64
+    ) -> Result<Vec<ThreadMessageQueryResult>, sqlx::Error> {
+        query_as::<_, ThreadMessageQueryResult>(
+            r"
+            with recursive descendants as (
+                select mr.responding_msg_id as id, mr.original_msg_id as parent_id, 1 as depth, array[mr.responding_msg_id] as path
+                    from message_response mr
+                    where mr.original_msg_id = $1
+                union all
+                select mr.responding_msg_id, mr.original_msg_id, d.depth + 1, d.path || mr.responding_msg_id
+                    from message_response mr
+                        join descendants d on mr.original_msg_id = d.id
+            )
+            select m.id, m.updated_at, case when m.deleted_at is null then m.body end as body, m.likes, m.edited_at, message_attachments(m.id) as attachments, message_mentions(m.id) as mentions, m.user_id, p.user_name, p.full_name, p.avatar_id, m.deleted_at is not null as deleted,
+                    d.parent_id,
+                    d.depth,
+                    (select count(*) from message_response r where r.original_msg_id = m.id) as reply_count,
+                    d.path
+                from descendants d
+                    join message m on m.id = d.id
+                    join profile p on p.id = m.user_id
+                where $2::bigint[] is null or d.path > $2
+                order by d.path
+                limit $3
+        "
+        )
+        .bind(id)
+        .bind(after_path)
+        .bind(limit)
+        .fetch_all(conn)
+        .await
+    }
+}
+
+/// Links a message to the hashtags used in `body`, creating the ones seen for the first
+/// time, and to the profiles it mentions. Mentions of unknown user names are dropped.
+async fn insert_message_entities(conn: &mut PgConnection, message_id: i64, body: &str) -> Result<(), Error> {
+    let mut hashtags = extract_hashtags(body);
+    if !hashtags.is_empty() {
+        // messages sharing tags lock their rows in the same order, so they can't deadlock
+        hashtags.sort();
+        // the no-op update makes existing tags return their id too
+        query(r"
+            with tag as (
+                insert into hashtag (name) select unnest($2::varchar[])
+                    on conflict (name) do update set name = excluded.name
+                    returning id
+            )
+            insert into message_hashtag (message_id, hashtag_id)
+                select $1, id from tag
+                on conflict do nothing
+        ")
+        .bind(message_id)
+        .bind(hashtags)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    let mentions = extract_mentions(body);
+    if !mentions.is_empty() {
+        query(r"
+            insert into message_mention (message_id, profile_id, start_offset, end_offset)
+                select $1, p.id, e.start_offset, e.end_offset
+                    from unnest($2::varchar[], $3::int[], $4::int[]) as e(user_name, start_offset, end_offset)
+                        join profile p on lower(p.user_name) = lower(e.user_name)
+        ")
+        .bind(message_id)
+        .bind(mentions.iter().map(|m| m.user_name.as_str()).collect::<Vec<&str>>())
+        .bind(mentions.iter().map(|m| m.start).collect::<Vec<i32>>())
+        .bind(mentions.iter().map(|m| m.end).collect::<Vec<i32>>())
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Fills in the message each row broadcasts, fetching all of them in one query.
+async fn expand_broadcasts(
+    conn: &PgPool,
+    messages: Vec<MessageWithProfileQueryResult>
+) -> Vec<MessageWithFollowingAndBroadcastQueryResult> {
+    let messages_with_broadcasts = messages
+        .iter()
+        .filter(|msg| {
+            msg.message_broadcast_id.is_some() && msg.message_broadcast_id.unwrap() > 0
+        })
+        .collect::<Vec<&MessageWithProfileQueryResult>>();
+
+    let optional_matching_broadcast_messages = get_broadcasting_messages_of_messages(
+        conn,
+        messages_with_broadcasts
+    ).await;
+    append_broadcast_msgs_to_msgs(
+        &optional_matching_broadcast_messages,
+        &messages
+    )
 }
 
 async fn get_broadcasting_messages_of_messages(
@@ -203,11 +690,11 @@ async fn get_broadcasting_messages_of_messages(
 
     match query_as::<_, MessageWithProfileQueryResult>(
             r"
-            select m.id, m.updated_at, m.body, m.likes, m.image, m.msg_group_type, m.user_id, p.user_name, p.full_name, p.avatar, mb.id as broadcast_msg_id
+            select m.id, m.updated_at, case when m.deleted_at is null then m.body end as body, m.likes, m.edited_at, message_attachments(m.id) as attachments, message_mentions(m.id) as mentions, m.user_id, p.user_name, p.full_name, p.avatar_id, m.deleted_at is not null as deleted, mb.id as message_broadcast_id
                 from message m 
                     join profile p on m.user_id = p.id
-                    left join message_broadcast mb on m.id = mb.main_msg_id
-                where m.id = ANY($1)
+                    join message_broadcast mb on m.id = mb.broadcasting_msg_id
+                where mb.id = ANY($1)
         "
         )
         .bind(following_broadcast_message_ids)
@@ -227,7 +714,7 @@ async fn get_broadcasting_message_of_message(
 ) -> Option<MessageWithProfileQueryResult> {
     match query_as::<_, MessageWithProfileQueryResult>(
             r"
-            select m.id, m.updated_at, m.body, m.likes, m.image, m.user_id, p.user_name, p.full_name, p.avatar, mb.id as message_broadcast_id
+            select m.id, m.updated_at, case when m.deleted_at is null then m.body end as body, m.likes, m.edited_at, message_attachments(m.id) as attachments, message_mentions(m.id) as mentions, m.user_id, p.user_name, p.full_name, p.avatar_id, m.deleted_at is not null as deleted, mb.id as message_broadcast_id
                 from message m 
                     join profile p on m.user_id = p.id
                     left join message_broadcast mb on m.id = mb.broadcasting_msg_id
@@ -247,7 +734,7 @@ async fn get_broadcasting_message_of_message(
 
 fn append_broadcast_msgs_to_msgs(
     optional_broadcast_messages: &Option<Vec<MessageWithProfileQueryResult>>,
-    following_messages_with_broadcasts: &Vec<MessageWithProfileQueryResult>
+    following_messages_with_broadcasts: &[MessageWithProfileQueryResult]
 ) -> Vec<MessageWithFollowingAndBroadcastQueryResult> {
     let mut final_list_of_messages: Vec<MessageWithFollowingAndBroadcastQueryResult> = vec![];
 
@@ -257,7 +744,7 @@ fn append_broadcast_msgs_to_msgs(
         {
             broadcast_messages
                 .iter()
-                .find(|bm| { Some(bm.id) == following_message_with_broadcast.message_broadcast_id })
+                .find(|bm| { bm.message_broadcast_id == following_message_with_broadcast.message_broadcast_id })
         } else {
             None
         };
@@ -282,32 +769,43 @@ fn append_broadcast_msg_to_msg(
         updated_at: message_with_broadcast.updated_at,
         body: message_with_broadcast.body.clone(),
         likes: message_with_broadcast.likes,
-        image: message_with_broadcast.image.clone(),
+        edited_at: message_with_broadcast.edited_at,
+        attachments: message_with_broadcast.attachments.clone(),
+        mentions: message_with_broadcast.mentions.clone(),
         user_id: message_with_broadcast.user_id,
         user_name: message_with_broadcast.user_name.clone(),
         full_name: message_with_broadcast.full_name.clone(),
-        avatar: message_with_broadcast.avatar.clone(),
+        avatar_id: message_with_broadcast.avatar_id,
         message_broadcast_id: None,
         message_broadcast_updated_at: None,
         message_broadcast_user_id: None,
         message_broadcast_body: None,
         message_broadcast_likes: None,
-        message_broadcast_image: None,
+        message_broadcast_edited_at: None,
+        message_broadcast_attachments: None,
+        message_broadcast_mentions: None,
         message_broadcast_user_name: None,
         message_broadcast_full_name: None,
-        message_broadcast_avatar: None,
+        message_broadcast_avatar_id: None,
+        message_broadcast_unavailable: false,
     };
 
-    if let Some(matching_broadcast) = broadcast_message {
+    if let Some(matching_broadcast) = broadcast_message.filter(|broadcast| broadcast.deleted) {
+        // the broadcast message was deleted: keep the reference but render a placeholder
+        final_message.message_broadcast_id = Some(matching_broadcast.id);
+        final_message.message_broadcast_unavailable = true;
+    } else if let Some(matching_broadcast) = broadcast_message {
         final_message.message_broadcast_id = Some(matching_broadcast.id);
         final_message.message_broadcast_updated_at = Some(matching_broadcast.updated_at);
         final_message.message_broadcast_body = matching_broadcast.body.to_owned();
         final_message.message_broadcast_likes = Some(matching_broadcast.likes);
-        final_message.message_broadcast_image = matching_broadcast.image.to_owned();
+        final_message.message_broadcast_edited_at = matching_broadcast.edited_at;
+        final_message.message_broadcast_attachments = Some(matching_broadcast.attachments.clone());
+        final_message.message_broadcast_mentions = Some(matching_broadcast.mentions.clone());
         final_message.message_broadcast_user_id = Some(matching_broadcast.user_id);
         final_message.message_broadcast_user_name = Some(matching_broadcast.user_name.to_string());
         final_message.message_broadcast_full_name = Some(matching_broadcast.full_name.to_string());
-        final_message.message_broadcast_avatar = matching_broadcast.avatar.to_owned();
+        final_message.message_broadcast_avatar_id = matching_broadcast.avatar_id;
     }
 
     final_message
@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::FromRow;
+
+#[derive(Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum NotificationKind {
+    Follow,
+    Like,
+    Reply,
+    Broadcast,
+    Mention
+}
+
+/// A group of similar events for one recipient. `message_id` is the recipient's message
+/// that was liked, replied to or broadcast, or the message that mentioned them; follows
+/// have none. `actors` holds the latest few profiles, `actor_count` all of them.
+#[derive(Serialize, Deserialize, FromRow)]
+pub struct NotificationQueryResult {
+    pub id: i64,
+    pub kind: NotificationKind,
+    pub message_id: Option<i64>,
+    pub last_event_at: DateTime<Utc>,
+    pub read: bool,
+    pub actor_count: i64,
+    pub actors: Json<Vec<NotificationActor>>
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NotificationActor {
+    pub profile_id: i64,
+    pub user_name: String,
+    pub full_name: String,
+    pub avatar_id: Option<i64>
+}
+
+/// Keyset position in a notification listing. A group moves to the top when it gets a
+/// new event, so it may show up again on a later page.
+#[derive(Serialize, Deserialize)]
+pub struct NotificationCursor {
+    pub last_event_at: DateTime<Utc>,
+    pub id: i64
+}
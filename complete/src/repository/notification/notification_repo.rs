@@ -0,0 +1,114 @@
+use async_trait::async_trait;
+use sqlx::{query, query_as, query_scalar, Error, PgPool};
+use crate::repository::repo::DbRepo;
+use super::notification_models::{NotificationCursor, NotificationQueryResult};
+
+/// Latest three actors are listed, newest first.
+const NOTIFICATION_COLUMNS: &str = r"
+    select n.id, n.kind, n.message_id, n.last_event_at, n.read_at is not null as read,
+        (select count(*) from notification_actor na where na.notification_id = n.id) as actor_count,
+        (
+            select coalesce(jsonb_agg(jsonb_build_object(
+                'profile_id', p.id,
+                'user_name', p.user_name,
+                'full_name', p.full_name,
+                'avatar_id', p.avatar_id
+            ) order by latest.created_at desc), '[]'::jsonb)
+            from (
+                select na.actor_id, na.created_at from notification_actor na
+                    where na.notification_id = n.id
+                    order by na.created_at desc
+                    limit 3
+            ) latest
+                join profile p on p.id = latest.actor_id
+        ) as actors";
+
+/// Notifications are written by triggers on the follow, like, reply, broadcast and
+/// mention tables, so this only reads them and tracks what has been read.
+#[async_trait]
+pub trait NotificationRepo {
+    /// Notifications for `profile_id`, most recent event first, starting after `before`.
+    /// Notifications about deleted messages are left out.
+    async fn select_notifications(
+        &self,
+        pool: &PgPool,
+        profile_id: i64,
+        before: Option<NotificationCursor>,
+        limit: i64
+    ) -> Result<Vec<NotificationQueryResult>, Error>;
+    /// A single notification, unless it is about a deleted message.
+    async fn select_notification(&self, pool: &PgPool, id: i64) -> Result<Option<NotificationQueryResult>, Error>;
+    async fn select_unread_notification_count(&self, pool: &PgPool, profile_id: i64) -> Result<i64, Error>;
+    /// Marks the given notifications of `profile_id` read, or all of them when `ids` is
+    /// `None`. Returns how many were unread.
+    async fn mark_notifications_read(&self, pool: &PgPool, profile_id: i64, ids: Option<&[i64]>) -> Result<u64, Error>;
+}
+
+#[async_trait]
+impl NotificationRepo for DbRepo {
+    async fn select_notifications(
+        &self,
+        pool: &PgPool,
+        profile_id: i64,
+        before: Option<NotificationCursor>,
+        limit: i64
+    ) -> Result<Vec<NotificationQueryResult>, Error> {
+        let (before_last_event_at, before_id) = match before {
+            Some(cursor) => (Some(cursor.last_event_at), Some(cursor.id)),
+            None => (None, None)
+        };
+
+        query_as::<_, NotificationQueryResult>(&format!(r"
+            {NOTIFICATION_COLUMNS}
+                from notification n
+                    left join message m on m.id = n.message_id
+                where
+                    n.recipient_id = $1
+                    and m.deleted_at is null
+                    and ($2::timestamptz is null or (n.last_event_at, n.id) < ($2, $3))
+                order by n.last_event_at desc, n.id desc
+                limit $4
+        "))
+        .bind(profile_id)
+        .bind(before_last_event_at)
+        .bind(before_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn select_notification(&self, pool: &PgPool, id: i64) -> Result<Option<NotificationQueryResult>, Error> {
+        query_as::<_, NotificationQueryResult>(&format!(r"
+            {NOTIFICATION_COLUMNS}
+                from notification n
+                    left join message m on m.id = n.message_id
+                where n.id = $1 and m.deleted_at is null
+        "))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    async fn select_unread_notification_count(&self, pool: &PgPool, profile_id: i64) -> Result<i64, Error> {
+        query_scalar::<_, i64>(r"
+            select count(*) from notification n
+                left join message m on m.id = n.message_id
+                where n.recipient_id = $1 and n.read_at is null and m.deleted_at is null
+        ")
+        .bind(profile_id)
+        .fetch_one(pool)
+        .await
+    }
+
+    async fn mark_notifications_read(&self, pool: &PgPool, profile_id: i64, ids: Option<&[i64]>) -> Result<u64, Error> {
+        query(r"
+            update notification set read_at = now()
+                where recipient_id = $1 and read_at is null and ($2::bigint[] is null or id = any($2))
+        ")
+        .bind(profile_id)
+        .bind(ids)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected())
+    }
+}
@@ -12,5 +12,26 @@ pub struct ProfileQueryResult {
     pub description: String,
     pub region: Option<String>,
     pub main_url: Option<String>,
-    pub avatar: Option<Vec<u8>>,
+    pub avatar_id: Option<i64>,
+    pub dm_policy: DmPolicy
+}
+
+/// Who may start a direct conversation with a profile.
+#[derive(Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum DmPolicy {
+    Everyone,
+    /// Only profiles this profile follows.
+    Following
+}
+
+/// Changes to a profile. `None` leaves a field as it is, while `Some(None)` clears one of
+/// the optional fields.
+pub struct ProfileUpdate {
+    pub full_name: Option<String>,
+    pub description: Option<String>,
+    pub region: Option<Option<String>>,
+    pub main_url: Option<Option<String>>,
+    pub dm_policy: Option<DmPolicy>
 }
@@ -1,8 +1,9 @@
 use crate::repository::repo::{DbRepo, EntityId};
 use sqlx::error::Error;
-use sqlx::query_as;
+use sqlx::{query, query_as, query_scalar};
 use sqlx::PgPool;
-use super::profile_models::ProfileQueryResult;
+use crate::repository::media::media_models::MediaQueryResult;
+use super::profile_models::{ProfileQueryResult, ProfileUpdate};
 use async_trait::async_trait;
 
 #[async_trait]
@@ -14,8 +15,7 @@ pub trait InsertProfileFn {
         full_name: String,
         description: String,
         region: Option<String>,
-        main_url: Option<String>,
-        avatar: Option<Vec<u8>>
+        main_url: Option<String>
     ) -> Result<EntityId, Error>;
 }
 
@@ -28,14 +28,13 @@ impl InsertProfileFn for DbRepo {
         full_name: String,
         description: String,
         region: Option<String>,
-        main_url: Option<String>,
-        avatar: Option<Vec<u8>>
+        main_url: Option<String>
     ) -> Result<EntityId, Error> {
         query_as::<_, EntityId>(r"
             insert into Profile
-            (user_name, full_name, description, region, main_url, avatar)
+            (user_name, full_name, description, region, main_url)
             values
-            ($1, $2, $3, $4, $5, $6)
+            ($1, $2, $3, $4, $5)
             returning id
         ")
         .bind(user_name)
@@ -43,7 +42,6 @@ impl InsertProfileFn for DbRepo {
         .bind(description)
         .bind(region)
         .bind(main_url)
-        .bind(avatar)
         .fetch_one(pool)
         .await
     }
@@ -52,6 +50,8 @@ impl InsertProfileFn for DbRepo {
 #[async_trait]
 pub trait SelectProfileFn {
     async fn select_profile(&self, pool: &PgPool, id: i64) -> Result<Option<ProfileQueryResult>, Error>;
+    /// Looks a profile up by handle, ignoring case.
+    async fn select_profile_by_user_name(&self, pool: &PgPool, user_name: &str) -> Result<Option<ProfileQueryResult>, Error>;
 }
 
 #[async_trait]
@@ -62,4 +62,114 @@ impl SelectProfileFn for DbRepo {
             .fetch_optional(conn)
             .await
     }
+
+    async fn select_profile_by_user_name(&self, conn: &PgPool, user_name: &str) -> Result<Option<ProfileQueryResult>, Error> {
+        query_as::<_, ProfileQueryResult>("select * from profile where lower(user_name) = lower($1)")
+            .bind(user_name)
+            .fetch_optional(conn)
+            .await
+    }
+}
+
+#[async_trait]
+pub trait UpdateProfileFn {
+    /// Changes only the fields given as `Some`; returns `None` if the profile does not exist.
+    async fn update_profile(&self, pool: &PgPool, id: i64, update: &ProfileUpdate) -> Result<Option<ProfileQueryResult>, Error>;
+}
+
+#[async_trait]
+impl UpdateProfileFn for DbRepo {
+    async fn update_profile(&self, pool: &PgPool, id: i64, update: &ProfileUpdate) -> Result<Option<ProfileQueryResult>, Error> {
+        query_as::<_, ProfileQueryResult>(r"
+            update profile set
+                full_name = coalesce($2, full_name),
+                description = coalesce($3, description),
+                region = case when $4 then $5 else region end,
+                main_url = case when $6 then $7 else main_url end,
+                dm_policy = coalesce($8, dm_policy)
+            where id = $1
+            returning *
+        ")
+        .bind(id)
+        .bind(&update.full_name)
+        .bind(&update.description)
+        .bind(update.region.is_some())
+        .bind(update.region.as_ref().and_then(Option::as_deref))
+        .bind(update.main_url.is_some())
+        .bind(update.main_url.as_ref().and_then(Option::as_deref))
+        .bind(update.dm_policy)
+        .fetch_optional(pool)
+        .await
+    }
+}
+
+#[async_trait]
+pub trait ProfileAvatarFn {
+    /// Points a profile's avatar at freshly stored media, one per rendered size, with
+    /// `default_media_id` becoming `profile.avatar_id`. The media the old avatar used is
+    /// deleted and its storage keys returned so the objects can be removed from the store.
+    /// Returns `None` if the profile does not exist.
+    async fn upsert_avatar(
+        &self,
+        pool: &PgPool,
+        profile_id: i64,
+        default_media_id: i64,
+        sizes: Vec<(i32, i64)>
+    ) -> Result<Option<Vec<String>>, Error>;
+    async fn select_avatar(&self, pool: &PgPool, profile_id: i64, size: i32) -> Result<Option<MediaQueryResult>, Error>;
+}
+
+#[async_trait]
+impl ProfileAvatarFn for DbRepo {
+    async fn upsert_avatar(
+        &self,
+        pool: &PgPool,
+        profile_id: i64,
+        default_media_id: i64,
+        sizes: Vec<(i32, i64)>
+    ) -> Result<Option<Vec<String>>, Error> {
+        let mut tx = pool.begin().await?;
+
+        let updated = query("update profile set avatar_id = $2 where id = $1")
+            .bind(profile_id)
+            .bind(default_media_id)
+            .execute(&mut *tx)
+            .await?;
+        if updated.rows_affected() == 0 {
+            _ = tx.rollback().await;
+            return Ok(None);
+        }
+
+        let replaced_media_ids = query_scalar::<_, i64>("delete from profile_avatar where profile_id = $1 returning media_id")
+            .bind(profile_id)
+            .fetch_all(&mut *tx)
+            .await?;
+        for (size, media_id) in sizes {
+            query("insert into profile_avatar (profile_id, size, media_id) values ($1, $2, $3)")
+                .bind(profile_id)
+                .bind(size)
+                .bind(media_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        let replaced_keys = query_scalar::<_, String>("delete from media where id = any($1) returning storage_key")
+            .bind(replaced_media_ids)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(Some(replaced_keys))
+    }
+
+    async fn select_avatar(&self, pool: &PgPool, profile_id: i64, size: i32) -> Result<Option<MediaQueryResult>, Error> {
+        query_as::<_, MediaQueryResult>(r"
+            select m.*, true as is_avatar from profile_avatar pa
+            join media m on m.id = pa.media_id
+            where pa.profile_id = $1 and pa.size = $2
+        ")
+        .bind(profile_id)
+        .bind(size)
+        .fetch_optional(pool)
+        .await
+    }
 }
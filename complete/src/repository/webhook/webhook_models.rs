@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::FromRow;
+
+/// A webhook as shown to its owner. The secret is only returned when it is created.
+#[derive(Serialize, Deserialize, FromRow)]
+pub struct WebhookQueryResult {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub consecutive_failures: i32,
+    /// Set once the endpoint failed too often in a row; no deliveries are made after that.
+    pub disabled_at: Option<DateTime<Utc>>
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    /// Gave up after the maximum number of attempts.
+    Failed
+}
+
+/// One event sent, or to be sent, to a webhook, with every attempt made so far.
+#[derive(Serialize, Deserialize, FromRow)]
+pub struct WebhookDeliveryQueryResult {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub event_type: String,
+    pub payload: Json<serde_json::Value>,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub attempt_log: Json<Vec<WebhookDeliveryAttempt>>
+}
+
+/// `response_status` is missing when the endpoint could not be reached or timed out,
+/// in which case `error` says why.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WebhookDeliveryAttempt {
+    pub created_at: DateTime<Utc>,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i32
+}
+
+/// A delivery claimed by a worker, with what it needs to send it.
+#[derive(FromRow)]
+pub struct DueWebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub url: String,
+    pub secret: String,
+    pub event_type: String,
+    pub payload: Json<serde_json::Value>,
+    /// Including the attempt about to be made.
+    pub attempts: i32
+}
+
+/// The outcome of sending a delivery. `retry_at` is when to try again should it have
+/// failed, or `None` to give up.
+pub struct WebhookAttemptResult {
+    pub delivery_id: i64,
+    pub webhook_id: i64,
+    pub succeeded: bool,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i32,
+    pub retry_at: Option<DateTime<Utc>>
+}
+
+/// Keyset position in a webhook's deliveries, newest first.
+#[derive(Serialize, Deserialize)]
+pub struct WebhookDeliveryCursor {
+    pub id: i64
+}
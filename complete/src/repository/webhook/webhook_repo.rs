@@ -0,0 +1,232 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, query_scalar, Error, PgPool};
+use crate::repository::repo::DbRepo;
+use super::webhook_models::{DueWebhookDelivery, WebhookAttemptResult, WebhookDeliveryCursor, WebhookDeliveryQueryResult, WebhookQueryResult};
+
+const WEBHOOK_COLUMNS: &str = "id, created_at, url, event_types, consecutive_failures, disabled_at";
+
+/// Deliveries are queued by triggers when domain events are raised; this manages the
+/// webhooks themselves and the progress of their deliveries.
+#[async_trait]
+pub trait WebhookRepo {
+    async fn insert_webhook(
+        &self,
+        pool: &PgPool,
+        owner_id: i64,
+        url: &str,
+        secret: &str,
+        event_types: &[String]
+    ) -> Result<WebhookQueryResult, Error>;
+    async fn select_webhooks(&self, pool: &PgPool, owner_id: i64) -> Result<Vec<WebhookQueryResult>, Error>;
+    /// The webhook, provided `owner_id` owns it.
+    async fn select_webhook(&self, pool: &PgPool, owner_id: i64, id: i64) -> Result<Option<WebhookQueryResult>, Error>;
+    /// Deletes the webhook along with its deliveries. False when `owner_id` has no such webhook.
+    async fn delete_webhook(&self, pool: &PgPool, owner_id: i64, id: i64) -> Result<bool, Error>;
+    /// Turns a disabled webhook back on with a clean failure count. Deliveries that were
+    /// still pending resume; ones that gave up stay failed.
+    async fn enable_webhook(&self, pool: &PgPool, owner_id: i64, id: i64) -> Result<Option<WebhookQueryResult>, Error>;
+    async fn select_webhook_deliveries(
+        &self,
+        pool: &PgPool,
+        webhook_id: i64,
+        before: Option<WebhookDeliveryCursor>,
+        limit: i64
+    ) -> Result<Vec<WebhookDeliveryQueryResult>, Error>;
+    /// Claims up to `limit` pending deliveries that are due, counting the attempt about
+    /// to be made. Claimed deliveries are not due again until `lease_until`, so other
+    /// workers skip them unless this one never reports back.
+    async fn claim_due_webhook_deliveries(
+        &self,
+        pool: &PgPool,
+        lease_until: DateTime<Utc>,
+        limit: i64
+    ) -> Result<Vec<DueWebhookDelivery>, Error>;
+    /// Records an attempt and moves its delivery and webhook on. A webhook whose failures
+    /// in a row reach `disable_after_failures` is disabled; returns whether this attempt
+    /// was the one that disabled it.
+    async fn record_webhook_attempt(
+        &self,
+        pool: &PgPool,
+        result: &WebhookAttemptResult,
+        disable_after_failures: i32
+    ) -> Result<bool, Error>;
+}
+
+#[async_trait]
+impl WebhookRepo for DbRepo {
+    async fn insert_webhook(
+        &self,
+        pool: &PgPool,
+        owner_id: i64,
+        url: &str,
+        secret: &str,
+        event_types: &[String]
+    ) -> Result<WebhookQueryResult, Error> {
+        query_as::<_, WebhookQueryResult>(&format!(
+            "insert into webhook (owner_id, url, secret, event_types) values ($1, $2, $3, $4) returning {}",
+            WEBHOOK_COLUMNS
+        ))
+        .bind(owner_id)
+        .bind(url)
+        .bind(secret)
+        .bind(event_types)
+        .fetch_one(pool)
+        .await
+    }
+
+    async fn select_webhooks(&self, pool: &PgPool, owner_id: i64) -> Result<Vec<WebhookQueryResult>, Error> {
+        query_as::<_, WebhookQueryResult>(&format!(
+            "select {} from webhook where owner_id = $1 order by id",
+            WEBHOOK_COLUMNS
+        ))
+        .bind(owner_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn select_webhook(&self, pool: &PgPool, owner_id: i64, id: i64) -> Result<Option<WebhookQueryResult>, Error> {
+        query_as::<_, WebhookQueryResult>(&format!(
+            "select {} from webhook where id = $2 and owner_id = $1",
+            WEBHOOK_COLUMNS
+        ))
+        .bind(owner_id)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    async fn delete_webhook(&self, pool: &PgPool, owner_id: i64, id: i64) -> Result<bool, Error> {
+        query("delete from webhook where id = $2 and owner_id = $1")
+            .bind(owner_id)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected() > 0)
+    }
+
+    async fn enable_webhook(&self, pool: &PgPool, owner_id: i64, id: i64) -> Result<Option<WebhookQueryResult>, Error> {
+        query_as::<_, WebhookQueryResult>(&format!(
+            "update webhook set disabled_at = null, consecutive_failures = 0 where id = $2 and owner_id = $1 returning {}",
+            WEBHOOK_COLUMNS
+        ))
+        .bind(owner_id)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    async fn select_webhook_deliveries(
+        &self,
+        pool: &PgPool,
+        webhook_id: i64,
+        before: Option<WebhookDeliveryCursor>,
+        limit: i64
+    ) -> Result<Vec<WebhookDeliveryQueryResult>, Error> {
+        query_as::<_, WebhookDeliveryQueryResult>(r"
+            select d.id, d.created_at, d.event_type, d.payload, d.status, d.attempts, d.next_attempt_at, d.delivered_at,
+                (
+                    select coalesce(jsonb_agg(jsonb_build_object(
+                        'created_at', a.created_at,
+                        'response_status', a.response_status,
+                        'error', a.error,
+                        'duration_ms', a.duration_ms
+                    ) order by a.id), '[]'::jsonb)
+                    from webhook_delivery_attempt a
+                    where a.delivery_id = d.id
+                ) as attempt_log
+                from webhook_delivery d
+                where d.webhook_id = $1 and ($2::bigint is null or d.id < $2)
+                order by d.id desc
+                limit $3
+        ")
+        .bind(webhook_id)
+        .bind(before.map(|cursor| cursor.id))
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn claim_due_webhook_deliveries(
+        &self,
+        pool: &PgPool,
+        lease_until: DateTime<Utc>,
+        limit: i64
+    ) -> Result<Vec<DueWebhookDelivery>, Error> {
+        query_as::<_, DueWebhookDelivery>(r"
+            update webhook_delivery d set attempts = d.attempts + 1, next_attempt_at = $1
+                from webhook w
+                where w.id = d.webhook_id and d.id in (
+                    select due.id from webhook_delivery due
+                        join webhook dw on dw.id = due.webhook_id
+                        where due.status = 'pending' and due.next_attempt_at <= now() and dw.disabled_at is null
+                        order by due.next_attempt_at
+                        limit $2
+                        for update of due skip locked
+                )
+                returning d.id, d.webhook_id, w.url, w.secret, d.event_type, d.payload, d.attempts
+        ")
+        .bind(lease_until)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn record_webhook_attempt(
+        &self,
+        pool: &PgPool,
+        result: &WebhookAttemptResult,
+        disable_after_failures: i32
+    ) -> Result<bool, Error> {
+        let mut tx = pool.begin().await?;
+
+        query("insert into webhook_delivery_attempt (delivery_id, response_status, error, duration_ms) values ($1, $2, $3, $4)")
+            .bind(result.delivery_id)
+            .bind(result.response_status)
+            .bind(&result.error)
+            .bind(result.duration_ms)
+            .execute(&mut *tx)
+            .await?;
+
+        let disabled = if result.succeeded {
+            query("update webhook_delivery set status = 'delivered', delivered_at = now() where id = $1")
+                .bind(result.delivery_id)
+                .execute(&mut *tx)
+                .await?;
+            query("update webhook set consecutive_failures = 0 where id = $1 and consecutive_failures <> 0")
+                .bind(result.webhook_id)
+                .execute(&mut *tx)
+                .await?;
+            false
+        } else {
+            query(r"
+                update webhook_delivery set
+                    status = case when $2::timestamptz is null then 'failed' else 'pending' end,
+                    next_attempt_at = coalesce($2, next_attempt_at)
+                    where id = $1
+            ")
+            .bind(result.delivery_id)
+            .bind(result.retry_at)
+            .execute(&mut *tx)
+            .await?;
+            query_scalar::<_, bool>(r"
+                update webhook set
+                    consecutive_failures = consecutive_failures + 1,
+                    disabled_at = case
+                        when disabled_at is null and consecutive_failures + 1 >= $2 then now()
+                        else disabled_at
+                    end
+                    where id = $1
+                    returning consecutive_failures = $2
+            ")
+            .bind(result.webhook_id)
+            .bind(disable_after_failures)
+            .fetch_optional(&mut *tx)
+            .await?
+            .unwrap_or(false)
+        };
+
+        tx.commit().await?;
+        Ok(disabled)
+    }
+}
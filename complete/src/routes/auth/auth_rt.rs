@@ -0,0 +1,12 @@
+use std::sync::Arc;
+use axum::{extract::State, routing::post, Router};
+use crate::{controllers::auth::auth_ctrl::{login, logout, refresh, register}, lib::app_state::AppState};
+
+pub fn get_auth_routes(State(state): State<Arc<AppState>>) -> Router {
+    Router::new()
+        .route("/auth/register", post(register))
+        .route("/auth/login", post(login))
+        .route("/auth/refresh", post(refresh))
+        .route("/auth/logout", post(logout))
+        .with_state(state)
+}
@@ -0,0 +1,12 @@
+use std::sync::Arc;
+use axum::{extract::State, routing::{get, post}, Router};
+use crate::{controllers::direct_message::direct_message_ctrl::{get_conversation, get_conversations, get_direct_messages, mark_conversation_read, send_direct_message, start_conversation}, lib::app_state::AppState};
+
+pub fn get_direct_message_routes(State(state): State<Arc<AppState>>) -> Router {
+    Router::new()
+        .route("/conversations", post(start_conversation).get(get_conversations))
+        .route("/conversations/:id", get(get_conversation))
+        .route("/conversations/:id/messages", post(send_direct_message).get(get_direct_messages))
+        .route("/conversations/:id/read", post(mark_conversation_read))
+        .with_state(state)
+}
@@ -0,0 +1,11 @@
+use std::sync::Arc;
+use axum::{extract::State, routing::{get, post}, Router};
+use crate::{controllers::follow::follow_ctrl::{follow_profile, get_followers, get_following, unfollow_profile}, lib::app_state::AppState};
+
+pub fn get_follow_routes(State(state): State<Arc<AppState>>) -> Router {
+    Router::new()
+        .route("/profile/:id/follow", post(follow_profile).delete(unfollow_profile))
+        .route("/profile/:id/followers", get(get_followers))
+        .route("/profile/:id/following", get(get_following))
+        .with_state(state)
+}
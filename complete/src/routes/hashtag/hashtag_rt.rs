@@ -0,0 +1,10 @@
+use std::sync::Arc;
+use axum::{extract::State, routing::get, Router};
+use crate::{controllers::hashtag::hashtag_ctrl::{get_hashtag_timeline, get_trends}, lib::app_state::AppState};
+
+pub fn get_hashtag_routes(State(state): State<Arc<AppState>>) -> Router {
+    Router::new()
+        .route("/hashtag/:tag/messages", get(get_hashtag_timeline))
+        .route("/trends", get(get_trends))
+        .with_state(state)
+}
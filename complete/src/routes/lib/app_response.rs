@@ -1,5 +1,6 @@
-use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::{http::StatusCode, response::{IntoResponse, Response}, Json};
 use serde::Serialize;
+use super::error::AppErrors;
 
 pub enum AppResponse<T: Serialize> {
     Ok,
@@ -7,6 +8,17 @@ pub enum AppResponse<T: Serialize> {
     JsonData(T)
 }
 
+impl<T: Serialize> AppResponse<T> {
+    /// Responds with `JsonData` when a lookup found something and with a 404 otherwise,
+    /// so a missing row never renders as `200 null`.
+    pub fn found(data: Option<T>) -> Response {
+        match data {
+            Some(data) => AppResponse::JsonData(data).into_response(),
+            None => AppErrors::NotFound.into_response()
+        }
+    }
+}
+
 impl<T: Serialize> IntoResponse for AppResponse<T> {
     fn into_response(self) -> axum::response::Response {
         match self {
@@ -15,4 +27,4 @@ impl<T: Serialize> IntoResponse for AppResponse<T> {
             AppResponse::JsonData(data) => (StatusCode::OK, Json(data)).into_response()
         }
     }
-}
\ No newline at end of file
+}
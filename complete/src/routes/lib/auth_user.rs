@@ -0,0 +1,31 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+use crate::lib::app_state::AppState;
+use super::error::AppErrors;
+
+/// The authenticated caller, resolved from an `Authorization: Bearer <token>` header.
+/// Handlers that act on behalf of a user take the author from here, never from the payload.
+pub struct AuthUser {
+    pub profile_id: i64
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for AuthUser {
+    type Rejection = AppErrors;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        let token = parts.headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(AppErrors::Unauthorized)?;
+
+        state.tokens
+            .verify_access_token(token.trim())
+            .map(|profile_id| AuthUser { profile_id })
+            .ok_or(AppErrors::Unauthorized)
+    }
+}
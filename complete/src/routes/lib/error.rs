@@ -1,13 +1,160 @@
-use axum::{http::StatusCode, response::IntoResponse};
+use axum::extract::multipart::{MultipartError, MultipartRejection};
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::http::{header::CONTENT_TYPE, StatusCode};
+use axum::response::IntoResponse;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use sqlx::error::ErrorKind;
+use crate::lib::media_store::MediaStoreError;
+
+/// Postgres `string_data_right_truncation`, raised when a value exceeds its `varchar` limit.
+const PG_STRING_DATA_RIGHT_TRUNCATION: &str = "22001";
 
 pub enum AppErrors {
+    BadRequest(String),
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict(String),
+    PayloadTooLarge(String),
+    UnsupportedMediaType(String),
+    InvalidReference(String),
+    ValidationFailed(String),
     InternalServerError
 }
 
+/// RFC 7807 problem details. `code` is stable and meant for clients to switch on.
+#[derive(Serialize, Deserialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub code: String
+}
+
+impl AppErrors {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppErrors::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppErrors::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppErrors::Forbidden => StatusCode::FORBIDDEN,
+            AppErrors::NotFound => StatusCode::NOT_FOUND,
+            AppErrors::Conflict(_) => StatusCode::CONFLICT,
+            AppErrors::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppErrors::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppErrors::InvalidReference(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppErrors::ValidationFailed(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppErrors::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppErrors::BadRequest(_) => "bad_request",
+            AppErrors::Unauthorized => "unauthorized",
+            AppErrors::Forbidden => "forbidden",
+            AppErrors::NotFound => "not_found",
+            AppErrors::Conflict(_) => "conflict",
+            AppErrors::PayloadTooLarge(_) => "payload_too_large",
+            AppErrors::UnsupportedMediaType(_) => "unsupported_media_type",
+            AppErrors::InvalidReference(_) => "invalid_reference",
+            AppErrors::ValidationFailed(_) => "validation_failed",
+            AppErrors::InternalServerError => "internal_error"
+        }
+    }
+
+    fn detail(self) -> Option<String> {
+        match self {
+            AppErrors::BadRequest(detail)
+            | AppErrors::Conflict(detail)
+            | AppErrors::PayloadTooLarge(detail)
+            | AppErrors::UnsupportedMediaType(detail)
+            | AppErrors::InvalidReference(detail)
+            | AppErrors::ValidationFailed(detail) => Some(detail),
+            _ => None
+        }
+    }
+}
+
 impl IntoResponse for AppErrors {
     fn into_response(self) -> axum::response::Response {
-        match self {
-            AppErrors::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        let status = self.status();
+        let code = self.code();
+        let problem = ProblemDetails {
+            problem_type: format!("/problems/{}", code),
+            title: status.canonical_reason().unwrap_or_default().to_string(),
+            status: status.as_u16(),
+            detail: self.detail(),
+            code: code.to_string()
+        };
+
+        (status, [(CONTENT_TYPE, "application/problem+json")], Json(problem)).into_response()
+    }
+}
+
+impl From<sqlx::Error> for AppErrors {
+    fn from(error: sqlx::Error) -> Self {
+        match error {
+            sqlx::Error::RowNotFound => AppErrors::NotFound,
+            sqlx::Error::Database(db_error) => {
+                let constraint = db_error.constraint().unwrap_or_default().to_string();
+                match db_error.kind() {
+                    ErrorKind::UniqueViolation => AppErrors::Conflict(format!("violates unique constraint {}", constraint)),
+                    ErrorKind::ForeignKeyViolation => AppErrors::InvalidReference(format!("references a missing row ({})", constraint)),
+                    ErrorKind::CheckViolation => AppErrors::ValidationFailed(format!("violates check constraint {}", constraint)),
+                    ErrorKind::NotNullViolation => AppErrors::ValidationFailed("a required value is missing".to_string()),
+                    _ if db_error.code().as_deref() == Some(PG_STRING_DATA_RIGHT_TRUNCATION) => {
+                        AppErrors::ValidationFailed("a value exceeds its maximum length".to_string())
+                    }
+                    _ => AppErrors::InternalServerError
+                }
+            }
+            _ => AppErrors::InternalServerError
         }
     }
-}
\ No newline at end of file
+}
+
+impl From<JsonRejection> for AppErrors {
+    fn from(rejection: JsonRejection) -> Self {
+        match rejection {
+            JsonRejection::JsonDataError(e) => AppErrors::ValidationFailed(e.body_text()),
+            e => AppErrors::BadRequest(e.body_text())
+        }
+    }
+}
+
+impl From<PathRejection> for AppErrors {
+    fn from(rejection: PathRejection) -> Self {
+        AppErrors::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for AppErrors {
+    fn from(rejection: QueryRejection) -> Self {
+        AppErrors::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<MultipartRejection> for AppErrors {
+    fn from(rejection: MultipartRejection) -> Self {
+        AppErrors::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<MultipartError> for AppErrors {
+    fn from(error: MultipartError) -> Self {
+        match error.status() {
+            StatusCode::PAYLOAD_TOO_LARGE => AppErrors::PayloadTooLarge(error.body_text()),
+            _ => AppErrors::BadRequest(error.body_text())
+        }
+    }
+}
+
+impl From<MediaStoreError> for AppErrors {
+    fn from(_: MediaStoreError) -> Self {
+        AppErrors::InternalServerError
+    }
+}
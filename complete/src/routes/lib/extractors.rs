@@ -0,0 +1,47 @@
+use async_trait::async_trait;
+use axum::extract::multipart::Field;
+use axum::extract::{FromRequest, FromRequestParts, Multipart, Request};
+use super::error::AppErrors;
+
+/// `axum::Json` whose rejections are reported as problem+json.
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(AppErrors))]
+pub struct AppJson<T>(pub T);
+
+/// `axum::extract::Path` whose rejections are reported as problem+json.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(AppErrors))]
+pub struct AppPath<T>(pub T);
+
+/// `axum::extract::Query` whose rejections are reported as problem+json.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(AppErrors))]
+pub struct AppQuery<T>(pub T);
+
+/// `axum::extract::Multipart` whose rejections are reported as problem+json.
+pub struct AppMultipart(pub Multipart);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequest<S> for AppMultipart {
+    type Rejection = AppErrors;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        Multipart::from_request(req, state)
+            .await
+            .map(AppMultipart)
+            .map_err(AppErrors::from)
+    }
+}
+
+/// Buffers a multipart field, failing as soon as it grows past `max_bytes` instead of
+/// after reading all of it. `label` names the field in the error.
+pub async fn read_field_limited(field: &mut Field<'_>, max_bytes: usize, label: &str) -> Result<Vec<u8>, AppErrors> {
+    let mut data = vec![];
+    while let Some(chunk) = field.chunk().await? {
+        if data.len() + chunk.len() > max_bytes {
+            return Err(AppErrors::PayloadTooLarge(format!("{} must be at most {} bytes", label, max_bytes)));
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
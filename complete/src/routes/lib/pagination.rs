@@ -0,0 +1,60 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use super::error::AppErrors;
+
+pub const DEFAULT_PAGE_SIZE: i16 = 20;
+pub const MAX_PAGE_SIZE: i16 = 100;
+
+#[derive(Deserialize)]
+pub struct PageQuery {
+    pub cursor: Option<String>,
+    pub page_size: Option<i16>
+}
+
+impl PageQuery {
+    /// Requested page size clamped to `1..=MAX_PAGE_SIZE`.
+    pub fn page_size(&self) -> i16 {
+        self.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+
+    pub fn decode_cursor<C: DeserializeOwned>(&self) -> Result<Option<C>, AppErrors> {
+        self.cursor.as_deref().map(decode_cursor).transpose()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>
+}
+
+impl<T> Page<T> {
+    /// Builds a page from `page_size + 1` fetched rows; the extra row only signals
+    /// that another page exists and the cursor points at the last returned item.
+    pub fn from_rows<C: Serialize>(mut rows: Vec<T>, page_size: i16, cursor_of: impl Fn(&T) -> C) -> Self {
+        let has_more = rows.len() > page_size as usize;
+        rows.truncate(page_size as usize);
+        let next_cursor = if has_more {
+            rows.last().map(|last| encode_cursor(&cursor_of(last)))
+        } else {
+            None
+        };
+
+        Page { items: rows, next_cursor }
+    }
+}
+
+/// Cursors are opaque to clients: url-safe base64 over the JSON of the keyset position.
+pub fn encode_cursor<C: Serialize>(cursor: &C) -> String {
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(cursor).unwrap_or_default())
+}
+
+pub fn decode_cursor<C: DeserializeOwned>(cursor: &str) -> Result<C, AppErrors> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<C>(&bytes).ok())
+        .ok_or_else(|| AppErrors::BadRequest("invalid cursor".to_string()))
+}
@@ -0,0 +1,10 @@
+use std::sync::Arc;
+use axum::{extract::State, routing::{get, put}, Router};
+use crate::{controllers::like::like_ctrl::{get_message_likes, like_message, unlike_message}, lib::app_state::AppState};
+
+pub fn get_like_routes(State(state): State<Arc<AppState>>) -> Router {
+    Router::new()
+        .route("/message/:id/like", put(like_message).delete(unlike_message))
+        .route("/message/:id/likes", get(get_message_likes))
+        .with_state(state)
+}
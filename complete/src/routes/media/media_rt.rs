@@ -0,0 +1,9 @@
+use std::sync::Arc;
+use axum::{extract::State, routing::get, Router};
+use crate::{controllers::media::media_ctrl::get_media, lib::app_state::AppState};
+
+pub fn get_media_routes(State(state): State<Arc<AppState>>) -> Router {
+    Router::new()
+        .route("/media/:id", get(get_media))
+        .with_state(state)
+}
@@ -1,10 +1,18 @@
 use std::sync::Arc;
-use axum::{extract::State, routing::{get, post}, Router};
-use crate::{controllers::message::message_ctrl::{create_message, get_message}, lib::app_state::AppState};
+use axum::{extract::{DefaultBodyLimit, State}, routing::{get, post}, Router};
+use crate::{controllers::message::message_ctrl::{create_message, delete_message, edit_message, get_message, get_message_history, get_message_thread, reply_to_message}, lib::{app_state::AppState, attachment::{ATTACHMENT_MAX_BYTES, MESSAGE_ATTACHMENTS_MAX}}};
 
 pub fn get_message_routes(State(state): State<Arc<AppState>>) -> Router {
     Router::new()
-        .route("/message", post(create_message))
-        .route("/message/:id", get(get_message))
+        .route(
+            "/message",
+            post(create_message)
+                // leave room for the multipart framing around the largest allowed attachments
+                .layer(DefaultBodyLimit::max(MESSAGE_ATTACHMENTS_MAX * ATTACHMENT_MAX_BYTES + 64 * 1024))
+        )
+        .route("/message/:id", get(get_message).patch(edit_message).delete(delete_message))
+        .route("/message/:id/history", get(get_message_history))
+        .route("/message/:id/reply", post(reply_to_message))
+        .route("/message/:id/thread", get(get_message_thread))
         .with_state(state)
 }
\ No newline at end of file
@@ -0,0 +1,10 @@
+use std::sync::Arc;
+use axum::{extract::State, routing::{get, post}, Router};
+use crate::{controllers::notification::notification_ctrl::{get_notifications, mark_notifications_read}, lib::app_state::AppState};
+
+pub fn get_notification_routes(State(state): State<Arc<AppState>>) -> Router {
+    Router::new()
+        .route("/notifications", get(get_notifications))
+        .route("/notifications/read", post(mark_notifications_read))
+        .with_state(state)
+}
@@ -1,10 +1,17 @@
 use std::sync::Arc;
-use axum::{extract::State, routing::{get, post}, Router};
-use crate::{controllers::profile::profile_ctrl::{create_profile, get_profile}, lib::app_state::AppState};
+use axum::{extract::{DefaultBodyLimit, State}, routing::get, Router};
+use crate::{controllers::profile::profile_ctrl::{get_avatar, get_profile, get_profile_by_user_name, update_profile, upload_avatar}, lib::{app_state::AppState, avatar::AVATAR_MAX_BYTES}};
 
 pub fn get_profile_router(State(state): State<Arc<AppState>>) -> Router {
     Router::new()
-        .route("/profile", post(create_profile))
-        .route("/profile/:id", get(get_profile))
+        .route("/profile/:id", get(get_profile).patch(update_profile))
+        .route("/profile/by-handle/:user_name", get(get_profile_by_user_name))
+        .route(
+            "/profile/:id/avatar",
+            get(get_avatar)
+                .put(upload_avatar)
+                // leave room for the multipart framing around a maximum size upload
+                .layer(DefaultBodyLimit::max(AVATAR_MAX_BYTES + 64 * 1024))
+        )
         .with_state(state)
 }
\ No newline at end of file
@@ -0,0 +1,11 @@
+use std::sync::Arc;
+use axum::{extract::State, routing::get, Router};
+use crate::{controllers::stream::stream_ctrl::{connect_websocket, stream_home, stream_notifications}, lib::app_state::AppState};
+
+pub fn get_stream_routes(State(state): State<Arc<AppState>>) -> Router {
+    Router::new()
+        .route("/ws", get(connect_websocket))
+        .route("/stream/home", get(stream_home))
+        .route("/stream/notifications", get(stream_notifications))
+        .with_state(state)
+}
@@ -0,0 +1,11 @@
+use std::sync::Arc;
+use axum::{extract::State, routing::get, Router};
+use crate::{controllers::timeline::timeline_ctrl::{get_author_timeline, get_home_timeline, get_mentions_timeline}, lib::app_state::AppState};
+
+pub fn get_timeline_routes(State(state): State<Arc<AppState>>) -> Router {
+    Router::new()
+        .route("/timeline", get(get_home_timeline))
+        .route("/profile/:id/messages", get(get_author_timeline))
+        .route("/mentions", get(get_mentions_timeline))
+        .with_state(state)
+}
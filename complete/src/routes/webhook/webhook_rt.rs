@@ -0,0 +1,12 @@
+use std::sync::Arc;
+use axum::{extract::State, routing::{delete, get, post}, Router};
+use crate::{controllers::webhook::webhook_ctrl::{create_webhook, delete_webhook, enable_webhook, get_webhook_deliveries, get_webhooks}, lib::app_state::AppState};
+
+pub fn get_webhook_routes(State(state): State<Arc<AppState>>) -> Router {
+    Router::new()
+        .route("/webhooks", post(create_webhook).get(get_webhooks))
+        .route("/webhooks/:id", delete(delete_webhook))
+        .route("/webhooks/:id/enable", post(enable_webhook))
+        .route("/webhooks/:id/deliveries", get(get_webhook_deliveries))
+        .with_state(state)
+}
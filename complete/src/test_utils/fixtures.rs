@@ -1,3 +1,28 @@
+use std::sync::Arc;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::Request;
+use fake::faker::internet::en::Username;
+use fake::faker::lorem::en::Sentence;
+use fake::faker::name::en::{FirstName, LastName};
+use fake::Fake;
+use serde_json::json;
+use tower::ServiceExt;
+use crate::controllers::auth::auth_models::AuthTokens;
+use crate::lib::app_state::AppState;
+use crate::repository::repo::EntityId;
+use crate::routes::auth::auth_rt::get_auth_routes;
+use crate::routes::follow::follow_rt::get_follow_routes;
+use crate::routes::message::message_rt::get_message_routes;
+
+pub struct TestAccount {
+    pub id: i64,
+    pub user_name: String,
+    pub password: String,
+    pub access_token: String,
+    pub refresh_token: String
+}
+
 pub fn init_test_logging() {
     _ = tracing_subscriber::FmtSubscriber::builder()
         .with_max_level(tracing::Level::INFO)
@@ -5,4 +30,131 @@ pub fn init_test_logging() {
         .with_line_number(true)
         .with_target(true)
         .try_init();
-}
\ No newline at end of file
+}
+
+/// A random handle restricted to the characters profiles accept.
+pub fn fake_user_name() -> String {
+    let user_name = Username().fake::<String>()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .take(40)
+        .collect::<String>();
+    format!("{}{}", user_name, (0..1_000_000).fake::<u32>())
+}
+
+pub async fn create_test_account(state: State<Arc<AppState>>) -> TestAccount {
+    let user_name = fake_user_name();
+    let password = "test-password".to_string();
+    let req_register = Request::builder()
+        .uri("/auth/register")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({
+            "user_name": user_name,
+            "full_name": format!("{} {}", FirstName().fake::<String>(), LastName().fake::<String>()),
+            "description": Sentence(1..2).fake::<String>(),
+            "password": password
+        }).to_string()))
+        .unwrap();
+    let auth_router = get_auth_routes(state);
+    let res_register = auth_router.clone().oneshot(req_register).await.unwrap();
+    let profile: EntityId = serde_json::from_slice(
+        &axum::body::to_bytes(res_register.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+
+    let req_login = Request::builder()
+        .uri("/auth/login")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({
+            "user_name": user_name,
+            "password": password
+        }).to_string()))
+        .unwrap();
+    let res_login = auth_router.oneshot(req_login).await.unwrap();
+    let tokens: AuthTokens = serde_json::from_slice(
+        &axum::body::to_bytes(res_login.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+
+    TestAccount {
+        id: profile.id,
+        user_name,
+        password,
+        access_token: tokens.access_token,
+        refresh_token: tokens.refresh_token
+    }
+}
+
+pub fn bearer(account: &TestAccount) -> String {
+    format!("Bearer {}", account.access_token)
+}
+
+pub async fn create_test_message(state: State<Arc<AppState>>, author: &TestAccount, body: &str, broadcasting_msg_id: Option<i64>) -> i64 {
+    let req_create_message = Request::builder()
+        .uri("/message")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .header("Authorization", bearer(author))
+        .body(Body::from(json!({
+            "body": body,
+            "broadcasting_msg_id": broadcasting_msg_id
+        }).to_string()))
+        .unwrap();
+    let res_create_message = get_message_routes(state).oneshot(req_create_message).await.unwrap();
+    let message: EntityId = serde_json::from_slice(
+        &axum::body::to_bytes(res_create_message.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+
+    message.id
+}
+
+pub async fn follow_test_account(state: State<Arc<AppState>>, follower: &TestAccount, following_id: i64) {
+    let req_follow = Request::builder()
+        .uri(format!("/profile/{}/follow", following_id))
+        .method("POST")
+        .header("Authorization", bearer(follower))
+        .body(Body::empty())
+        .unwrap();
+    get_follow_routes(state).oneshot(req_follow).await.unwrap();
+}
+
+/// A small gradient PNG for upload tests.
+pub fn test_png(width: u32, height: u32) -> Vec<u8> {
+    let image = image::RgbImage::from_fn(width, height, |x, y| image::Rgb([(x % 256) as u8, (y % 256) as u8, 128]));
+    let mut bytes = std::io::Cursor::new(vec![]);
+    image.write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+    bytes.into_inner()
+}
+
+/// Builds a `multipart/form-data` body from `(field name, content type, bytes)` parts,
+/// where parts with a content type are sent as files. Returns the content type header too.
+pub fn multipart_body(parts: &[(&str, Option<&str>, &[u8])]) -> (String, Vec<u8>) {
+    let boundary = "test-multipart-boundary";
+    let mut body = vec![];
+    for (name, content_type, data) in parts {
+        let headers = match content_type {
+            Some(content_type) => format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"; filename=\"{name}\"\r\nContent-Type: {content_type}\r\n\r\n"
+            ),
+            None => format!("--{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n")
+        };
+        body.extend_from_slice(headers.as_bytes());
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+
+    (format!("multipart/form-data; boundary={}", boundary), body)
+}
+
+pub fn upload_avatar_request(account: &TestAccount, id: i64, content_type: &str, data: Vec<u8>) -> Request<Body> {
+    let (multipart_content_type, body) = multipart_body(&[("avatar", Some(content_type), &data)]);
+
+    Request::builder()
+        .uri(format!("/profile/{}/avatar", id))
+        .method("PUT")
+        .header("Authorization", bearer(account))
+        .header("Content-Type", multipart_content_type)
+        .body(Body::from(body))
+        .unwrap()
+}
@@ -0,0 +1,32 @@
+use complete::lib::entities::{extract_hashtags, extract_mentions, normalize_hashtag};
+
+#[test]
+fn test_extract_hashtags() {
+    assert_eq!(
+        extract_hashtags("#Rust and #rust, #async_await! #2024 a#b #café"),
+        vec!["rust", "async_await", "café"]
+    );
+    assert!(extract_hashtags("no tags # here ##").is_empty());
+    assert_eq!(extract_hashtags("(#axum)"), vec!["axum"]);
+}
+
+#[test]
+fn test_normalize_hashtag() {
+    assert_eq!(normalize_hashtag("#Axum"), Some("axum".to_string()));
+    assert_eq!(normalize_hashtag("axum"), Some("axum".to_string()));
+    assert_eq!(normalize_hashtag("2024"), None);
+    assert_eq!(normalize_hashtag("two words"), None);
+    assert_eq!(normalize_hashtag(&"a".repeat(101)), None);
+    // `İ` lowercases to two chars
+    assert_eq!(normalize_hashtag(&"İ".repeat(50)), Some("i\u{307}".repeat(50)));
+    assert_eq!(normalize_hashtag(&"İ".repeat(51)), None);
+}
+
+#[test]
+fn test_extract_mentions() {
+    let mentions = extract_mentions("héllo @alice, @bob_2! me@example.com @");
+    assert_eq!(
+        mentions.iter().map(|m| (m.start, m.end, m.user_name.as_str())).collect::<Vec<_>>(),
+        vec![(6, 12, "alice"), (14, 20, "bob_2")]
+    );
+}
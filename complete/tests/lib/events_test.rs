@@ -0,0 +1,135 @@
+use std::sync::Arc;
+use std::time::Duration;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{Request, StatusCode};
+use complete::lib::app_state::AppState;
+use complete::lib::events::{spawn_event_pump, subscribe_events, DomainEvent};
+use complete::repository::event::event_models::EventKind;
+use complete::repository::event::event_repo::EventRepo;
+use complete::repository::repo::Repository;
+use complete::routes::like::like_rt::get_like_routes;
+use complete::test_utils::fixtures::{bearer, create_test_account, create_test_message, follow_test_account, init_test_logging};
+use tokio::sync::broadcast;
+use tower::ServiceExt;
+
+/// Well under the fallback poll interval, so events have to arrive by notification.
+const RELAY_TIMEOUT: Duration = Duration::from_secs(2);
+
+async fn next_domain_event(events: &mut broadcast::Receiver<DomainEvent>, matches: impl Fn(&DomainEvent) -> bool) -> DomainEvent {
+    tokio::time::timeout(RELAY_TIMEOUT, async {
+        loop {
+            let event = events.recv().await.unwrap();
+            if matches(&event) {
+                return event;
+            }
+        }
+    }).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_events_reach_other_instances() {
+    init_test_logging();
+    // two app states stand in for two server instances sharing a database
+    let writer = State(Arc::new(AppState::init().await));
+    let other_instance = Arc::new(AppState::init().await);
+    spawn_event_pump(Arc::clone(&other_instance));
+    let mut domain_events = other_instance.events.subscribe_domain();
+
+    let author = create_test_account(writer.clone()).await;
+    let reader = create_test_account(writer.clone()).await;
+    follow_test_account(writer.clone(), &reader, author.id).await;
+    assert_eq!(
+        next_domain_event(&mut domain_events, |e| matches!(e, DomainEvent::FollowCreated { follower_id, .. } if *follower_id == reader.id)).await,
+        DomainEvent::FollowCreated { follower_id: reader.id, following_id: author.id }
+    );
+
+    let mut stream_events = subscribe_events(Arc::clone(&other_instance), reader.id, &[EventKind::Message], None).await.unwrap();
+    let message_id = create_test_message(writer.clone(), &author, "from the other instance", None).await;
+    assert_eq!(
+        next_domain_event(&mut domain_events, |e| matches!(e, DomainEvent::MessageCreated { author_id, .. } if *author_id == author.id)).await,
+        DomainEvent::MessageCreated { message_id, author_id: author.id }
+    );
+    let stream_event = tokio::time::timeout(RELAY_TIMEOUT, stream_events.recv()).await.unwrap().unwrap();
+    assert_eq!(stream_event.data["id"], message_id);
+
+    let req_like = Request::builder()
+        .uri(format!("/message/{}/like", message_id))
+        .method("PUT")
+        .header("Authorization", bearer(&reader))
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(get_like_routes(writer).oneshot(req_like).await.unwrap().status(), StatusCode::OK);
+    assert_eq!(
+        next_domain_event(&mut domain_events, |e| matches!(e, DomainEvent::LikeAdded { profile_id, .. } if *profile_id == reader.id)).await,
+        DomainEvent::LikeAdded { message_id, profile_id: reader.id }
+    );
+}
+
+#[tokio::test]
+async fn test_events_are_sequenced_in_order_once_their_transactions_finish() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let pool = state.repo.get_pool();
+    // the audience keeps other tests' events out of the way
+    let audience = create_test_account(state.clone()).await.id;
+    let record = || sqlx::query("select record_event('notification', array[$1::bigint], '{}'::jsonb)").bind(audience);
+    let seqs = || sqlx::query_scalar::<_, Option<i64>>("select seq from event where audience = array[$1::bigint] order by id")
+        .bind(audience)
+        .fetch_all(pool);
+
+    let mut open = pool.begin().await.unwrap();
+    record().execute(&mut *open).await.unwrap();
+    // a second writer doesn't wait for the first to commit
+    tokio::time::timeout(RELAY_TIMEOUT, record().execute(pool)).await.unwrap().unwrap();
+    state.repo.sequence_events(pool).await.unwrap();
+    assert_eq!(seqs().await.unwrap(), vec![None], "an event was sequenced ahead of an open transaction's");
+
+    open.commit().await.unwrap();
+    let sequenced = tokio::time::timeout(RELAY_TIMEOUT, async {
+        loop {
+            state.repo.sequence_events(pool).await.unwrap();
+            let sequenced = seqs().await.unwrap();
+            if sequenced.iter().all(Option::is_some) {
+                return sequenced;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }).await.unwrap();
+    assert_eq!(sequenced.len(), 2);
+    assert!(sequenced[0] < sequenced[1]);
+}
+
+#[tokio::test]
+async fn test_events_are_delivered_after_every_event_expired() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let pool = state.repo.get_pool();
+    let audience = create_test_account(state.clone()).await.id;
+    sqlx::query("select record_event('notification', array[$1::bigint], '{}'::jsonb)").bind(audience).execute(pool).await.unwrap();
+    state.repo.sequence_events(pool).await.unwrap();
+    let last_seen = state.repo.select_latest_event_id(pool).await.unwrap();
+    assert!(last_seen > 0);
+
+    // as the pump's cleanup does once nothing happened for the retention period
+    sqlx::query("delete from event where seq <= $1").bind(last_seen).execute(pool).await.unwrap();
+    assert!(state.repo.select_latest_event_id(pool).await.unwrap() >= last_seen);
+
+    sqlx::query("select record_event('notification', array[$1::bigint], '{\"after\": \"expiry\"}'::jsonb)")
+        .bind(audience)
+        .execute(pool)
+        .await
+        .unwrap();
+    let delivered = tokio::time::timeout(RELAY_TIMEOUT, async {
+        loop {
+            state.repo.sequence_events(pool).await.unwrap();
+            let events = state.repo.select_profile_events_after(pool, audience, last_seen, 10).await.unwrap();
+            if !events.is_empty() {
+                return events;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }).await.unwrap();
+    assert_eq!(delivered.len(), 1);
+    assert_eq!(delivered[0].data.0["after"], "expiry");
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::Utc;
+use complete::lib::app_state::AppState;
+use complete::lib::events::spawn_event_pump;
+use complete::lib::jobs::{enqueue, enqueue_at, run_due_jobs, spawn_job_workers, Job, JobError, JobRegistry};
+use complete::repository::job::job_models::JobStatus;
+use complete::repository::job::job_repo::JobRepo;
+use complete::repository::repo::Repository;
+use complete::test_utils::fixtures::{fake_user_name, init_test_logging};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+const RETRY_BASE: Duration = Duration::from_millis(300);
+
+/// Runs per job key, across every job type below.
+fn runs() -> &'static Mutex<HashMap<String, u32>> {
+    static RUNS: OnceLock<Mutex<HashMap<String, u32>>> = OnceLock::new();
+    RUNS.get_or_init(Default::default)
+}
+
+fn record_run(key: &str) -> u32 {
+    let mut runs = runs().lock().unwrap();
+    let count = runs.entry(key.to_string()).or_default();
+    *count += 1;
+    *count
+}
+
+fn run_count(key: &str) -> u32 {
+    runs().lock().unwrap().get(key).copied().unwrap_or_default()
+}
+
+/// Fails its first `failures` runs.
+#[derive(Serialize, Deserialize)]
+struct FlakyJob {
+    key: String,
+    failures: u32
+}
+
+#[async_trait]
+impl Job for FlakyJob {
+    const KIND: &'static str = "test_flaky";
+    const MAX_ATTEMPTS: i32 = 3;
+
+    async fn run(&self, _app_state: &AppState) -> Result<(), JobError> {
+        if record_run(&self.key) <= self.failures {
+            return Err(JobError::Failed(format!("{} is not ready yet", self.key)));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PingJob {
+    key: String,
+    panics: bool
+}
+
+#[async_trait]
+impl Job for PingJob {
+    const KIND: &'static str = "test_ping";
+
+    async fn run(&self, _app_state: &AppState) -> Result<(), JobError> {
+        record_run(&self.key);
+        if self.panics {
+            panic!("{} panicked", self.key);
+        }
+        Ok(())
+    }
+}
+
+async fn test_state(poll_interval: Duration) -> Arc<AppState> {
+    let mut app_state = AppState::init().await;
+    app_state.jobs.config.retry_base = RETRY_BASE;
+    app_state.jobs.config.poll_interval = poll_interval;
+    // a single worker, so the one that ran a panicking job is the one running the next
+    app_state.jobs.config.workers = 1;
+    Arc::new(app_state)
+}
+
+#[tokio::test]
+async fn test_jobs_retry_dead_letter_and_wait_for_their_time() {
+    init_test_logging();
+    let app_state = test_state(Duration::from_secs(60)).await;
+    let pool = app_state.repo.get_pool();
+    let registry = JobRegistry::new().register::<FlakyJob>();
+
+    // a failure is retried after the backoff
+    let key = fake_user_name();
+    let job_id = enqueue(&app_state, &FlakyJob { key: key.clone(), failures: 1 }).await.unwrap();
+    run_due_jobs(&app_state, &registry).await.unwrap();
+    assert_eq!(run_count(&key), 1);
+    let job = app_state.repo.select_job(pool, job_id).await.unwrap().unwrap();
+    assert_eq!(job.status, JobStatus::Pending);
+    assert_eq!(job.attempts, 1);
+    assert!(job.run_at > Utc::now());
+    assert_eq!(job.last_error.unwrap(), format!("{} is not ready yet", key));
+
+    run_due_jobs(&app_state, &registry).await.unwrap();
+    assert_eq!(run_count(&key), 1);
+    tokio::time::sleep(RETRY_BASE * 2).await;
+    run_due_jobs(&app_state, &registry).await.unwrap();
+    assert_eq!(run_count(&key), 2);
+    assert!(app_state.repo.select_job(pool, job_id).await.unwrap().is_none());
+
+    // out of attempts it goes to the dead letters, from where it can be requeued
+    let key = fake_user_name();
+    let job_id = enqueue(&app_state, &FlakyJob { key: key.clone(), failures: 10 }).await.unwrap();
+    for attempt in 1..=3 {
+        if attempt > 1 {
+            tokio::time::sleep(RETRY_BASE * 2u32.pow(attempt - 1)).await;
+        }
+        run_due_jobs(&app_state, &registry).await.unwrap();
+        assert_eq!(run_count(&key), attempt);
+    }
+    tokio::time::sleep(RETRY_BASE * 8).await;
+    run_due_jobs(&app_state, &registry).await.unwrap();
+    assert_eq!(run_count(&key), 3);
+    let dead = app_state.repo.select_dead_jobs(pool, Some(FlakyJob::KIND), 1000).await.unwrap();
+    let job = dead.iter().find(|job| job.id == job_id).unwrap();
+    assert_eq!(job.attempts, 3);
+    assert_eq!(job.payload.0, json!({ "key": key, "failures": 10 }));
+
+    assert!(app_state.repo.requeue_dead_job(pool, job_id).await.unwrap());
+    run_due_jobs(&app_state, &registry).await.unwrap();
+    assert_eq!(run_count(&key), 4);
+    let job = app_state.repo.select_job(pool, job_id).await.unwrap().unwrap();
+    assert_eq!((job.status, job.attempts), (JobStatus::Pending, 1));
+
+    // a payload that doesn't deserialize is never going to work
+    let job_id = app_state.repo.insert_job(pool, FlakyJob::KIND, &json!({ "key": 1 }), 3, Utc::now()).await.unwrap();
+    run_due_jobs(&app_state, &registry).await.unwrap();
+    let job = app_state.repo.select_job(pool, job_id).await.unwrap().unwrap();
+    assert_eq!((job.status, job.attempts), (JobStatus::Dead, 1));
+    assert!(job.last_error.unwrap().starts_with("invalid"));
+
+    // scheduled jobs wait for their time
+    let key = fake_user_name();
+    enqueue_at(&app_state, &FlakyJob { key: key.clone(), failures: 0 }, Utc::now() + chrono::Duration::milliseconds(800)).await.unwrap();
+    run_due_jobs(&app_state, &registry).await.unwrap();
+    assert_eq!(run_count(&key), 0);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    run_due_jobs(&app_state, &registry).await.unwrap();
+    assert_eq!(run_count(&key), 1);
+}
+
+async fn wait_for_runs(key: &str, count: u32) {
+    tokio::time::timeout(Duration::from_secs(3), async {
+        while run_count(key) < count {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_job_workers_are_woken_by_enqueued_jobs() {
+    init_test_logging();
+    // far longer than the test, so jobs have to be picked up by notification
+    let app_state = test_state(Duration::from_secs(60)).await;
+    spawn_event_pump(Arc::clone(&app_state));
+    spawn_job_workers(Arc::clone(&app_state), JobRegistry::new().register::<PingJob>());
+    // let the pump start listening
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let panicking = fake_user_name();
+    let job_id = enqueue(&app_state, &PingJob { key: panicking.clone(), panics: true }).await.unwrap();
+    wait_for_runs(&panicking, 1).await;
+
+    // the worker survived the panic and the job is up for a retry
+    let key = fake_user_name();
+    enqueue(&app_state, &PingJob { key: key.clone(), panics: false }).await.unwrap();
+    wait_for_runs(&key, 1).await;
+    let job = tokio::time::timeout(Duration::from_secs(3), async {
+        loop {
+            let job = app_state.repo.select_job(app_state.repo.get_pool(), job_id).await.unwrap().unwrap();
+            if job.status == JobStatus::Pending {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }).await.unwrap();
+    assert_eq!(job.attempts, 1);
+    assert!(job.last_error.unwrap().contains("panic"));
+}
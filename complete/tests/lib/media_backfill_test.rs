@@ -0,0 +1,89 @@
+use std::sync::Arc;
+use axum::extract::State;
+use complete::lib::app_state::AppState;
+use complete::lib::jobs::Job;
+use complete::lib::media_backfill::BackfillLegacyMedia;
+use complete::repository::repo::Repository;
+use complete::test_utils::fixtures::{create_test_account, create_test_message, init_test_logging, test_png};
+use sqlx::{query, query_as, query_scalar};
+
+#[tokio::test]
+async fn test_backfill_moves_legacy_bytes_into_the_media_store() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let pool = state.repo.get_pool();
+    let author = create_test_account(state.clone()).await;
+    let message_id = create_test_message(state.clone(), &author, "from before the media store", None).await;
+    let deleted_id = create_test_message(state.clone(), &author, "deleted since", None).await;
+    let unreadable_id = create_test_message(state.clone(), &author, "never was an image", None).await;
+
+    query("insert into legacy_avatar (profile_id, data) values ($1, $2)")
+        .bind(author.id)
+        .bind(test_png(64, 32))
+        .execute(pool)
+        .await
+        .unwrap();
+    for (id, data) in [(message_id, test_png(30, 20)), (deleted_id, test_png(10, 10)), (unreadable_id, b"not an image".to_vec())] {
+        query("insert into legacy_message_image (message_id, data) values ($1, $2)")
+            .bind(id)
+            .bind(data)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+    query("update message set deleted_at = now() where id = $1").bind(deleted_id).execute(pool).await.unwrap();
+
+    BackfillLegacyMedia.run(&state).await.unwrap();
+
+    let avatar_id = query_scalar::<_, Option<i64>>("select avatar_id from profile where id = $1")
+        .bind(author.id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    assert!(avatar_id.is_some());
+    let avatar_sizes = query_scalar::<_, i64>("select count(*) from profile_avatar where profile_id = $1")
+        .bind(author.id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    assert_eq!(avatar_sizes, 3);
+
+    let (media_id, width, height) = query_as::<_, (i64, i32, i32)>(
+        "select media_id, width, height from message_attachment where message_id = $1 and position = 0"
+    )
+    .bind(message_id)
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    assert_eq!((width, height), (30, 20));
+    let storage_key = query_scalar::<_, String>("select storage_key from media where id = $1")
+        .bind(media_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    assert!(state.media.get(&storage_key).await.unwrap().is_some());
+
+    let attached_to_deleted = query_scalar::<_, i64>("select count(*) from message_attachment where message_id = $1")
+        .bind(deleted_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    assert_eq!(attached_to_deleted, 0);
+    // only what couldn't be decoded is left behind
+    let legacy_left = query_scalar::<_, i64>(
+        "select message_id from legacy_message_image where message_id = any($1) order by message_id"
+    )
+    .bind(vec![message_id, deleted_id, unreadable_id])
+    .fetch_all(pool)
+    .await
+    .unwrap();
+    assert_eq!(legacy_left, vec![unreadable_id]);
+    let legacy_avatar_left = query_scalar::<_, i64>("select count(*) from legacy_avatar where profile_id = $1")
+        .bind(author.id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    assert_eq!(legacy_avatar_left, 0);
+
+    query("delete from legacy_message_image where message_id = $1").bind(unreadable_id).execute(pool).await.unwrap();
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::future::IntoFuture;
+use std::sync::{Arc, Mutex};
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::put;
+use axum::Router;
+use complete::lib::media_store::{generate_media_key, LocalMediaStore, MediaStore, S3MediaStore};
+use futures_util::TryStreamExt;
+use sha2::{Digest, Sha256};
+
+type Objects = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+async fn read_all(store: &dyn MediaStore, key: &str) -> Option<Vec<u8>> {
+    let stream = store.get(key).await.unwrap()?;
+    let chunks = stream.try_collect::<Vec<Bytes>>().await.unwrap();
+    Some(chunks.concat())
+}
+
+async fn assert_round_trip(store: &dyn MediaStore) {
+    let key = generate_media_key();
+    let data = b"not really an image, but bytes all the same".to_vec();
+
+    assert!(read_all(store, &key).await.is_none());
+    store.put(&key, "image/png", data.clone()).await.unwrap();
+    assert_eq!(read_all(store, &key).await, Some(data));
+    store.delete(&key).await.unwrap();
+    assert!(read_all(store, &key).await.is_none());
+    // deleting twice is fine
+    store.delete(&key).await.unwrap();
+    assert!(store.put("../escape", "image/png", vec![]).await.is_err());
+}
+
+#[tokio::test]
+async fn test_local_media_store_round_trip() {
+    let root = std::env::temp_dir().join(format!("media-store-test-{}", generate_media_key()));
+    assert_round_trip(&LocalMediaStore::new(&root)).await;
+    _ = tokio::fs::remove_dir_all(root).await;
+}
+
+/// A minimal S3 stand-in: path-style object PUT/GET/DELETE that rejects unsigned requests.
+fn s3_stand_in(objects: Objects) -> Router {
+    async fn authorized(headers: &HeaderMap, body: &[u8]) -> bool {
+        let signed = headers.get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("AWS4-HMAC-SHA256 Credential=test-key/"));
+        let payload_hash = headers.get("x-amz-content-sha256").and_then(|value| value.to_str().ok());
+        signed && payload_hash == Some(format!("{:x}", Sha256::digest(body)).as_str())
+    }
+
+    async fn put_object(State(objects): State<Objects>, Path((_, key)): Path<(String, String)>, headers: HeaderMap, body: Bytes) -> Response {
+        if !authorized(&headers, &body).await {
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        objects.lock().unwrap().insert(key, body.to_vec());
+        StatusCode::OK.into_response()
+    }
+
+    async fn get_object(State(objects): State<Objects>, Path((_, key)): Path<(String, String)>, headers: HeaderMap) -> Response {
+        if !authorized(&headers, &[]).await {
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        match objects.lock().unwrap().get(&key) {
+            Some(data) => data.clone().into_response(),
+            None => StatusCode::NOT_FOUND.into_response()
+        }
+    }
+
+    async fn delete_object(State(objects): State<Objects>, Path((_, key)): Path<(String, String)>, headers: HeaderMap) -> Response {
+        if !authorized(&headers, &[]).await {
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        objects.lock().unwrap().remove(&key);
+        StatusCode::NO_CONTENT.into_response()
+    }
+
+    Router::new()
+        .route("/:bucket/*key", put(put_object).get(get_object).delete(delete_object))
+        .with_state(objects)
+}
+
+#[tokio::test]
+async fn test_s3_media_store_round_trip() {
+    let objects = Objects::default();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(axum::serve(listener, s3_stand_in(objects.clone())).into_future());
+
+    let store = S3MediaStore::new(&endpoint, "media", "us-east-1", "test-key", "test-secret");
+    assert_round_trip(&store).await;
+    assert!(objects.lock().unwrap().is_empty());
+}
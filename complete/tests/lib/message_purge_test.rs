@@ -0,0 +1,43 @@
+use std::sync::Arc;
+use axum::extract::State;
+use chrono::Utc;
+use complete::lib::app_state::AppState;
+use complete::lib::jobs::{enqueue_unless_pending, Job};
+use complete::lib::message_purge::PurgeDeletedMessages;
+use complete::repository::repo::Repository;
+use complete::test_utils::fixtures::init_test_logging;
+use sqlx::{query, query_scalar};
+
+#[tokio::test]
+async fn test_message_purge_keeps_a_single_run_scheduled() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let pool = state.repo.get_pool();
+    let pending_runs = || query_scalar::<_, i64>(
+        "select count(*) from job where kind = 'purge_deleted_messages' and status = 'pending'"
+    )
+    .fetch_one(pool);
+    query("delete from job where kind = 'purge_deleted_messages'").execute(pool).await.unwrap();
+
+    // as when several instances start at once
+    assert!(enqueue_unless_pending(&state, &PurgeDeletedMessages, Utc::now()).await.unwrap().is_some());
+    assert!(enqueue_unless_pending(&state, &PurgeDeletedMessages, Utc::now()).await.unwrap().is_none());
+    assert_eq!(pending_runs().await.unwrap(), 1);
+
+    query("delete from job where kind = 'purge_deleted_messages'").execute(pool).await.unwrap();
+    let started = Utc::now();
+    PurgeDeletedMessages.run(&state).await.unwrap();
+    PurgeDeletedMessages.run(&state).await.unwrap();
+    assert_eq!(pending_runs().await.unwrap(), 1);
+    let next_run_at = query_scalar::<_, chrono::DateTime<Utc>>(
+        "select run_at from job where kind = 'purge_deleted_messages' and status = 'pending'"
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    // run_at is stored to the millisecond
+    let earliest = started + chrono::Duration::from_std(state.messages.purge_interval).unwrap() - chrono::Duration::milliseconds(1);
+    assert!(next_run_at >= earliest);
+
+    query("delete from job where kind = 'purge_deleted_messages'").execute(pool).await.unwrap();
+}
@@ -1,8 +1,46 @@
+pub mod lib {
+    pub mod entities_test;
+    pub mod events_test;
+    pub mod jobs_test;
+    pub mod media_backfill_test;
+    pub mod media_store_test;
+    pub mod message_purge_test;
+}
 pub mod routes {
+    pub mod auth {
+        pub mod auth_rt_test;
+    }
+    pub mod direct_message {
+        pub mod direct_message_rt_test;
+    }
+    pub mod follow {
+        pub mod follow_rt_test;
+    }
+    pub mod hashtag {
+        pub mod hashtag_rt_test;
+    }
+    pub mod like {
+        pub mod like_rt_test;
+    }
+    pub mod media {
+        pub mod media_rt_test;
+    }
     pub mod message {
         pub mod message_rt_test;
     }
+    pub mod notification {
+        pub mod notification_rt_test;
+    }
     pub mod profile {
         pub mod profile_rt_test;
     }
+    pub mod stream {
+        pub mod stream_rt_test;
+    }
+    pub mod timeline {
+        pub mod timeline_rt_test;
+    }
+    pub mod webhook {
+        pub mod webhook_rt_test;
+    }
 }
\ No newline at end of file
@@ -0,0 +1,163 @@
+use std::sync::Arc;
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use complete::controllers::auth::auth_models::AuthTokens;
+use complete::lib::app_state::AppState;
+use complete::repository::repo::EntityId;
+use complete::routes::auth::auth_rt::get_auth_routes;
+use complete::test_utils::fixtures::{create_test_account, fake_user_name, init_test_logging};
+use serde_json::json;
+use fake::faker::name::en::{FirstName, LastName};
+use fake::faker::lorem::en::Sentence;
+use fake::Fake;
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn test_register_and_login() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+
+    let user_name = fake_user_name();
+    let auth_router = get_auth_routes(state.clone());
+    let req_register = Request::builder()
+        .uri("/auth/register")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!({
+                "user_name": user_name,
+                "full_name": format!("{} {}", FirstName().fake::<String>(), LastName().fake::<String>()),
+                "description": Sentence(1..2).fake::<String>(),
+                "password": "correct horse battery"
+            }).to_string()
+        ))
+        .unwrap();
+    let res_register = auth_router.clone().oneshot(req_register).await.unwrap();
+    assert_eq!(res_register.status(), StatusCode::CREATED);
+    let profile_entity: EntityId = serde_json::from_slice(
+        &axum::body::to_bytes(res_register.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert!(profile_entity.id > 0);
+
+    let req_login = Request::builder()
+        .uri("/auth/login")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            json!({
+                "user_name": user_name,
+                "password": "correct horse battery"
+            }).to_string()
+        ))
+        .unwrap();
+    let res_login = auth_router.oneshot(req_login).await.unwrap();
+    assert_eq!(res_login.status(), StatusCode::OK);
+    let tokens: AuthTokens = serde_json::from_slice(
+        &axum::body::to_bytes(res_login.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert_eq!(state.tokens.verify_access_token(&tokens.access_token), Some(profile_entity.id));
+}
+
+#[tokio::test]
+async fn test_register_rejects_taken_user_name() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let account = create_test_account(state.clone()).await;
+    let auth_router = get_auth_routes(state);
+
+    for user_name in [account.user_name.clone(), account.user_name.to_uppercase()] {
+        let req_register = Request::builder()
+            .uri("/auth/register")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                json!({
+                    "user_name": user_name,
+                    "full_name": "Someone Else",
+                    "description": "",
+                    "password": "another password"
+                }).to_string()
+            ))
+            .unwrap();
+        let res_register = auth_router.clone().oneshot(req_register).await.unwrap();
+        assert_eq!(res_register.status(), StatusCode::CONFLICT);
+    }
+}
+
+#[tokio::test]
+async fn test_register_rejects_invalid_user_name() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let auth_router = get_auth_routes(state);
+
+    for user_name in ["", "has space", "semi;colon", "ümlaut", &"x".repeat(51)] {
+        let req_register = Request::builder()
+            .uri("/auth/register")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                json!({
+                    "user_name": user_name,
+                    "full_name": "Someone",
+                    "description": "",
+                    "password": "a good password"
+                }).to_string()
+            ))
+            .unwrap();
+        let res_register = auth_router.clone().oneshot(req_register).await.unwrap();
+        assert_eq!(res_register.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
+
+#[tokio::test]
+async fn test_login_failures_are_indistinguishable() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let account = create_test_account(state.clone()).await;
+    let auth_router = get_auth_routes(state);
+
+    let mut bodies = vec![];
+    for (user_name, password) in [(account.user_name.as_str(), "wrong password"), ("no_such_user_name", "wrong password")] {
+        let req_login = Request::builder()
+            .uri("/auth/login")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                json!({ "user_name": user_name, "password": password }).to_string()
+            ))
+            .unwrap();
+        let res_login = auth_router.clone().oneshot(req_login).await.unwrap();
+        assert_eq!(res_login.status(), StatusCode::UNAUTHORIZED);
+        bodies.push(axum::body::to_bytes(res_login.into_body(), usize::MAX).await.unwrap());
+    }
+    assert_eq!(bodies[0], bodies[1]);
+}
+
+#[tokio::test]
+async fn test_refresh_token_rotation_and_replay() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let account = create_test_account(state.clone()).await;
+    let auth_router = get_auth_routes(state);
+
+    let refresh_request = |refresh_token: &str| Request::builder()
+        .uri("/auth/refresh")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({ "refresh_token": refresh_token }).to_string()))
+        .unwrap();
+
+    let res_refresh = auth_router.clone().oneshot(refresh_request(&account.refresh_token)).await.unwrap();
+    assert_eq!(res_refresh.status(), StatusCode::OK);
+    let rotated: AuthTokens = serde_json::from_slice(
+        &axum::body::to_bytes(res_refresh.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert_ne!(rotated.refresh_token, account.refresh_token);
+
+    // replaying the rotated token revokes the family, including its successor
+    let res_replay = auth_router.clone().oneshot(refresh_request(&account.refresh_token)).await.unwrap();
+    assert_eq!(res_replay.status(), StatusCode::UNAUTHORIZED);
+    let res_successor = auth_router.oneshot(refresh_request(&rotated.refresh_token)).await.unwrap();
+    assert_eq!(res_successor.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,196 @@
+use std::sync::Arc;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{Request, Response, StatusCode};
+use axum::Router;
+use complete::controllers::direct_message::direct_message_models::ConversationPage;
+use chrono::{DateTime, Utc};
+use complete::lib::app_state::AppState;
+use complete::repository::direct_message::direct_message_models::{ConversationQueryResult, DirectMessageQueryResult};
+use complete::routes::direct_message::direct_message_rt::get_direct_message_routes;
+use complete::routes::lib::pagination::Page;
+use complete::repository::repo::Repository;
+use complete::routes::profile::profile_rt::get_profile_router;
+use complete::test_utils::fixtures::{bearer, create_test_account, follow_test_account, init_test_logging, TestAccount};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+async fn send(router: &Router, account: &TestAccount, method: &str, uri: &str, body: Option<Value>) -> Response<Body> {
+    let req = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("Content-Type", "application/json")
+        .header("Authorization", bearer(account))
+        .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+        .unwrap();
+    router.clone().oneshot(req).await.unwrap()
+}
+
+async fn read_json<T: DeserializeOwned>(res: Response<Body>) -> T {
+    serde_json::from_slice(&axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap()).unwrap()
+}
+
+async fn start_conversation(router: &Router, account: &TestAccount, body: Value, status: StatusCode) -> ConversationQueryResult {
+    let res = send(router, account, "POST", "/conversations", Some(body)).await;
+    assert_eq!(res.status(), status);
+    read_json(res).await
+}
+
+async fn send_direct_message(router: &Router, account: &TestAccount, conversation_id: i64, body: &str) -> Response<Body> {
+    send(router, account, "POST", &format!("/conversations/{}/messages", conversation_id), Some(json!({ "body": body }))).await
+}
+
+async fn get_conversations(router: &Router, account: &TestAccount, query: &str) -> ConversationPage {
+    let res = send(router, account, "GET", &format!("/conversations{}", query), None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    read_json(res).await
+}
+
+#[tokio::test]
+async fn test_direct_messages_between_two_profiles() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let alice = create_test_account(state.clone()).await;
+    let bob = create_test_account(state.clone()).await;
+    let outsider = create_test_account(state.clone()).await;
+    let router = get_direct_message_routes(state.clone());
+
+    let conversation = start_conversation(&router, &alice, json!({ "member_ids": [bob.id, alice.id, bob.id] }), StatusCode::CREATED).await;
+    assert!(!conversation.is_group);
+    assert_eq!(conversation.members.iter().map(|m| m.profile_id).collect::<Vec<i64>>(), vec![alice.id, bob.id]);
+    // either side starting it again finds the same conversation
+    let again = start_conversation(&router, &bob, json!({ "member_ids": [alice.id] }), StatusCode::OK).await;
+    assert_eq!(again.id, conversation.id);
+
+    let mut sent = vec![];
+    for (sender, body) in [(&alice, "hi"), (&bob, "hello"), (&alice, "how are you"), (&alice, "still there?")] {
+        let res = send_direct_message(&router, sender, conversation.id, body).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        sent.push(read_json::<DirectMessageQueryResult>(res).await);
+    }
+    assert_eq!(send_direct_message(&router, &alice, conversation.id, " ").await.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let bobs = get_conversations(&router, &bob, "").await;
+    assert_eq!(bobs.unread_count, 2);
+    assert_eq!(bobs.page.items[0].unread_count, 2);
+    assert_eq!(bobs.page.items[0].last_message.as_ref().unwrap().body, "still there?");
+    // the sender has read up to their own message
+    assert_eq!(get_conversations(&router, &alice, "").await.unread_count, 0);
+
+    let uri = format!("/conversations/{}/messages?page_size=3", conversation.id);
+    let first_page: Page<DirectMessageQueryResult> = read_json(send(&router, &bob, "GET", &uri, None).await).await;
+    assert_eq!(first_page.items.iter().map(|m| m.body.as_str()).collect::<Vec<&str>>(), vec!["still there?", "how are you", "hello"]);
+    let uri = format!("/conversations/{}/messages?page_size=3&cursor={}", conversation.id, first_page.next_cursor.unwrap());
+    let second_page: Page<DirectMessageQueryResult> = read_json(send(&router, &bob, "GET", &uri, None).await).await;
+    assert_eq!(second_page.items.iter().map(|m| m.id).collect::<Vec<i64>>(), vec![sent[0].id]);
+    assert!(second_page.next_cursor.is_none());
+
+    let read_uri = format!("/conversations/{}/read", conversation.id);
+    let res_read = send(&router, &bob, "POST", &read_uri, Some(json!({ "message_id": sent[2].id }))).await;
+    assert_eq!(res_read.status(), StatusCode::OK);
+    assert_eq!(read_json::<ConversationQueryResult>(res_read).await.unread_count, 1);
+    // read markers never move back
+    let res_read = send(&router, &bob, "POST", &read_uri, Some(json!({ "message_id": sent[0].id }))).await;
+    assert_eq!(read_json::<ConversationQueryResult>(res_read).await.unread_count, 1);
+    let res_read = send(&router, &bob, "POST", &read_uri, Some(json!({}))).await;
+    assert_eq!(read_json::<ConversationQueryResult>(res_read).await.unread_count, 0);
+
+    let conversation_uri = format!("/conversations/{}", conversation.id);
+    assert_eq!(send(&router, &outsider, "GET", &conversation_uri, None).await.status(), StatusCode::NOT_FOUND);
+    assert_eq!(send_direct_message(&router, &outsider, conversation.id, "let me in").await.status(), StatusCode::NOT_FOUND);
+    let history_uri = format!("/conversations/{}/messages", conversation.id);
+    assert_eq!(send(&router, &outsider, "GET", &history_uri, None).await.status(), StatusCode::NOT_FOUND);
+    assert_eq!(send(&router, &outsider, "POST", &read_uri, Some(json!({}))).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_group_conversations_list_by_last_activity() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let alice = create_test_account(state.clone()).await;
+    let bob = create_test_account(state.clone()).await;
+    let carol = create_test_account(state.clone()).await;
+    let router = get_direct_message_routes(state.clone());
+
+    let group = start_conversation(
+        &router,
+        &alice,
+        json!({ "member_ids": [bob.id, carol.id], "title": "weekend plans" }),
+        StatusCode::CREATED
+    ).await;
+    assert!(group.is_group);
+    assert_eq!(group.title.as_deref(), Some("weekend plans"));
+    assert_eq!(group.members.len(), 3);
+    let pair = start_conversation(&router, &carol, json!({ "member_ids": [alice.id] }), StatusCode::CREATED).await;
+    // a second group with the same members is a separate conversation
+    let other_group = start_conversation(&router, &bob, json!({ "member_ids": [alice.id, carol.id] }), StatusCode::CREATED).await;
+    assert_ne!(other_group.id, group.id);
+
+    assert_eq!(send_direct_message(&router, &bob, group.id, "saturday?").await.status(), StatusCode::CREATED);
+    assert_eq!(send_direct_message(&router, &carol, group.id, "works for me").await.status(), StatusCode::CREATED);
+
+    let alices = get_conversations(&router, &alice, "").await;
+    assert_eq!(alices.page.items.iter().map(|c| c.id).collect::<Vec<i64>>(), vec![group.id, other_group.id, pair.id]);
+    assert_eq!(alices.unread_count, 2);
+    let carols = get_conversations(&router, &carol, "?page_size=2").await;
+    assert_eq!(carols.page.items.iter().map(|c| c.id).collect::<Vec<i64>>(), vec![group.id, other_group.id]);
+    // replying marks what came before as read
+    assert_eq!(carols.unread_count, 0);
+    let carols = get_conversations(&router, &carol, &format!("?page_size=2&cursor={}", carols.page.next_cursor.unwrap())).await;
+    assert_eq!(carols.page.items.iter().map(|c| c.id).collect::<Vec<i64>>(), vec![pair.id]);
+
+    // with nothing said yet the last activity is the start, so the pair rises above
+    // the older of the two groups once someone speaks in it
+    assert_eq!(send_direct_message(&router, &alice, pair.id, "psst").await.status(), StatusCode::CREATED);
+    let alices = get_conversations(&router, &alice, "").await;
+    assert_eq!(alices.page.items.iter().map(|c| c.id).collect::<Vec<i64>>(), vec![pair.id, group.id, other_group.id]);
+
+    // a message committing after a later one doesn't move the last activity back
+    let later = sqlx::query_scalar::<_, DateTime<Utc>>(
+        "update conversation set last_message_at = now() + interval '1 minute' where id = $1 returning last_message_at"
+    )
+    .bind(pair.id)
+    .fetch_one(state.repo.get_pool())
+    .await
+    .unwrap();
+    assert_eq!(send_direct_message(&router, &carol, pair.id, "late").await.status(), StatusCode::CREATED);
+    let pair_now = read_json::<ConversationQueryResult>(send(&router, &alice, "GET", &format!("/conversations/{}", pair.id), None).await).await;
+    assert_eq!(pair_now.last_message_at, later);
+
+    let res = send(&router, &alice, "POST", "/conversations", Some(json!({ "member_ids": [bob.id], "title": "just us" }))).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let res = send(&router, &alice, "POST", "/conversations", Some(json!({ "member_ids": [alice.id] }))).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let res = send(&router, &alice, "POST", "/conversations", Some(json!({ "member_ids": [bob.id, i64::MAX] }))).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_dm_policy_limits_who_may_message() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let private = create_test_account(state.clone()).await;
+    let friend = create_test_account(state.clone()).await;
+    let stranger = create_test_account(state.clone()).await;
+    let router = get_direct_message_routes(state.clone());
+    let profile_router = get_profile_router(state.clone());
+
+    let conversation = start_conversation(&router, &stranger, json!({ "member_ids": [private.id] }), StatusCode::CREATED).await;
+    let res_policy = send(&profile_router, &private, "PATCH", &format!("/profile/{}", private.id), Some(json!({ "dm_policy": "following" }))).await;
+    assert_eq!(res_policy.status(), StatusCode::OK);
+    assert_eq!(read_json::<Value>(res_policy).await["dm_policy"], "following");
+
+    // the policy also applies to conversations started before it was set
+    assert_eq!(send_direct_message(&router, &stranger, conversation.id, "hello?").await.status(), StatusCode::FORBIDDEN);
+    let res = send(&router, &friend, "POST", "/conversations", Some(json!({ "member_ids": [private.id] }))).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = send(&router, &friend, "POST", "/conversations", Some(json!({ "member_ids": [stranger.id, private.id] }))).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    follow_test_account(state.clone(), &private, friend.id).await;
+    let allowed = start_conversation(&router, &friend, json!({ "member_ids": [private.id] }), StatusCode::CREATED).await;
+    assert_eq!(send_direct_message(&router, &friend, allowed.id, "hey").await.status(), StatusCode::CREATED);
+    // the policy is only about who may write to the profile, not who it may write to
+    assert_eq!(send_direct_message(&router, &private, conversation.id, "who is this?").await.status(), StatusCode::CREATED);
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use complete::lib::app_state::AppState;
+use complete::repository::follow::follow_models::FollowProfileQueryResult;
+use complete::routes::follow::follow_rt::get_follow_routes;
+use complete::routes::lib::pagination::Page;
+use complete::test_utils::fixtures::{bearer, create_test_account, init_test_logging, TestAccount};
+use tower::ServiceExt;
+
+async fn send_follow(router: &Router, method: &str, follower: &TestAccount, following_id: i64) -> StatusCode {
+    let req_follow = Request::builder()
+        .uri(format!("/profile/{}/follow", following_id))
+        .method(method)
+        .header("Authorization", bearer(follower))
+        .body(Body::empty())
+        .unwrap();
+    router.clone().oneshot(req_follow).await.unwrap().status()
+}
+
+async fn get_follow_page(router: &Router, uri: String) -> Page<FollowProfileQueryResult> {
+    let req_page = Request::builder()
+        .uri(uri)
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_page = router.clone().oneshot(req_page).await.unwrap();
+    assert_eq!(res_page.status(), StatusCode::OK);
+    serde_json::from_slice(
+        &axum::body::to_bytes(res_page.into_body(), usize::MAX).await.unwrap()
+    ).unwrap()
+}
+
+#[tokio::test]
+async fn test_follow_and_unfollow() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let follower = create_test_account(state.clone()).await;
+    let following = create_test_account(state.clone()).await;
+    let follow_router = get_follow_routes(state);
+
+    assert_eq!(send_follow(&follow_router, "POST", &follower, following.id).await, StatusCode::CREATED);
+    assert_eq!(send_follow(&follow_router, "POST", &follower, following.id).await, StatusCode::CONFLICT);
+    assert_eq!(send_follow(&follow_router, "POST", &follower, follower.id).await, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(send_follow(&follow_router, "POST", &follower, i64::MAX).await, StatusCode::NOT_FOUND);
+
+    let followers = get_follow_page(&follow_router, format!("/profile/{}/followers", following.id)).await;
+    assert_eq!(followers.items.len(), 1);
+    assert_eq!(followers.items[0].profile_id, follower.id);
+    let following_page = get_follow_page(&follow_router, format!("/profile/{}/following", follower.id)).await;
+    assert_eq!(following_page.items.len(), 1);
+    assert_eq!(following_page.items[0].profile_id, following.id);
+
+    assert_eq!(send_follow(&follow_router, "DELETE", &follower, following.id).await, StatusCode::OK);
+    assert_eq!(send_follow(&follow_router, "DELETE", &follower, following.id).await, StatusCode::NOT_FOUND);
+    let followers = get_follow_page(&follow_router, format!("/profile/{}/followers", following.id)).await;
+    assert!(followers.items.is_empty());
+}
+
+#[tokio::test]
+async fn test_followers_are_paginated() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let following = create_test_account(state.clone()).await;
+    let follow_router = get_follow_routes(state.clone());
+
+    let mut follower_ids = vec![];
+    for _ in 0..3 {
+        let follower = create_test_account(state.clone()).await;
+        assert_eq!(send_follow(&follow_router, "POST", &follower, following.id).await, StatusCode::CREATED);
+        follower_ids.push(follower.id);
+    }
+
+    let first_page = get_follow_page(&follow_router, format!("/profile/{}/followers?page_size=2", following.id)).await;
+    assert_eq!(first_page.items.len(), 2);
+    let cursor = first_page.next_cursor.expect("a second page");
+    let second_page = get_follow_page(&follow_router, format!("/profile/{}/followers?page_size=2&cursor={}", following.id, cursor)).await;
+    assert_eq!(second_page.items.len(), 1);
+    assert!(second_page.next_cursor.is_none());
+
+    let mut paged_ids = first_page.items.iter().chain(second_page.items.iter()).map(|f| f.profile_id).collect::<Vec<i64>>();
+    paged_ids.sort();
+    follower_ids.sort();
+    assert_eq!(paged_ids, follower_ids);
+}
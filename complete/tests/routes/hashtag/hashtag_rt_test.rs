@@ -0,0 +1,139 @@
+use std::sync::Arc;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use complete::lib::app_state::AppState;
+use complete::repository::hashtag::hashtag_models::TrendingHashtagQueryResult;
+use complete::repository::message::message_models::MessageWithFollowingAndBroadcastQueryResult;
+use complete::repository::repo::{EntityId, Repository};
+use complete::routes::hashtag::hashtag_rt::get_hashtag_routes;
+use complete::routes::lib::pagination::Page;
+use complete::routes::message::message_rt::get_message_routes;
+use complete::test_utils::fixtures::{bearer, create_test_account, create_test_message, fake_user_name, init_test_logging};
+use serde_json::json;
+use sqlx::query;
+use tower::ServiceExt;
+
+async fn get_hashtag_message_ids(router: &Router, tag: &str, query: &str) -> Vec<i64> {
+    let req_messages = Request::builder()
+        .uri(format!("/hashtag/{}/messages{}", tag, query))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_messages = router.clone().oneshot(req_messages).await.unwrap();
+    assert_eq!(res_messages.status(), StatusCode::OK);
+    let page: Page<MessageWithFollowingAndBroadcastQueryResult> = serde_json::from_slice(
+        &axum::body::to_bytes(res_messages.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    page.items.iter().map(|m| m.id).collect()
+}
+
+#[tokio::test]
+async fn test_hashtag_timeline() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let author = create_test_account(state.clone()).await;
+    let message_router = get_message_routes(state.clone());
+    let tag = fake_user_name().to_lowercase();
+
+    let first_id = create_test_message(state.clone(), &author, &format!("starting #{}", tag.to_uppercase()), None).await;
+    let untagged_id = create_test_message(state.clone(), &author, "nothing to see", None).await;
+    let req_reply = Request::builder()
+        .uri(format!("/message/{}/reply", untagged_id))
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .header("Authorization", bearer(&author))
+        .body(Body::from(json!({ "body": format!("replying with #{} #{}", tag, tag) }).to_string()))
+        .unwrap();
+    let res_reply = message_router.clone().oneshot(req_reply).await.unwrap();
+    assert_eq!(res_reply.status(), StatusCode::CREATED);
+    let reply: EntityId = serde_json::from_slice(
+        &axum::body::to_bytes(res_reply.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    let reply_id = reply.id;
+    let deleted_id = create_test_message(state.clone(), &author, &format!("#{} going away", tag), None).await;
+    let edited_id = create_test_message(state.clone(), &author, &format!("#{} for now", tag), None).await;
+    let last_id = create_test_message(state.clone(), &author, &format!("#{}", tag), None).await;
+
+    let req_delete = Request::builder()
+        .uri(format!("/message/{}", deleted_id))
+        .method("DELETE")
+        .header("Authorization", bearer(&author))
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(message_router.clone().oneshot(req_delete).await.unwrap().status(), StatusCode::OK);
+    let req_edit = Request::builder()
+        .uri(format!("/message/{}", edited_id))
+        .method("PATCH")
+        .header("Content-Type", "application/json")
+        .header("Authorization", bearer(&author))
+        .body(Body::from(json!({ "body": "no longer tagged" }).to_string()))
+        .unwrap();
+    assert_eq!(message_router.oneshot(req_edit).await.unwrap().status(), StatusCode::OK);
+
+    let hashtag_router = get_hashtag_routes(state);
+    assert_eq!(
+        get_hashtag_message_ids(&hashtag_router, &tag.to_uppercase(), "").await,
+        vec![last_id, reply_id, first_id]
+    );
+    assert_eq!(
+        get_hashtag_message_ids(&hashtag_router, &format!("%23{}", tag), "?page_size=2").await,
+        vec![last_id, reply_id]
+    );
+
+    let req_invalid = Request::builder()
+        .uri("/hashtag/2024/messages")
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_invalid = hashtag_router.oneshot(req_invalid).await.unwrap();
+    assert_eq!(res_invalid.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_trends_rank_rising_tags() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let author = create_test_account(state.clone()).await;
+    let rising = fake_user_name().to_lowercase();
+    let climbing = fake_user_name().to_lowercase();
+    let steady = fake_user_name().to_lowercase();
+
+    // the previous window gets one #climbing and two #steady
+    let mut earlier_ids = vec![create_test_message(state.clone(), &author, &format!("#{}", climbing), None).await];
+    for _ in 0..2 {
+        earlier_ids.push(create_test_message(state.clone(), &author, &format!("#{}", steady), None).await);
+    }
+    query("update message_hashtag set created_at = now() - interval '90 seconds' where message_id = any($1)")
+        .bind(&earlier_ids)
+        .execute(state.repo.get_pool())
+        .await
+        .unwrap();
+    for _ in 0..3 {
+        create_test_message(state.clone(), &author, &format!("#{}", rising), None).await;
+    }
+    for _ in 0..2 {
+        create_test_message(state.clone(), &author, &format!("#{} #{}", climbing, steady), None).await;
+    }
+
+    let req_trends = Request::builder()
+        .uri("/trends?window_minutes=1&limit=50")
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_trends = get_hashtag_routes(state).oneshot(req_trends).await.unwrap();
+    assert_eq!(res_trends.status(), StatusCode::OK);
+    let trends: Vec<TrendingHashtagQueryResult> = serde_json::from_slice(
+        &axum::body::to_bytes(res_trends.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    let position = |name: &str| trends.iter().position(|t| t.name == name);
+
+    let rising_trend = &trends[position(&rising).unwrap()];
+    assert_eq!((rising_trend.current_count, rising_trend.previous_count), (3, 0));
+    let climbing_trend = &trends[position(&climbing).unwrap()];
+    assert_eq!((climbing_trend.current_count, climbing_trend.previous_count), (2, 1));
+    assert!(position(&rising) < position(&climbing));
+    // used as often as before, so not rising at all
+    assert_eq!(position(&steady), None);
+}
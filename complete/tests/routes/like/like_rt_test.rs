@@ -0,0 +1,89 @@
+use std::sync::Arc;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use complete::controllers::like::like_models::MessageLikes;
+use complete::lib::app_state::AppState;
+use complete::repository::like::like_models::LikeProfileQueryResult;
+use complete::repository::message::message_models::MessageWithFollowingAndBroadcastQueryResult;
+use complete::routes::like::like_rt::get_like_routes;
+use complete::routes::lib::pagination::Page;
+use complete::routes::message::message_rt::get_message_routes;
+use complete::test_utils::fixtures::{bearer, create_test_account, create_test_message, init_test_logging, TestAccount};
+use tower::ServiceExt;
+
+async fn send_like(router: &Router, method: &str, account: &TestAccount, message_id: i64) -> (StatusCode, Option<MessageLikes>) {
+    let req_like = Request::builder()
+        .uri(format!("/message/{}/like", message_id))
+        .method(method)
+        .header("Authorization", bearer(account))
+        .body(Body::empty())
+        .unwrap();
+    let res_like = router.clone().oneshot(req_like).await.unwrap();
+    let status = res_like.status();
+    let body = axum::body::to_bytes(res_like.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).ok())
+}
+
+#[tokio::test]
+async fn test_like_and_unlike_are_idempotent() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let author = create_test_account(state.clone()).await;
+    let fan = create_test_account(state.clone()).await;
+    let other_fan = create_test_account(state.clone()).await;
+    let message_id = create_test_message(state.clone(), &author, "like me", None).await;
+    let like_router = get_like_routes(state.clone());
+
+    let (status, likes) = send_like(&like_router, "PUT", &fan, message_id).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(likes.unwrap().likes, 1);
+    let (_, likes) = send_like(&like_router, "PUT", &fan, message_id).await;
+    assert_eq!(likes.unwrap().likes, 1);
+    let (_, likes) = send_like(&like_router, "PUT", &other_fan, message_id).await;
+    assert_eq!(likes.unwrap().likes, 2);
+
+    let req_likes = Request::builder()
+        .uri(format!("/message/{}/likes", message_id))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_likes = like_router.clone().oneshot(req_likes).await.unwrap();
+    let likes_page: Page<LikeProfileQueryResult> = serde_json::from_slice(
+        &axum::body::to_bytes(res_likes.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    let mut liker_ids = likes_page.items.iter().map(|like| like.profile_id).collect::<Vec<i64>>();
+    liker_ids.sort();
+    assert_eq!(liker_ids, vec![fan.id, other_fan.id]);
+
+    let (status, likes) = send_like(&like_router, "DELETE", &fan, message_id).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(likes.unwrap().likes, 1);
+    let (_, likes) = send_like(&like_router, "DELETE", &fan, message_id).await;
+    assert_eq!(likes.unwrap().likes, 1);
+
+    let req_message = Request::builder()
+        .uri(format!("/message/{}", message_id))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_message = get_message_routes(state).oneshot(req_message).await.unwrap();
+    let message: MessageWithFollowingAndBroadcastQueryResult = serde_json::from_slice(
+        &axum::body::to_bytes(res_message.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert_eq!(message.likes, 1);
+}
+
+#[tokio::test]
+async fn test_like_missing_message_is_not_found() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let fan = create_test_account(state.clone()).await;
+    let like_router = get_like_routes(state);
+
+    let (status, _) = send_like(&like_router, "PUT", &fan, i64::MAX).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send_like(&like_router, "DELETE", &fan, i64::MAX).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use complete::controllers::profile::profile_models::AvatarUploaded;
+use complete::lib::app_state::AppState;
+use complete::repository::profile::profile_models::ProfileQueryResult;
+use complete::routes::media::media_rt::get_media_routes;
+use complete::routes::profile::profile_rt::get_profile_router;
+use complete::test_utils::fixtures::{create_test_account, init_test_logging, test_png, upload_avatar_request};
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn test_get_media_streams_stored_avatar() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let account = create_test_account(state.clone()).await;
+    let profile_router = get_profile_router(state.clone());
+    let media_router = get_media_routes(state);
+
+    let res_upload = profile_router.clone().oneshot(
+        upload_avatar_request(&account, account.id, "image/png", test_png(300, 200))
+    ).await.unwrap();
+    let uploaded: AvatarUploaded = serde_json::from_slice(
+        &axum::body::to_bytes(res_upload.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+
+    let req_profile = Request::builder()
+        .uri(format!("/profile/{}", account.id))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_profile = profile_router.clone().oneshot(req_profile).await.unwrap();
+    let profile: ProfileQueryResult = serde_json::from_slice(
+        &axum::body::to_bytes(res_profile.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert_eq!(profile.avatar_id, Some(uploaded.avatar_id));
+
+    let req_media = Request::builder()
+        .uri(format!("/media/{}", uploaded.avatar_id))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_media = media_router.clone().oneshot(req_media).await.unwrap();
+    assert_eq!(res_media.status(), StatusCode::OK);
+    assert_eq!(res_media.headers()[header::CONTENT_TYPE], "image/png");
+    assert!(res_media.headers()[header::CACHE_CONTROL].to_str().unwrap().contains("immutable"));
+    let etag = res_media.headers()[header::ETAG].clone();
+    let bytes = axum::body::to_bytes(res_media.into_body(), usize::MAX).await.unwrap();
+    let image = image::load_from_memory(&bytes).unwrap();
+    assert_eq!((image.width(), image.height()), (128, 128));
+
+    let req_revalidate = Request::builder()
+        .uri(format!("/media/{}", uploaded.avatar_id))
+        .method("GET")
+        .header(header::IF_NONE_MATCH, etag)
+        .body(Body::empty())
+        .unwrap();
+    let res_revalidate = media_router.clone().oneshot(req_revalidate).await.unwrap();
+    assert_eq!(res_revalidate.status(), StatusCode::NOT_MODIFIED);
+
+    // replacing the avatar removes the media the old one used
+    profile_router.oneshot(
+        upload_avatar_request(&account, account.id, "image/png", test_png(64, 64))
+    ).await.unwrap();
+    let req_replaced = Request::builder()
+        .uri(format!("/media/{}", uploaded.avatar_id))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_replaced = media_router.oneshot(req_replaced).await.unwrap();
+    assert_eq!(res_replaced.status(), StatusCode::NOT_FOUND);
+}
@@ -1,18 +1,21 @@
 use std::sync::Arc;
-use std::usize;
 use axum::body::Body;
 use axum::extract::State;
-use axum::http::{Request, StatusCode};
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use complete::controllers::media::media_ctrl::ATTACHMENT_MEDIA_CACHE_CONTROL;
+use complete::controllers::message::message_models::{MessageHistory, MessageThread};
+use chrono::Duration;
 use complete::lib::app_state::AppState;
+use complete::lib::message_purge::purge_deleted_messages;
 use complete::repository::message::message_models::MessageWithFollowingAndBroadcastQueryResult;
-use complete::repository::repo::{DbRepo, EntityId};
+use complete::repository::repo::{EntityId, Repository};
+use complete::routes::lib::error::ProblemDetails;
 use complete::routes::message::message_rt::get_message_routes;
-use complete::routes::profile::profile_rt::get_profile_router;
-use complete::test_utils::fixtures::init_test_logging;
+use complete::routes::media::media_rt::get_media_routes;
+use complete::test_utils::fixtures::{bearer, create_test_account, create_test_message, init_test_logging, multipart_body, test_png, TestAccount};
 use tower::ServiceExt;
 use serde_json::json;
-use fake::faker::internet::en::Username;
-use fake::faker::name::en::{FirstName, LastName};
 use fake::faker::lorem::en::Sentence;
 use fake::Fake;
 
@@ -20,24 +23,9 @@ use fake::Fake;
 async fn test_insert_message() {
     init_test_logging();
 
-    let state = State(Arc::new(AppState {
-        repo: DbRepo::init().await
-    }));    
+    let state = State(Arc::new(AppState::init().await));    
 
-    let req_create_profile = Request::builder()
-        .uri("/profile")
-        .method("POST")
-        .header("Content-Type", "application/json")
-        .body(Body::from(json!({
-            "user_name": Username().fake::<String>(),
-            "full_name": format!("{} {}", FirstName().fake::<String>(), LastName().fake::<String>()),
-            "description": Sentence(1..2).fake::<String>()
-        }).to_string()))
-        .unwrap();   
-    let res_profile = get_profile_router(state.clone()).oneshot(req_create_profile).await.unwrap();
-    let profile: EntityId = serde_json::from_slice(
-        &axum::body::to_bytes(res_profile.into_body(), usize::MAX).await.unwrap()
-    ).unwrap();
+    let profile = create_test_account(state.clone()).await;
 
     let new_message = Sentence(1..2).fake::<String>();
     let message_router = get_message_routes(state.clone());
@@ -45,8 +33,8 @@ async fn test_insert_message() {
         .uri("/message")
         .method("POST")
         .header("Content-Type", "application/json")
+        .header("Authorization", bearer(&profile))
         .body(Body::from(json!({
-            "user_id": profile.id,
             "body": new_message.clone()
         }).to_string()))
         .unwrap();
@@ -67,4 +55,431 @@ async fn test_insert_message() {
         &axum::body::to_bytes(res_message.into_body(), usize::MAX).await.unwrap()
     ).unwrap();
     assert!(message.body.unwrap() == new_message);
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_insert_message_requires_auth() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+
+    let req_create_message = Request::builder()
+        .uri("/message")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({
+            "body": Sentence(1..2).fake::<String>()
+        }).to_string()))
+        .unwrap();
+    let res_create_message = get_message_routes(state).oneshot(req_create_message).await.unwrap();
+    assert_eq!(res_create_message.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_insert_message_errors_are_problem_json() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let profile = create_test_account(state.clone()).await;
+    let message_router = get_message_routes(state);
+
+    let cases = [
+        (json!({ "body": "x".repeat(141) }), "validation_failed"),
+        (json!({ "body": "broadcast", "broadcasting_msg_id": i64::MAX }), "invalid_reference"),
+        (json!({ "text": "missing body field" }), "validation_failed")
+    ];
+    for (payload, expected_code) in cases {
+        let req_create_message = Request::builder()
+            .uri("/message")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .header("Authorization", bearer(&profile))
+            .body(Body::from(payload.to_string()))
+            .unwrap();
+        let res_create_message = message_router.clone().oneshot(req_create_message).await.unwrap();
+        assert_eq!(res_create_message.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(res_create_message.headers()["Content-Type"], "application/problem+json");
+        let problem: ProblemDetails = serde_json::from_slice(
+            &axum::body::to_bytes(res_create_message.into_body(), usize::MAX).await.unwrap()
+        ).unwrap();
+        assert_eq!(problem.status, 422);
+        assert_eq!(problem.code, expected_code);
+    }
+}
+
+#[tokio::test]
+async fn test_get_missing_message_is_not_found() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+
+    let req_message = Request::builder()
+        .uri(format!("/message/{}", i64::MAX))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_message = get_message_routes(state).oneshot(req_message).await.unwrap();
+    assert_eq!(res_message.status(), StatusCode::NOT_FOUND);
+    let problem: ProblemDetails = serde_json::from_slice(
+        &axum::body::to_bytes(res_message.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert_eq!(problem.code, "not_found");
+}
+
+async fn post_reply(router: &Router, author: &TestAccount, original_id: i64, body: &str) -> i64 {
+    let req_reply = Request::builder()
+        .uri(format!("/message/{}/reply", original_id))
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .header("Authorization", bearer(author))
+        .body(Body::from(json!({ "body": body }).to_string()))
+        .unwrap();
+    let res_reply = router.clone().oneshot(req_reply).await.unwrap();
+    assert_eq!(res_reply.status(), StatusCode::CREATED);
+    let reply: EntityId = serde_json::from_slice(
+        &axum::body::to_bytes(res_reply.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    reply.id
+}
+
+async fn get_thread(router: &Router, uri: String) -> MessageThread {
+    let req_thread = Request::builder()
+        .uri(uri)
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_thread = router.clone().oneshot(req_thread).await.unwrap();
+    assert_eq!(res_thread.status(), StatusCode::OK);
+    serde_json::from_slice(
+        &axum::body::to_bytes(res_thread.into_body(), usize::MAX).await.unwrap()
+    ).unwrap()
+}
+
+#[tokio::test]
+async fn test_reply_thread() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let author = create_test_account(state.clone()).await;
+    let replier = create_test_account(state.clone()).await;
+    let message_router = get_message_routes(state.clone());
+
+    // root -> reply_a -> reply_a1, root -> reply_b
+    let root_id = create_test_message(state.clone(), &author, "root", None).await;
+    let reply_a = post_reply(&message_router, &replier, root_id, "reply a").await;
+    let reply_a1 = post_reply(&message_router, &author, reply_a, "reply a1").await;
+    let reply_b = post_reply(&message_router, &replier, root_id, "reply b").await;
+
+    let thread = get_thread(&message_router, format!("/message/{}/thread", reply_a1)).await;
+    assert_eq!(thread.ancestors.iter().map(|m| m.id).collect::<Vec<i64>>(), vec![root_id, reply_a]);
+    assert_eq!(thread.ancestors.iter().map(|m| m.depth).collect::<Vec<i32>>(), vec![-2, -1]);
+    assert_eq!(thread.message.id, reply_a1);
+    assert_eq!(thread.message.parent_id, Some(reply_a));
+    assert!(thread.replies.items.is_empty());
+
+    let first_page = get_thread(&message_router, format!("/message/{}/thread?page_size=2", root_id)).await;
+    assert!(first_page.ancestors.is_empty());
+    assert_eq!(first_page.message.reply_count, 2);
+    assert_eq!(first_page.replies.items.iter().map(|m| (m.id, m.depth)).collect::<Vec<(i64, i32)>>(), vec![(reply_a, 1), (reply_a1, 2)]);
+    assert_eq!(first_page.replies.items[0].reply_count, 1);
+    let cursor = first_page.replies.next_cursor.expect("a second page of replies");
+    let second_page = get_thread(&message_router, format!("/message/{}/thread?page_size=2&cursor={}", root_id, cursor)).await;
+    assert_eq!(second_page.replies.items.iter().map(|m| m.id).collect::<Vec<i64>>(), vec![reply_b]);
+    assert!(second_page.replies.next_cursor.is_none());
+}
+
+#[tokio::test]
+async fn test_reply_to_missing_message_is_not_found() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let replier = create_test_account(state.clone()).await;
+
+    let req_reply = Request::builder()
+        .uri(format!("/message/{}/reply", i64::MAX))
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .header("Authorization", bearer(&replier))
+        .body(Body::from(json!({ "body": "hello?" }).to_string()))
+        .unwrap();
+    let res_reply = get_message_routes(state).oneshot(req_reply).await.unwrap();
+    assert_eq!(res_reply.status(), StatusCode::NOT_FOUND);
+}
+
+fn post_multipart_message(author: &TestAccount, parts: &[(&str, Option<&str>, &[u8])]) -> Request<Body> {
+    let (content_type, body) = multipart_body(parts);
+    Request::builder()
+        .uri("/message")
+        .method("POST")
+        .header("Content-Type", content_type)
+        .header("Authorization", bearer(author))
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_insert_message_with_attachments() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let author = create_test_account(state.clone()).await;
+    let message_router = get_message_routes(state.clone());
+
+    let mut jpeg = std::io::Cursor::new(vec![]);
+    image::RgbImage::new(40, 30).write_to(&mut jpeg, image::ImageFormat::Jpeg).unwrap();
+    let png = test_png(20, 10);
+    let res_create = message_router.clone().oneshot(post_multipart_message(&author, &[
+        ("body", None, b"two pictures"),
+        ("attachment", Some("image/png"), &png),
+        ("alt_text", None, b"a gradient"),
+        ("attachment", Some("image/jpeg"), jpeg.get_ref())
+    ])).await.unwrap();
+    assert_eq!(res_create.status(), StatusCode::CREATED);
+    let message: EntityId = serde_json::from_slice(
+        &axum::body::to_bytes(res_create.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+
+    let req_message = Request::builder()
+        .uri(format!("/message/{}", message.id))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_message = message_router.oneshot(req_message).await.unwrap();
+    let message: MessageWithFollowingAndBroadcastQueryResult = serde_json::from_slice(
+        &axum::body::to_bytes(res_message.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert_eq!(message.body.as_deref(), Some("two pictures"));
+    let attachments = &message.attachments.0;
+    assert_eq!(attachments.len(), 2);
+    assert_eq!(
+        (attachments[0].content_type.as_str(), attachments[0].width, attachments[0].height, attachments[0].alt_text.as_deref()),
+        ("image/png", 20, 10, Some("a gradient"))
+    );
+    assert_eq!(
+        (attachments[1].content_type.as_str(), attachments[1].width, attachments[1].height, attachments[1].alt_text.as_deref()),
+        ("image/jpeg", 40, 30, None)
+    );
+
+    let req_media = Request::builder()
+        .uri(&attachments[0].url)
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_media = get_media_routes(state).oneshot(req_media).await.unwrap();
+    assert_eq!(res_media.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(res_media.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(bytes.len() as i64, attachments[0].byte_size);
+}
+
+#[tokio::test]
+async fn test_insert_message_attachments_are_validated() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let author = create_test_account(state.clone()).await;
+    let message_router = get_message_routes(state);
+    let png = test_png(8, 8);
+
+    let res_too_many = message_router.clone().oneshot(post_multipart_message(&author, &[
+        ("body", None, b"too many"),
+        ("attachment", Some("image/png"), &png),
+        ("attachment", Some("image/png"), &png),
+        ("attachment", Some("image/png"), &png),
+        ("attachment", Some("image/png"), &png),
+        ("attachment", Some("image/png"), &png)
+    ])).await.unwrap();
+    assert_eq!(res_too_many.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let res_not_image = message_router.clone().oneshot(post_multipart_message(&author, &[
+        ("body", None, b"not an image"),
+        ("attachment", Some("image/png"), b"plain text pretending")
+    ])).await.unwrap();
+    assert_eq!(res_not_image.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let res_orphan_alt_text = message_router.oneshot(post_multipart_message(&author, &[
+        ("body", None, b"alt text first"),
+        ("alt_text", None, b"describes nothing"),
+        ("attachment", Some("image/png"), &png)
+    ])).await.unwrap();
+    assert_eq!(res_orphan_alt_text.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+fn delete_message_request(account: &TestAccount, id: i64) -> Request<Body> {
+    Request::builder()
+        .uri(format!("/message/{}", id))
+        .method("DELETE")
+        .header("Authorization", bearer(account))
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_delete_message_leaves_tombstone() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let author = create_test_account(state.clone()).await;
+    let broadcaster = create_test_account(state.clone()).await;
+    let message_router = get_message_routes(state.clone());
+
+    let original_id = create_test_message(state.clone(), &author, "soon gone", None).await;
+    let broadcast_id = create_test_message(state.clone(), &broadcaster, "look at this", Some(original_id)).await;
+    let reply_id = post_reply(&message_router, &broadcaster, original_id, "still here").await;
+
+    let res_forbidden = message_router.clone().oneshot(delete_message_request(&broadcaster, original_id)).await.unwrap();
+    assert_eq!(res_forbidden.status(), StatusCode::FORBIDDEN);
+    let res_delete = message_router.clone().oneshot(delete_message_request(&author, original_id)).await.unwrap();
+    assert_eq!(res_delete.status(), StatusCode::OK);
+    let res_delete_again = message_router.clone().oneshot(delete_message_request(&author, original_id)).await.unwrap();
+    assert_eq!(res_delete_again.status(), StatusCode::NOT_FOUND);
+
+    let req_original = Request::builder()
+        .uri(format!("/message/{}", original_id))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_original = message_router.clone().oneshot(req_original).await.unwrap();
+    assert_eq!(res_original.status(), StatusCode::NOT_FOUND);
+
+    let req_broadcast = Request::builder()
+        .uri(format!("/message/{}", broadcast_id))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_broadcast = message_router.clone().oneshot(req_broadcast).await.unwrap();
+    let broadcast: MessageWithFollowingAndBroadcastQueryResult = serde_json::from_slice(
+        &axum::body::to_bytes(res_broadcast.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert_eq!(broadcast.message_broadcast_id, Some(original_id));
+    assert!(broadcast.message_broadcast_unavailable);
+    assert!(broadcast.message_broadcast_body.is_none());
+
+    let thread = get_thread(&message_router, format!("/message/{}/thread", reply_id)).await;
+    assert_eq!(thread.ancestors.len(), 1);
+    assert_eq!(thread.ancestors[0].id, original_id);
+    assert!(thread.ancestors[0].deleted);
+    assert!(thread.ancestors[0].body.is_none());
+
+    let req_reply = Request::builder()
+        .uri(format!("/message/{}/reply", original_id))
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .header("Authorization", bearer(&broadcaster))
+        .body(Body::from(json!({ "body": "too late" }).to_string()))
+        .unwrap();
+    let res_reply = message_router.oneshot(req_reply).await.unwrap();
+    assert_eq!(res_reply.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_purge_removes_deleted_message_content() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let author = create_test_account(state.clone()).await;
+    let message_router = get_message_routes(state.clone());
+
+    let png = test_png(4, 4);
+    let res_create = message_router.clone().oneshot(post_multipart_message(&author, &[
+        ("body", None, b"purge me"),
+        ("attachment", Some("image/png"), &png)
+    ])).await.unwrap();
+    let message: EntityId = serde_json::from_slice(
+        &axum::body::to_bytes(res_create.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    let media_id = sqlx::query_scalar::<_, i64>("select media_id from message_attachment where message_id = $1")
+        .bind(message.id)
+        .fetch_one(state.repo.get_pool())
+        .await
+        .unwrap();
+    let media_request = || Request::builder()
+        .uri(format!("/media/{}", media_id))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let media_router = get_media_routes(state.clone());
+    let res_media = media_router.clone().oneshot(media_request()).await.unwrap();
+    assert_eq!(res_media.status(), StatusCode::OK);
+    // unlike avatars, attachments may be taken down, so caches must check back
+    assert_eq!(res_media.headers()[header::CACHE_CONTROL], ATTACHMENT_MEDIA_CACHE_CONTROL);
+    message_router.oneshot(delete_message_request(&author, message.id)).await.unwrap();
+    // attachments go away with the message, long before the purge
+    assert_eq!(media_router.clone().oneshot(media_request()).await.unwrap().status(), StatusCode::NOT_FOUND);
+
+    // nothing is purged while the message is within the retention period
+    purge_deleted_messages(&state, Duration::days(1)).await.unwrap();
+    let body = sqlx::query_scalar::<_, Option<String>>("select body from message where id = $1")
+        .bind(message.id)
+        .fetch_one(state.repo.get_pool())
+        .await
+        .unwrap();
+    assert_eq!(body.as_deref(), Some("purge me"));
+
+    assert!(purge_deleted_messages(&state, Duration::zero()).await.unwrap() >= 1);
+    let body = sqlx::query_scalar::<_, Option<String>>("select body from message where id = $1")
+        .bind(message.id)
+        .fetch_one(state.repo.get_pool())
+        .await
+        .unwrap();
+    assert!(body.is_none());
+
+    let media_rows = sqlx::query_scalar::<_, i64>("select count(*) from media where id = $1")
+        .bind(media_id)
+        .fetch_one(state.repo.get_pool())
+        .await
+        .unwrap();
+    assert_eq!(media_rows, 0);
+}
+
+fn edit_message_request(account: &TestAccount, id: i64, body: &str) -> Request<Body> {
+    Request::builder()
+        .uri(format!("/message/{}", id))
+        .method("PATCH")
+        .header("Content-Type", "application/json")
+        .header("Authorization", bearer(account))
+        .body(Body::from(json!({ "body": body }).to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_edit_message_keeps_history() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let author = create_test_account(state.clone()).await;
+    let other = create_test_account(state.clone()).await;
+    let message_router = get_message_routes(state.clone());
+    let message_id = create_test_message(state.clone(), &author, "teh first draft", None).await;
+
+    let res_forbidden = message_router.clone().oneshot(edit_message_request(&other, message_id, "hijacked")).await.unwrap();
+    assert_eq!(res_forbidden.status(), StatusCode::FORBIDDEN);
+    for body in ["the first draft", "the final draft"] {
+        let res_edit = message_router.clone().oneshot(edit_message_request(&author, message_id, body)).await.unwrap();
+        assert_eq!(res_edit.status(), StatusCode::OK);
+    }
+
+    let req_message = Request::builder()
+        .uri(format!("/message/{}", message_id))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_message = message_router.clone().oneshot(req_message).await.unwrap();
+    let message: MessageWithFollowingAndBroadcastQueryResult = serde_json::from_slice(
+        &axum::body::to_bytes(res_message.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert_eq!(message.body.as_deref(), Some("the final draft"));
+    assert!(message.edited_at.is_some());
+
+    let req_history = Request::builder()
+        .uri(format!("/message/{}/history", message_id))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_history = message_router.clone().oneshot(req_history).await.unwrap();
+    let history: MessageHistory = serde_json::from_slice(
+        &axum::body::to_bytes(res_history.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert_eq!(history.body.as_deref(), Some("the final draft"));
+    assert_eq!(
+        history.revisions.iter().map(|revision| revision.body.as_deref()).collect::<Vec<Option<&str>>>(),
+        vec![Some("the first draft"), Some("teh first draft")]
+    );
+
+    // once the edit window has passed the message is final
+    sqlx::query("update message set created_at = created_at - interval '1 day' where id = $1")
+        .bind(message_id)
+        .execute(state.repo.get_pool())
+        .await
+        .unwrap();
+    let res_too_late = message_router.oneshot(edit_message_request(&author, message_id, "too late")).await.unwrap();
+    assert_eq!(res_too_late.status(), StatusCode::CONFLICT);
+}
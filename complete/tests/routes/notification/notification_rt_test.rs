@@ -0,0 +1,139 @@
+use std::sync::Arc;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use complete::controllers::notification::notification_models::{NotificationPage, UnreadNotifications};
+use complete::lib::app_state::AppState;
+use complete::repository::notification::notification_models::NotificationKind;
+use complete::routes::like::like_rt::get_like_routes;
+use complete::routes::message::message_rt::get_message_routes;
+use complete::routes::notification::notification_rt::get_notification_routes;
+use complete::test_utils::fixtures::{bearer, create_test_account, create_test_message, follow_test_account, init_test_logging, TestAccount};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+async fn like(router: &Router, account: &TestAccount, message_id: i64, method: &str) {
+    let req_like = Request::builder()
+        .uri(format!("/message/{}/like", message_id))
+        .method(method)
+        .header("Authorization", bearer(account))
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(router.clone().oneshot(req_like).await.unwrap().status(), StatusCode::OK);
+}
+
+async fn get_notifications(router: &Router, account: &TestAccount, query: &str) -> NotificationPage {
+    let req_notifications = Request::builder()
+        .uri(format!("/notifications{}", query))
+        .method("GET")
+        .header("Authorization", bearer(account))
+        .body(Body::empty())
+        .unwrap();
+    let res_notifications = router.clone().oneshot(req_notifications).await.unwrap();
+    assert_eq!(res_notifications.status(), StatusCode::OK);
+    serde_json::from_slice(&axum::body::to_bytes(res_notifications.into_body(), usize::MAX).await.unwrap()).unwrap()
+}
+
+async fn mark_read(router: &Router, account: &TestAccount, body: Value) -> i64 {
+    let req_read = Request::builder()
+        .uri("/notifications/read")
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .header("Authorization", bearer(account))
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let res_read = router.clone().oneshot(req_read).await.unwrap();
+    assert_eq!(res_read.status(), StatusCode::OK);
+    let unread: UnreadNotifications = serde_json::from_slice(
+        &axum::body::to_bytes(res_read.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    unread.unread_count
+}
+
+#[tokio::test]
+async fn test_notifications_group_similar_events() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let recipient = create_test_account(state.clone()).await;
+    let alice = create_test_account(state.clone()).await;
+    let bob = create_test_account(state.clone()).await;
+    let carol = create_test_account(state.clone()).await;
+    let like_router = get_like_routes(state.clone());
+    let notification_router = get_notification_routes(state.clone());
+
+    let message_id = create_test_message(state.clone(), &recipient, "notice me", None).await;
+    follow_test_account(state.clone(), &alice, recipient.id).await;
+    for liker in [&alice, &bob, &carol, &recipient] {
+        like(&like_router, liker, message_id, "PUT").await;
+    }
+    let req_reply = Request::builder()
+        .uri(format!("/message/{}/reply", message_id))
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .header("Authorization", bearer(&bob))
+        .body(Body::from(json!({ "body": "noticed" }).to_string()))
+        .unwrap();
+    assert_eq!(get_message_routes(state.clone()).oneshot(req_reply).await.unwrap().status(), StatusCode::CREATED);
+    create_test_message(state.clone(), &carol, "", Some(message_id)).await;
+    let mention_id = create_test_message(state.clone(), &alice, &format!("hey @{} @{}", recipient.user_name, recipient.user_name), None).await;
+
+    let page = get_notifications(&notification_router, &recipient, "").await;
+    assert_eq!(page.unread_count, 5);
+    let kinds = page.page.items.iter().map(|n| n.notification.kind).collect::<Vec<NotificationKind>>();
+    assert_eq!(kinds, vec![
+        NotificationKind::Mention,
+        NotificationKind::Broadcast,
+        NotificationKind::Reply,
+        NotificationKind::Like,
+        NotificationKind::Follow
+    ]);
+    let likes = &page.page.items[3];
+    assert_eq!(likes.notification.message_id, Some(message_id));
+    assert_eq!(likes.notification.actor_count, 3);
+    assert_eq!(
+        likes.notification.actors.iter().map(|a| a.profile_id).collect::<Vec<i64>>(),
+        vec![carol.id, bob.id, alice.id]
+    );
+    assert_eq!(likes.summary, format!("{} and 2 others liked your message", carol.user_name));
+    assert_eq!(page.page.items[0].notification.message_id, Some(mention_id));
+    assert_eq!(page.page.items[0].summary, format!("{} mentioned you", alice.user_name));
+    assert_eq!(page.page.items[4].summary, format!("{} followed you", alice.user_name));
+
+    let first_page = get_notifications(&notification_router, &recipient, "?page_size=2").await;
+    assert_eq!(first_page.page.items.len(), 2);
+    let second_page = get_notifications(
+        &notification_router,
+        &recipient,
+        &format!("?page_size=2&cursor={}", first_page.page.next_cursor.unwrap())
+    ).await;
+    assert_eq!(second_page.page.items[0].notification.kind, NotificationKind::Reply);
+
+    // once read, the next like starts a new group
+    let like_group_id = likes.notification.id;
+    assert_eq!(mark_read(&notification_router, &recipient, json!({ "ids": [like_group_id] })).await, 4);
+    assert_eq!(mark_read(&notification_router, &alice, json!({ "ids": [like_group_id] })).await, 0);
+    like(&like_router, &alice, message_id, "DELETE").await;
+    like(&like_router, &alice, message_id, "PUT").await;
+    let page = get_notifications(&notification_router, &recipient, "").await;
+    assert_eq!(page.unread_count, 5);
+    assert_eq!(page.page.items[0].summary, format!("{} liked your message", alice.user_name));
+    assert!(page.page.items.iter().any(|n| n.notification.id == like_group_id && n.notification.read));
+
+    assert_eq!(mark_read(&notification_router, &recipient, json!({})).await, 0);
+    assert!(get_notifications(&notification_router, &recipient, "").await.page.items.iter().all(|n| n.notification.read));
+}
+
+#[tokio::test]
+async fn test_notifications_require_auth() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+
+    let req_notifications = Request::builder()
+        .uri("/notifications")
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_notifications = get_notification_routes(state).oneshot(req_notifications).await.unwrap();
+    assert_eq!(res_notifications.status(), StatusCode::UNAUTHORIZED);
+}
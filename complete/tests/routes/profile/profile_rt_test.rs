@@ -1,14 +1,16 @@
 use std::sync::Arc;
-use std::usize;
 use axum::body::Body;
 use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use complete::controllers::profile::profile_models::AvatarUploaded;
 use complete::lib::app_state::AppState;
 use complete::repository::profile::profile_models::ProfileQueryResult;
-use complete::repository::repo::{DbRepo, EntityId};
+use complete::repository::repo::EntityId;
+use complete::routes::auth::auth_rt::get_auth_routes;
+use complete::routes::lib::error::ProblemDetails;
 use complete::routes::profile::profile_rt::get_profile_router;
-use complete::test_utils::fixtures::init_test_logging;
+use complete::test_utils::fixtures::{bearer, create_test_account, fake_user_name, init_test_logging, test_png, upload_avatar_request, TestAccount};
 use serde_json::json;
-use fake::faker::internet::en::Username;
 use fake::faker::name::en::{FirstName, LastName};
 use fake::faker::lorem::en::Sentence;
 use fake::Fake;
@@ -17,27 +19,25 @@ use tower::ServiceExt;
 #[tokio::test]
 async fn test_create_profile() {
     init_test_logging();
-    let state = State(Arc::new(AppState {
-        repo: DbRepo::init().await
-    }));
+    let state = State(Arc::new(AppState::init().await));
 
-    let user_name = Username().fake::<String>();
+    let user_name = fake_user_name();
     let full_name = format!("{} {}", FirstName().fake::<String>(), LastName().fake::<String>());
     let description = Sentence(1..2).fake::<String>();
     let req_create_profile = Request::builder()
-        .uri("/profile")
+        .uri("/auth/register")
         .method("POST")
         .header("Content-Type", "application/json")
         .body(Body::from(
             json!({
                 "user_name": user_name,
                 "full_name": full_name,
-                "description": description
+                "description": description,
+                "password": "test-password"
             }).to_string()
         ))
         .unwrap();
-    let profile_router = get_profile_router(state.clone());
-    let res_create_profile = profile_router.clone().oneshot(req_create_profile).await.unwrap();    
+    let res_create_profile = get_auth_routes(state.clone()).oneshot(req_create_profile).await.unwrap();    
     let profile_entity: EntityId = serde_json::from_slice(
         &axum::body::to_bytes(res_create_profile.into_body(), usize::MAX).await.unwrap()
     ).unwrap();
@@ -48,7 +48,7 @@ async fn test_create_profile() {
         .method("GET")
         .body(Body::empty())
         .unwrap();
-    let res_profile = profile_router.oneshot(req_profile).await.unwrap();
+    let res_profile = get_profile_router(state).oneshot(req_profile).await.unwrap();
     let profile: ProfileQueryResult = serde_json::from_slice(
         &axum::body::to_bytes(res_profile.into_body(), usize::MAX).await.unwrap()
     ).unwrap();
@@ -56,4 +56,181 @@ async fn test_create_profile() {
     assert_eq!(profile.user_name, user_name);
     assert_eq!(profile.full_name, full_name);
     assert_eq!(profile.description, description);
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_get_missing_profile_is_not_found() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+
+    let req_profile = Request::builder()
+        .uri(format!("/profile/{}", i64::MAX))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_profile = get_profile_router(state).oneshot(req_profile).await.unwrap();
+    assert_eq!(res_profile.status(), StatusCode::NOT_FOUND);
+    let problem: ProblemDetails = serde_json::from_slice(
+        &axum::body::to_bytes(res_profile.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert_eq!(problem.code, "not_found");
+}
+
+fn patch_profile_request(account: &TestAccount, id: i64, payload: serde_json::Value) -> Request<Body> {
+    Request::builder()
+        .uri(format!("/profile/{}", id))
+        .method("PATCH")
+        .header("Content-Type", "application/json")
+        .header("Authorization", bearer(account))
+        .body(Body::from(payload.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_update_profile() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let account = create_test_account(state.clone()).await;
+    let profile_router = get_profile_router(state);
+
+    let res_update = profile_router.clone().oneshot(patch_profile_request(&account, account.id, json!({
+        "full_name": "Renamed Person",
+        "region": "Somewhere"
+    }))).await.unwrap();
+    assert_eq!(res_update.status(), StatusCode::OK);
+    let updated: ProfileQueryResult = serde_json::from_slice(
+        &axum::body::to_bytes(res_update.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert_eq!(updated.full_name, "Renamed Person");
+    assert_eq!(updated.region.as_deref(), Some("Somewhere"));
+    assert_eq!(updated.user_name, account.user_name);
+    assert!(updated.updated_at > updated.created_at);
+
+    let res_clear = profile_router.clone().oneshot(patch_profile_request(&account, account.id, json!({
+        "region": null
+    }))).await.unwrap();
+    let cleared: ProfileQueryResult = serde_json::from_slice(
+        &axum::body::to_bytes(res_clear.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert_eq!(cleared.full_name, "Renamed Person");
+    assert!(cleared.region.is_none());
+}
+
+#[tokio::test]
+async fn test_update_profile_is_owner_only_and_validated() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let owner = create_test_account(state.clone()).await;
+    let other = create_test_account(state.clone()).await;
+    let profile_router = get_profile_router(state);
+
+    let res_forbidden = profile_router.clone().oneshot(patch_profile_request(&other, owner.id, json!({
+        "full_name": "Hijacked"
+    }))).await.unwrap();
+    assert_eq!(res_forbidden.status(), StatusCode::FORBIDDEN);
+
+    let res_too_long = profile_router.clone().oneshot(patch_profile_request(&owner, owner.id, json!({
+        "description": "x".repeat(251)
+    }))).await.unwrap();
+    assert_eq!(res_too_long.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let res_bad_url = profile_router.oneshot(patch_profile_request(&owner, owner.id, json!({
+        "main_url": "javascript:alert(1)"
+    }))).await.unwrap();
+    assert_eq!(res_bad_url.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_get_profile_by_handle_ignores_case() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let account = create_test_account(state.clone()).await;
+    let profile_router = get_profile_router(state);
+
+    let req_profile = Request::builder()
+        .uri(format!("/profile/by-handle/{}", account.user_name.to_uppercase()))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_profile = profile_router.clone().oneshot(req_profile).await.unwrap();
+    assert_eq!(res_profile.status(), StatusCode::OK);
+    let profile: ProfileQueryResult = serde_json::from_slice(
+        &axum::body::to_bytes(res_profile.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert_eq!(profile.id, account.id);
+
+    let req_missing = Request::builder()
+        .uri("/profile/by-handle/no_such_handle_here")
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_missing = profile_router.oneshot(req_missing).await.unwrap();
+    assert_eq!(res_missing.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_upload_avatar_and_fetch_sizes() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let account = create_test_account(state.clone()).await;
+    let profile_router = get_profile_router(state);
+
+    let res_upload = profile_router.clone().oneshot(
+        upload_avatar_request(&account, account.id, "image/png", test_png(640, 480))
+    ).await.unwrap();
+    assert_eq!(res_upload.status(), StatusCode::OK);
+    let uploaded: AvatarUploaded = serde_json::from_slice(
+        &axum::body::to_bytes(res_upload.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert_eq!(uploaded.sizes, vec![48, 128, 400]);
+
+    for size in [48, 128, 400] {
+        let req_avatar = Request::builder()
+            .uri(format!("/profile/{}/avatar?size={}", account.id, size))
+            .method("GET")
+            .body(Body::empty())
+            .unwrap();
+        let res_avatar = profile_router.clone().oneshot(req_avatar).await.unwrap();
+        assert_eq!(res_avatar.status(), StatusCode::OK);
+        assert_eq!(res_avatar.headers()[header::CONTENT_TYPE], "image/png");
+        assert!(res_avatar.headers().contains_key(header::CACHE_CONTROL));
+        let bytes = axum::body::to_bytes(res_avatar.into_body(), usize::MAX).await.unwrap();
+        let image = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (size, size));
+    }
+
+    let req_bad_size = Request::builder()
+        .uri(format!("/profile/{}/avatar?size=77", account.id))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_bad_size = profile_router.oneshot(req_bad_size).await.unwrap();
+    assert_eq!(res_bad_size.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_upload_avatar_is_owner_only_and_validated() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let owner = create_test_account(state.clone()).await;
+    let other = create_test_account(state.clone()).await;
+    let profile_router = get_profile_router(state);
+
+    let res_forbidden = profile_router.clone().oneshot(
+        upload_avatar_request(&other, owner.id, "image/png", test_png(64, 64))
+    ).await.unwrap();
+    assert_eq!(res_forbidden.status(), StatusCode::FORBIDDEN);
+
+    let res_not_image = profile_router.clone().oneshot(
+        upload_avatar_request(&owner, owner.id, "image/png", b"definitely not an image".to_vec())
+    ).await.unwrap();
+    assert_eq!(res_not_image.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let req_missing = Request::builder()
+        .uri(format!("/profile/{}/avatar", owner.id))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_missing = profile_router.oneshot(req_missing).await.unwrap();
+    assert_eq!(res_missing.status(), StatusCode::NOT_FOUND);
+}
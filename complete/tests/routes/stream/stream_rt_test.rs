@@ -0,0 +1,182 @@
+use std::future::IntoFuture;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use axum::extract::State;
+use axum::http::StatusCode;
+use complete::lib::app_state::AppState;
+use complete::lib::events::{spawn_event_pump, StreamEvent};
+use complete::repository::event::event_models::EventKind;
+use complete::routes::like::like_rt::get_like_routes;
+use complete::routes::stream::stream_rt::get_stream_routes;
+use complete::test_utils::fixtures::{bearer, create_test_account, create_test_message, follow_test_account, init_test_logging, TestAccount};
+use axum::body::{Body, BodyDataStream};
+use axum::http::Request;
+use futures_util::StreamExt;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tower::ServiceExt;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+async fn serve_streams(state: State<Arc<AppState>>) -> SocketAddr {
+    spawn_event_pump(Arc::clone(&state.0));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(axum::serve(listener, get_stream_routes(state)).into_future());
+    addr
+}
+
+async fn connect(addr: SocketAddr, account: &TestAccount, query: &str) -> Socket {
+    let mut request = format!("ws://{}/ws{}", addr, query).into_client_request().unwrap();
+    request.headers_mut().insert("Authorization", bearer(account).parse().unwrap());
+    connect_async(request).await.unwrap().0
+}
+
+async fn next_event(socket: &mut Socket) -> StreamEvent {
+    loop {
+        let frame = tokio::time::timeout(Duration::from_secs(5), socket.next()).await.unwrap().unwrap().unwrap();
+        if let Message::Text(text) = frame {
+            return serde_json::from_str(&text).unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_websocket_pushes_and_resumes_events() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let addr = serve_streams(state.clone()).await;
+    let reader = create_test_account(state.clone()).await;
+    let author = create_test_account(state.clone()).await;
+    let liker = create_test_account(state.clone()).await;
+    follow_test_account(state.clone(), &reader, author.id).await;
+
+    let mut socket = connect(addr, &reader, "").await;
+    let message_id = create_test_message(state.clone(), &author, "live", None).await;
+    let message_event = next_event(&mut socket).await;
+    assert_eq!(message_event.kind, EventKind::Message);
+    assert_eq!(message_event.data["id"], message_id);
+    assert_eq!(message_event.data["body"], "live");
+
+    let req_like = Request::builder()
+        .uri(format!("/message/{}/like", message_id))
+        .method("PUT")
+        .header("Authorization", bearer(&liker))
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(get_like_routes(state.clone()).oneshot(req_like).await.unwrap().status(), StatusCode::OK);
+    let likes_event = next_event(&mut socket).await;
+    assert_eq!(likes_event.kind, EventKind::Likes);
+    assert_eq!((likes_event.data["message_id"].as_i64(), likes_event.data["likes"].as_i64()), (Some(message_id), Some(1)));
+
+    follow_test_account(state.clone(), &author, reader.id).await;
+    let notification_event = next_event(&mut socket).await;
+    assert_eq!(notification_event.kind, EventKind::Notification);
+    assert_eq!(notification_event.data["notification"]["kind"], "follow");
+    assert_eq!(notification_event.data["unread_count"], 1);
+    socket.close(None).await.unwrap();
+
+    // reconnecting after the first event replays the two that followed it
+    let mut resumed = connect(addr, &reader, &format!("?last_event_id={}", message_event.id)).await;
+    assert_eq!(next_event(&mut resumed).await.id, likes_event.id);
+    assert_eq!(next_event(&mut resumed).await.id, notification_event.id);
+}
+
+#[tokio::test]
+async fn test_websocket_requires_auth() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let addr = serve_streams(state.clone()).await;
+    let reader = create_test_account(state).await;
+
+    match connect_async(format!("ws://{}/ws", addr)).await {
+        Err(WsError::Http(response)) => assert_eq!(response.status(), StatusCode::UNAUTHORIZED),
+        _ => panic!("expected the handshake to be refused")
+    }
+    let (mut socket, _) = connect_async(format!("ws://{}/ws?access_token={}", addr, reader.access_token)).await.unwrap();
+    socket.close(None).await.unwrap();
+}
+
+/// Reads the event stream until an event with an `id:` field arrives and returns its fields.
+async fn next_sse_event(body: &mut BodyDataStream, buffer: &mut String) -> Vec<(String, String)> {
+    loop {
+        while let Some(end) = buffer.find("\n\n") {
+            let block = buffer[..end].to_string();
+            buffer.replace_range(..end + 2, "");
+            let fields = block
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .map(|(name, value)| (name.to_string(), value.trim_start().to_string()))
+                .collect::<Vec<(String, String)>>();
+            if fields.iter().any(|(name, _)| name == "id") {
+                return fields;
+            }
+        }
+        let chunk = tokio::time::timeout(Duration::from_secs(5), body.next()).await.unwrap().unwrap().unwrap();
+        buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+}
+
+fn sse_field<'a>(fields: &'a [(String, String)], name: &str) -> &'a str {
+    &fields.iter().find(|(field, _)| field == name).unwrap().1
+}
+
+#[tokio::test]
+async fn test_sse_streams_and_replays_events() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    spawn_event_pump(Arc::clone(&state.0));
+    let reader = create_test_account(state.clone()).await;
+    let author = create_test_account(state.clone()).await;
+    follow_test_account(state.clone(), &reader, author.id).await;
+    let stream_router = get_stream_routes(state.clone());
+
+    let req_home = Request::builder()
+        .uri("/stream/home")
+        .method("GET")
+        .header("Authorization", bearer(&reader))
+        .body(Body::empty())
+        .unwrap();
+    let res_home = stream_router.clone().oneshot(req_home).await.unwrap();
+    assert_eq!(res_home.status(), StatusCode::OK);
+    assert_eq!(res_home.headers()["content-type"], "text/event-stream");
+    let mut home = res_home.into_body().into_data_stream();
+    let mut home_buffer = String::new();
+
+    let first_id = create_test_message(state.clone(), &author, "first", None).await;
+    let second_id = create_test_message(state.clone(), &author, "second", None).await;
+    let first = next_sse_event(&mut home, &mut home_buffer).await;
+    assert_eq!(sse_field(&first, "event"), "message");
+    let first_event: StreamEvent = serde_json::from_str(sse_field(&first, "data")).unwrap();
+    assert_eq!(first_event.id.to_string(), sse_field(&first, "id"));
+    assert_eq!(first_event.data["id"], first_id);
+    let second = next_sse_event(&mut home, &mut home_buffer).await;
+    let second_event: StreamEvent = serde_json::from_str(sse_field(&second, "data")).unwrap();
+    assert_eq!(second_event.data["id"], second_id);
+
+    // the notification stream leaves out messages, and Last-Event-ID replays from there
+    follow_test_account(state.clone(), &author, reader.id).await;
+    let req_notifications = Request::builder()
+        .uri(format!("/stream/notifications?access_token={}", reader.access_token))
+        .method("GET")
+        .header("Last-Event-ID", first_event.id.to_string())
+        .body(Body::empty())
+        .unwrap();
+    let res_notifications = stream_router.clone().oneshot(req_notifications).await.unwrap();
+    assert_eq!(res_notifications.status(), StatusCode::OK);
+    let mut notifications = res_notifications.into_body().into_data_stream();
+    let replayed = next_sse_event(&mut notifications, &mut String::new()).await;
+    assert_eq!(sse_field(&replayed, "event"), "notification");
+    let replayed_event: StreamEvent = serde_json::from_str(sse_field(&replayed, "data")).unwrap();
+    assert_eq!(replayed_event.data["notification"]["kind"], "follow");
+
+    let req_anonymous = Request::builder()
+        .uri("/stream/notifications")
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(stream_router.oneshot(req_anonymous).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,271 @@
+use std::sync::Arc;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use chrono::Utc;
+use complete::lib::app_state::AppState;
+use complete::repository::message::message_models::MessageWithFollowingAndBroadcastQueryResult;
+use complete::repository::repo::{EntityId, Repository};
+use complete::routes::lib::pagination::Page;
+use complete::routes::message::message_rt::get_message_routes;
+use complete::routes::timeline::timeline_rt::get_timeline_routes;
+use complete::test_utils::fixtures::{bearer, create_test_account, create_test_message, follow_test_account, init_test_logging, multipart_body, test_png, TestAccount};
+use serde_json::json;
+use tower::ServiceExt;
+
+async fn get_timeline_page(router: &Router, account: &TestAccount, query: &str) -> Page<MessageWithFollowingAndBroadcastQueryResult> {
+    let req_timeline = Request::builder()
+        .uri(format!("/timeline{}", query))
+        .method("GET")
+        .header("Authorization", bearer(account))
+        .body(Body::empty())
+        .unwrap();
+    let res_timeline = router.clone().oneshot(req_timeline).await.unwrap();
+    assert_eq!(res_timeline.status(), StatusCode::OK);
+    serde_json::from_slice(
+        &axum::body::to_bytes(res_timeline.into_body(), usize::MAX).await.unwrap()
+    ).unwrap()
+}
+
+#[tokio::test]
+async fn test_timeline_pages_through_tied_timestamps() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let author = create_test_account(state.clone()).await;
+    let reader = create_test_account(state.clone()).await;
+    follow_test_account(state.clone(), &reader, author.id).await;
+
+    let mut message_ids = vec![];
+    for i in 0..5 {
+        message_ids.push(create_test_message(state.clone(), &author, &format!("message {}", i), None).await);
+    }
+    sqlx::query("update message set updated_at = $1 where user_id = $2")
+        .bind(Utc::now())
+        .bind(author.id)
+        .execute(state.repo.get_pool())
+        .await
+        .unwrap();
+
+    let timeline_router = get_timeline_routes(state.clone());
+    let mut paged_ids = vec![];
+    let mut query = "?page_size=2".to_string();
+    loop {
+        let page = get_timeline_page(&timeline_router, &reader, &query).await;
+        assert!(page.items.len() <= 2);
+        paged_ids.extend(page.items.iter().map(|m| m.id));
+        match page.next_cursor {
+            Some(cursor) => query = format!("?page_size=2&cursor={}", cursor),
+            None => break
+        }
+    }
+
+    message_ids.reverse();
+    assert_eq!(paged_ids, message_ids);
+}
+
+#[tokio::test]
+async fn test_edited_message_keeps_its_place_in_timelines() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let author = create_test_account(state.clone()).await;
+    let reader = create_test_account(state.clone()).await;
+    follow_test_account(state.clone(), &reader, author.id).await;
+    for i in 0..5 {
+        create_test_message(state.clone(), &author, &format!("message {}", i), None).await;
+    }
+
+    let timeline_router = get_timeline_routes(state.clone());
+    let first_page = get_timeline_page(&timeline_router, &reader, "?page_size=2").await;
+    let second_query = format!("?page_size=2&cursor={}", first_page.next_cursor.as_ref().unwrap());
+    let second_page = get_timeline_page(&timeline_router, &reader, &second_query).await;
+    let author_ids = get_author_message_ids(&timeline_router, author.id, "").await;
+
+    let req_edit = Request::builder()
+        .uri(format!("/message/{}", second_page.items[0].id))
+        .method("PATCH")
+        .header("Content-Type", "application/json")
+        .header("Authorization", bearer(&author))
+        .body(Body::from(json!({ "body": "message 2, edited" }).to_string()))
+        .unwrap();
+    let res_edit = get_message_routes(state.clone()).oneshot(req_edit).await.unwrap();
+    assert_eq!(res_edit.status(), StatusCode::OK);
+
+    let ids = |page: &Page<MessageWithFollowingAndBroadcastQueryResult>| page.items.iter().map(|m| m.id).collect::<Vec<i64>>();
+    assert_eq!(ids(&get_timeline_page(&timeline_router, &reader, "?page_size=2").await), ids(&first_page));
+    // a cursor handed out before the edit still continues at the same message
+    let edited_page = get_timeline_page(&timeline_router, &reader, &second_query).await;
+    assert_eq!(ids(&edited_page), ids(&second_page));
+    assert_eq!(edited_page.items[0].body.as_deref(), Some("message 2, edited"));
+    assert_eq!(get_author_message_ids(&timeline_router, author.id, "").await, author_ids);
+}
+
+#[tokio::test]
+async fn test_timeline_includes_broadcast_messages() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let original_author = create_test_account(state.clone()).await;
+    let broadcaster = create_test_account(state.clone()).await;
+    let reader = create_test_account(state.clone()).await;
+    follow_test_account(state.clone(), &reader, broadcaster.id).await;
+
+    let original_id = create_test_message(state.clone(), &original_author, "original message", None).await;
+    let broadcast_id = create_test_message(state.clone(), &broadcaster, "worth reading", Some(original_id)).await;
+
+    let page = get_timeline_page(&get_timeline_routes(state), &reader, "").await;
+    assert_eq!(page.items.len(), 1);
+    let message = &page.items[0];
+    assert_eq!(message.id, broadcast_id);
+    assert_eq!(message.message_broadcast_id, Some(original_id));
+    assert_eq!(message.message_broadcast_body.as_deref(), Some("original message"));
+    assert_eq!(message.message_broadcast_user_id, Some(original_author.id));
+}
+
+#[tokio::test]
+async fn test_timeline_requires_auth() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+
+    let req_timeline = Request::builder()
+        .uri("/timeline")
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_timeline = get_timeline_routes(state).oneshot(req_timeline).await.unwrap();
+    assert_eq!(res_timeline.status(), StatusCode::UNAUTHORIZED);
+}
+
+async fn get_author_message_ids(router: &Router, author_id: i64, query: &str) -> Vec<i64> {
+    let req_messages = Request::builder()
+        .uri(format!("/profile/{}/messages{}", author_id, query))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_messages = router.clone().oneshot(req_messages).await.unwrap();
+    assert_eq!(res_messages.status(), StatusCode::OK);
+    let page: Page<MessageWithFollowingAndBroadcastQueryResult> = serde_json::from_slice(
+        &axum::body::to_bytes(res_messages.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    page.items.iter().map(|m| m.id).collect()
+}
+
+#[tokio::test]
+async fn test_author_timeline_filters() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let author = create_test_account(state.clone()).await;
+    let other = create_test_account(state.clone()).await;
+    let message_router = get_message_routes(state.clone());
+
+    let other_message_id = create_test_message(state.clone(), &other, "someone else", None).await;
+    let plain_id = create_test_message(state.clone(), &author, "plain", None).await;
+    let broadcast_id = create_test_message(state.clone(), &author, "", Some(other_message_id)).await;
+    let req_reply = Request::builder()
+        .uri(format!("/message/{}/reply", other_message_id))
+        .method("POST")
+        .header("Content-Type", "application/json")
+        .header("Authorization", bearer(&author))
+        .body(Body::from(json!({ "body": "a reply" }).to_string()))
+        .unwrap();
+    let reply: EntityId = serde_json::from_slice(
+        &axum::body::to_bytes(message_router.clone().oneshot(req_reply).await.unwrap().into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    let png = test_png(4, 4);
+    let (content_type, body) = multipart_body(&[("body", None, b"with media"), ("attachment", Some("image/png"), &png)]);
+    let req_media = Request::builder()
+        .uri("/message")
+        .method("POST")
+        .header("Content-Type", content_type)
+        .header("Authorization", bearer(&author))
+        .body(Body::from(body))
+        .unwrap();
+    let media_message: EntityId = serde_json::from_slice(
+        &axum::body::to_bytes(message_router.oneshot(req_media).await.unwrap().into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+
+    let timeline_router = get_timeline_routes(state);
+    assert_eq!(
+        get_author_message_ids(&timeline_router, author.id, "").await,
+        vec![media_message.id, reply.id, broadcast_id, plain_id]
+    );
+    assert_eq!(
+        get_author_message_ids(&timeline_router, author.id, "?include_replies=false").await,
+        vec![media_message.id, broadcast_id, plain_id]
+    );
+    assert_eq!(
+        get_author_message_ids(&timeline_router, author.id, "?include_broadcasts=false&include_replies=false").await,
+        vec![media_message.id, plain_id]
+    );
+    assert_eq!(
+        get_author_message_ids(&timeline_router, author.id, "?only_media=true").await,
+        vec![media_message.id]
+    );
+    assert_eq!(
+        get_author_message_ids(&timeline_router, author.id, "?page_size=2").await,
+        vec![media_message.id, reply.id]
+    );
+
+    let req_missing = Request::builder()
+        .uri(format!("/profile/{}/messages", i64::MAX))
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_missing = timeline_router.oneshot(req_missing).await.unwrap();
+    assert_eq!(res_missing.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_mentions_timeline() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let author = create_test_account(state.clone()).await;
+    let mentioned = create_test_account(state.clone()).await;
+
+    let body = format!("hi @{} and @{}_missing", mentioned.user_name.to_uppercase(), mentioned.user_name);
+    let mention_id = create_test_message(state.clone(), &author, &body, None).await;
+    let edited_id = create_test_message(state.clone(), &author, &format!("@{}", mentioned.user_name), None).await;
+    create_test_message(state.clone(), &mentioned, &format!("talking to myself @{}", mentioned.user_name), None).await;
+    let req_edit = Request::builder()
+        .uri(format!("/message/{}", edited_id))
+        .method("PATCH")
+        .header("Content-Type", "application/json")
+        .header("Authorization", bearer(&author))
+        .body(Body::from(json!({ "body": "never mind" }).to_string()))
+        .unwrap();
+    assert_eq!(get_message_routes(state.clone()).oneshot(req_edit).await.unwrap().status(), StatusCode::OK);
+
+    let req_mentions = Request::builder()
+        .uri("/mentions")
+        .method("GET")
+        .header("Authorization", bearer(&mentioned))
+        .body(Body::empty())
+        .unwrap();
+    let res_mentions = get_timeline_routes(state.clone()).oneshot(req_mentions).await.unwrap();
+    assert_eq!(res_mentions.status(), StatusCode::OK);
+    let page: Page<MessageWithFollowingAndBroadcastQueryResult> = serde_json::from_slice(
+        &axum::body::to_bytes(res_mentions.into_body(), usize::MAX).await.unwrap()
+    ).unwrap();
+    assert_eq!(page.items.iter().map(|m| m.id).collect::<Vec<i64>>(), vec![mention_id]);
+    let mentions = &page.items[0].mentions.0;
+    assert_eq!(mentions.len(), 1);
+    let end = 4 + mentioned.user_name.chars().count() as i32;
+    assert_eq!((mentions[0].start, mentions[0].end, mentions[0].profile_id), (3, end, mentioned.id));
+
+    // each mention by someone else notified the profile, even if it was edited away since
+    let notified_ids = sqlx::query_scalar::<_, Option<i64>>(
+        "select message_id from notification where recipient_id = $1 and kind = 'mention' order by message_id"
+    )
+    .bind(mentioned.id)
+    .fetch_all(state.repo.get_pool())
+    .await
+    .unwrap();
+    assert_eq!(notified_ids, vec![Some(mention_id), Some(edited_id)]);
+
+    let req_anonymous = Request::builder()
+        .uri("/mentions")
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+    let res_anonymous = get_timeline_routes(state).oneshot(req_anonymous).await.unwrap();
+    assert_eq!(res_anonymous.status(), StatusCode::UNAUTHORIZED);
+}
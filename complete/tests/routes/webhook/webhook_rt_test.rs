@@ -0,0 +1,304 @@
+use std::collections::HashSet;
+use std::future::IntoFuture;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::{HeaderMap, Request, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use complete::controllers::webhook::webhook_models::CreatedWebhook;
+use complete::lib::app_state::AppState;
+use complete::lib::webhook_delivery::{deliver_due_webhooks, sign_webhook_payload, webhook_client};
+use complete::lib::webhook_target::PublicResolver;
+use complete::repository::like::like_repo::LikeRepo;
+use complete::repository::repo::Repository;
+use complete::repository::webhook::webhook_models::{WebhookDeliveryQueryResult, WebhookDeliveryStatus, WebhookQueryResult};
+use complete::routes::lib::pagination::Page;
+use complete::routes::webhook::webhook_rt::get_webhook_routes;
+use complete::test_utils::fixtures::{bearer, create_test_account, create_test_message, follow_test_account, init_test_logging, TestAccount};
+use serde_json::{json, Value};
+use reqwest::dns::Resolve;
+use tower::ServiceExt;
+
+const RETRY_BASE: Duration = Duration::from_millis(500);
+
+struct ReceivedHook {
+    headers: HeaderMap,
+    body: Bytes
+}
+
+/// Stands in for the services receiving webhooks: `/flaky` fails the first try of each
+/// delivery and `/down` fails them all.
+#[derive(Clone, Default)]
+struct Receiver {
+    received: Arc<Mutex<Vec<ReceivedHook>>>,
+    failed_once: Arc<Mutex<HashSet<String>>>
+}
+
+impl Receiver {
+    fn record(&self, headers: HeaderMap, body: Bytes) -> String {
+        let delivery_id = headers["x-webhook-delivery"].to_str().unwrap().to_string();
+        self.received.lock().unwrap().push(ReceivedHook { headers, body });
+        delivery_id
+    }
+
+    fn received_matching(&self, matches: impl Fn(&Value) -> bool) -> Vec<(HeaderMap, Bytes)> {
+        self.received.lock().unwrap()
+            .iter()
+            .filter(|hook| matches(&serde_json::from_slice(&hook.body).unwrap()))
+            .map(|hook| (hook.headers.clone(), hook.body.clone()))
+            .collect()
+    }
+}
+
+async fn serve_receiver() -> (SocketAddr, Receiver) {
+    let receiver = Receiver::default();
+    let router = Router::new()
+        .route("/flaky", post(|State(receiver): State<Receiver>, headers: HeaderMap, body: Bytes| async move {
+            let delivery_id = receiver.record(headers, body);
+            if receiver.failed_once.lock().unwrap().insert(delivery_id) {
+                StatusCode::INTERNAL_SERVER_ERROR
+            } else {
+                StatusCode::OK
+            }
+        }))
+        .route("/down", post(|State(receiver): State<Receiver>, headers: HeaderMap, body: Bytes| async move {
+            receiver.record(headers, body);
+            StatusCode::SERVICE_UNAVAILABLE
+        }))
+        .with_state(receiver.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(axum::serve(listener, router).into_future());
+    (addr, receiver)
+}
+
+async fn send(router: &Router, account: &TestAccount, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Bytes) {
+    let req = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("Content-Type", "application/json")
+        .header("Authorization", bearer(account))
+        .body(body.map(|body| Body::from(body.to_string())).unwrap_or_else(Body::empty))
+        .unwrap();
+    let res = router.clone().oneshot(req).await.unwrap();
+    (res.status(), axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap())
+}
+
+async fn create_webhook(router: &Router, account: &TestAccount, url: String, event_types: &[&str]) -> CreatedWebhook {
+    let (status, body) = send(router, account, "POST", "/webhooks", Some(json!({ "url": url, "event_types": event_types }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn get_deliveries(router: &Router, account: &TestAccount, webhook_id: i64) -> Vec<WebhookDeliveryQueryResult> {
+    let (status, body) = send(router, account, "GET", &format!("/webhooks/{}/deliveries?page_size=100", webhook_id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    serde_json::from_slice::<Page<WebhookDeliveryQueryResult>>(&body).unwrap().items
+}
+
+async fn get_webhook(router: &Router, account: &TestAccount, webhook_id: i64) -> WebhookQueryResult {
+    let (_, body) = send(router, account, "GET", "/webhooks", None).await;
+    serde_json::from_slice::<Vec<WebhookQueryResult>>(&body).unwrap()
+        .into_iter()
+        .find(|webhook| webhook.id == webhook_id)
+        .unwrap()
+}
+
+async fn test_state(max_attempts: i32, disable_after_failures: i32) -> Arc<AppState> {
+    let mut app_state = AppState::init().await;
+    app_state.webhooks.retry_base = RETRY_BASE;
+    app_state.webhooks.max_attempts = max_attempts;
+    app_state.webhooks.disable_after_failures = disable_after_failures;
+    // the receiver listens on loopback
+    app_state.webhooks.allow_private_targets = true;
+    Arc::new(app_state)
+}
+
+#[tokio::test]
+async fn test_create_webhook_validates_input() {
+    init_test_logging();
+    let state = State(Arc::new(AppState::init().await));
+    let router = get_webhook_routes(state.clone());
+    let owner = create_test_account(state.clone()).await;
+    let other = create_test_account(state.clone()).await;
+
+    for body in [
+        json!({ "url": "ftp://example.com/hook", "event_types": ["message_created"] }),
+        json!({ "url": "https://example.com/hook", "event_types": [] }),
+        json!({ "url": "https://example.com/hook", "event_types": ["message_deleted"] }),
+        json!({ "url": "http://127.0.0.1:8080/hook", "event_types": ["message_created"] }),
+        json!({ "url": "http://10.1.2.3/hook", "event_types": ["message_created"] }),
+        json!({ "url": "http://169.254.169.254/latest/meta-data", "event_types": ["message_created"] }),
+        json!({ "url": "http://[::1]/hook", "event_types": ["message_created"] }),
+        json!({ "url": "http://[::ffff:192.168.0.1]/hook", "event_types": ["message_created"] }),
+        json!({ "url": "http://0x7f000001/hook", "event_types": ["message_created"] }),
+        json!({ "url": "http://localhost:4000/hook", "event_types": ["message_created"] })
+    ] {
+        let (status, _) = send(&router, &owner, "POST", "/webhooks", Some(body)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let created = create_webhook(&router, &owner, "https://example.com/hook".to_string(), &["like_added", "message_created", "like_added"]).await;
+    assert_eq!(created.webhook.event_types, vec!["like_added", "message_created"]);
+    assert!(!created.secret.is_empty());
+
+    let (status, _) = send(&router, &other, "GET", &format!("/webhooks/{}/deliveries", created.webhook.id), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(&router, &other, "DELETE", &format!("/webhooks/{}", created.webhook.id), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(&router, &owner, "DELETE", &format!("/webhooks/{}", created.webhook.id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = send(&router, &owner, "GET", "/webhooks", None).await;
+    assert!(serde_json::from_slice::<Vec<WebhookQueryResult>>(&body).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_webhook_deliveries_are_signed_retried_and_disabled() {
+    init_test_logging();
+    let (addr, receiver) = serve_receiver().await;
+    let app_state = test_state(2, 1000).await;
+    let state = State(Arc::clone(&app_state));
+    let router = get_webhook_routes(state.clone());
+    let client = webhook_client(&app_state.webhooks);
+    let owner = create_test_account(state.clone()).await;
+    let follower = create_test_account(state.clone()).await;
+
+    // a failed first attempt is retried after the backoff and then succeeds
+    let flaky = create_webhook(&router, &owner, format!("http://{}/flaky", addr), &["follow_created"]).await;
+    follow_test_account(state.clone(), &follower, owner.id).await;
+    let is_follow = |payload: &Value| payload["follower_id"] == follower.id && payload["following_id"] == owner.id;
+    deliver_due_webhooks(&app_state, &client).await.unwrap();
+
+    let received = receiver.received_matching(is_follow);
+    assert_eq!(received.len(), 1);
+    let (headers, body) = &received[0];
+    assert_eq!(headers["x-webhook-event"], "follow_created");
+    let timestamp = headers["x-webhook-timestamp"].to_str().unwrap().parse::<i64>().unwrap();
+    assert_eq!(
+        headers["x-webhook-signature"].to_str().unwrap(),
+        format!("sha256={}", sign_webhook_payload(&flaky.secret, timestamp, body))
+    );
+    assert_ne!(sign_webhook_payload("some other secret", timestamp, body), sign_webhook_payload(&flaky.secret, timestamp, body));
+
+    let delivery = get_deliveries(&router, &owner, flaky.webhook.id).await
+        .into_iter()
+        .find(|d| is_follow(&d.payload.0))
+        .unwrap();
+    assert_eq!(delivery.status, WebhookDeliveryStatus::Pending);
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.attempt_log[0].response_status, Some(500));
+
+    // not due yet
+    deliver_due_webhooks(&app_state, &client).await.unwrap();
+    assert_eq!(receiver.received_matching(is_follow).len(), 1);
+    tokio::time::sleep(RETRY_BASE * 2).await;
+    deliver_due_webhooks(&app_state, &client).await.unwrap();
+    assert_eq!(receiver.received_matching(is_follow).len(), 2);
+
+    let delivery = get_deliveries(&router, &owner, flaky.webhook.id).await
+        .into_iter()
+        .find(|d| d.id == delivery.id)
+        .unwrap();
+    assert_eq!(delivery.status, WebhookDeliveryStatus::Delivered);
+    assert_eq!(delivery.attempts, 2);
+    assert_eq!(delivery.attempt_log.len(), 2);
+    assert_eq!(delivery.attempt_log[1].response_status, Some(200));
+    assert!(delivery.delivered_at.is_some());
+
+    // a delivery that keeps failing gives up after the maximum attempts
+    let down = create_webhook(&router, &owner, format!("http://{}/down", addr), &["message_created"]).await;
+    let message_id = create_test_message(state.clone(), &owner, "nobody is listening", None).await;
+    let is_message = |payload: &Value| payload["message_id"] == message_id;
+    for _ in 0..2 {
+        deliver_due_webhooks(&app_state, &client).await.unwrap();
+        tokio::time::sleep(RETRY_BASE * 2).await;
+    }
+    let delivery = get_deliveries(&router, &owner, down.webhook.id).await
+        .into_iter()
+        .find(|d| is_message(&d.payload.0))
+        .unwrap();
+    assert_eq!(delivery.status, WebhookDeliveryStatus::Failed);
+    assert_eq!(delivery.attempts, 2);
+    assert_eq!(receiver.received_matching(is_message).len(), 2);
+
+    // and an endpoint that keeps failing is disabled
+    let strict_state = test_state(2, 3).await;
+    let mut disabled = false;
+    for _ in 0..5 {
+        create_test_message(state.clone(), &owner, "still nobody", None).await;
+        deliver_due_webhooks(&strict_state, &client).await.unwrap();
+        if get_webhook(&router, &owner, down.webhook.id).await.disabled_at.is_some() {
+            disabled = true;
+            break;
+        }
+    }
+    assert!(disabled);
+    let message_id = create_test_message(state.clone(), &owner, "after disabling", None).await;
+    deliver_due_webhooks(&strict_state, &client).await.unwrap();
+    assert!(receiver.received_matching(|payload| payload["message_id"] == message_id).is_empty());
+
+    let (status, body) = send(&router, &owner, "POST", &format!("/webhooks/{}/enable", down.webhook.id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let enabled: WebhookQueryResult = serde_json::from_slice(&body).unwrap();
+    assert!(enabled.disabled_at.is_none());
+    assert_eq!(enabled.consecutive_failures, 0);
+
+    // private targets are checked again on delivery, not only when a webhook is created
+    let mut public_only_state = AppState::init().await;
+    public_only_state.webhooks.allow_private_targets = false;
+    let message_id = create_test_message(state.clone(), &owner, "for internal eyes only", None).await;
+    deliver_due_webhooks(&public_only_state, &webhook_client(&public_only_state.webhooks)).await.unwrap();
+    assert!(receiver.received_matching(|payload| payload["message_id"] == message_id).is_empty());
+    let delivery = get_deliveries(&router, &owner, down.webhook.id).await
+        .into_iter()
+        .find(|d| d.payload.0["message_id"] == message_id)
+        .unwrap();
+    assert!(delivery.attempt_log[0].response_status.is_none());
+    assert!(delivery.attempt_log[0].error.as_ref().unwrap().contains("is not a public address"));
+    // and names are refused when they resolve to private addresses
+    assert!(PublicResolver.resolve("localhost".parse().unwrap()).await.is_err());
+
+    for webhook_id in [flaky.webhook.id, down.webhook.id] {
+        let (status, _) = send(&router, &owner, "DELETE", &format!("/webhooks/{}", webhook_id), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn test_webhooks_only_receive_events_involving_their_owner() {
+    init_test_logging();
+    let (addr, _) = serve_receiver().await;
+    let state = State(test_state(1, 1000).await);
+    let pool = state.repo.get_pool();
+    let router = get_webhook_routes(state.clone());
+    let owner = create_test_account(state.clone()).await;
+    let fan = create_test_account(state.clone()).await;
+    let stranger = create_test_account(state.clone()).await;
+    let webhook = create_webhook(
+        &router,
+        &owner,
+        format!("http://{}/flaky", addr),
+        &["message_created", "follow_created", "like_added"]
+    ).await;
+
+    let own_message_id = create_test_message(state.clone(), &owner, "mine", None).await;
+    follow_test_account(state.clone(), &fan, owner.id).await;
+    state.repo.insert_like(pool, fan.id, own_message_id).await.unwrap();
+    // none of which involves the owner
+    let fan_message_id = create_test_message(state.clone(), &fan, "not the owner's", None).await;
+    follow_test_account(state.clone(), &stranger, fan.id).await;
+    state.repo.insert_like(pool, stranger.id, fan_message_id).await.unwrap();
+
+    let mut delivered = get_deliveries(&router, &owner, webhook.webhook.id).await
+        .into_iter()
+        .map(|d| d.event_type)
+        .collect::<Vec<String>>();
+    delivered.sort();
+    assert_eq!(delivered, vec!["follow_created", "like_added", "message_created"]);
+
+    let (status, _) = send(&router, &owner, "DELETE", &format!("/webhooks/{}", webhook.webhook.id), None).await;
+    assert_eq!(status, StatusCode::OK);
+}